@@ -8,4 +8,10 @@ fn main() {
     println!("cargo:rerun-if-changed={}", MAN_PI);
 
     setup_manpages(MAN_PI_CONTENT, "pi");
+
+    // used by `pi self-update` to pick the binary built for this platform
+    println!(
+        "cargo:rustc-env=PI_TARGET={}",
+        std::env::var("TARGET").unwrap()
+    );
 }
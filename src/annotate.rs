@@ -0,0 +1,199 @@
+//! `pi render --annotate`, showing what each placeholder of a template file
+//! was substituted with, and where that value comes from.
+
+use std::collections::BTreeMap;
+
+use rustache::{Data, HashBuilder};
+
+use crate::util::{data_summary, KeySource};
+
+/// What a tag of a template does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagKind {
+    /// `{{key}}`, `{{{key}}}` or `{{&key}}`, replaced by the value of the key
+    Variable,
+    /// `{{#key}}`, rendered for a truthy key or each item of a list
+    Section,
+    /// `{{^key}}`, rendered for a falsy key or an empty list
+    InvertedSection,
+}
+
+/// A tag found in a template, see [`tags`].
+#[derive(Debug)]
+pub struct Tag {
+    /// Line and column of the tag, from 1
+    pub line: usize,
+    pub column: usize,
+    /// The tag as written, delimiters included
+    pub text: String,
+    pub key: String,
+    pub kind: TagKind,
+    /// Innermost section the tag is in, if any
+    pub section: Option<String>,
+}
+
+/// Returns the variable and section tags of `template`, in order.
+///
+/// Comments, partials and closing tags are skipped, and templates changing
+/// their delimiters are only read up to the change.
+pub fn tags(template: &str) -> Vec<Tag> {
+    let mut tags = Vec::new();
+
+    // sections opened around the current position
+    let mut sections: Vec<String> = Vec::new();
+
+    let mut offset = 0;
+
+    while let Some(start) = template[offset..].find("{{") {
+        let start = offset + start;
+
+        let rest = &template[start + 2..];
+
+        let (tag, end) = match rest.strip_prefix('{') {
+            Some(triple) => match triple.find("}}}") {
+                Some(end) => (&triple[..end], 3 + 3),
+                None => break,
+            },
+            None => match rest.find("}}") {
+                Some(end) => (&rest[..end], 2 + 2),
+                None => break,
+            },
+        };
+
+        let text = &template[start..start + tag.len() + end];
+
+        offset = start + text.len();
+
+        let tag = tag.trim();
+
+        let name = |sigil: usize| tag[sigil..].trim().to_string();
+
+        let (key, kind) = match tag.chars().next() {
+            Some('=') => break,
+            Some('!') | Some('>') | None => continue,
+            Some('/') => {
+                sections.pop();
+
+                continue;
+            }
+            Some('#') => (name(1), TagKind::Section),
+            Some('^') => (name(1), TagKind::InvertedSection),
+            Some('&') => (name(1), TagKind::Variable),
+            Some(_) => (name(0), TagKind::Variable),
+        };
+
+        let before = &template[..start];
+
+        tags.push(Tag {
+            line: before.matches('\n').count() + 1,
+            column: before[before.rfind('\n').map_or(0, |line| line + 1)..]
+                .chars()
+                .count()
+                + 1,
+            text: text.to_string(),
+            key: key.clone(),
+            kind,
+            section: sections.last().cloned(),
+        });
+
+        if kind != TagKind::Variable {
+            sections.push(key);
+        }
+    }
+
+    tags
+}
+
+/// Returns the value of `key` in `keys`, following the dots of nested keys.
+fn lookup<'a, 'b>(keys: &'a HashBuilder<'b>, key: &str) -> Option<&'a Data<'b>> {
+    let mut parts = key.split('.');
+
+    let mut value = keys.data.get(parts.next()?)?;
+
+    for part in parts {
+        value = match value {
+            Data::Hash(table) => table.get(part)?,
+            _ => return None,
+        };
+    }
+
+    Some(value)
+}
+
+/// Table of the tags of `template`, with the value each one is substituted
+/// with and its source in `sources`.
+///
+/// Keys without a value at the top level are reported as taken from the
+/// enclosing section, when there is one, as they may be fields of its items.
+pub fn annotate(
+    template: &str,
+    keys: &HashBuilder,
+    sources: &BTreeMap<String, KeySource>,
+) -> String {
+    let rows = tags(template)
+        .into_iter()
+        .map(|tag| {
+            let location = format!("{}:{}", tag.line, tag.column);
+
+            let root = tag.key.split('.').next().unwrap_or_default();
+
+            let (value, source) = match (lookup(keys, &tag.key), &tag.section) {
+                (Some(value), _) => (
+                    data_summary(value),
+                    sources
+                        .get(root)
+                        .map_or_else(|| "-".to_string(), KeySource::to_string),
+                ),
+                (_, Some(section)) => ("-".to_string(), format!("item of section `{}`", section)),
+                (_, None) if tag.kind == TagKind::Variable => {
+                    ("<none>".to_string(), "unresolved".to_string())
+                }
+                // a missing section key skips the section
+                (_, None) => ("<none>".to_string(), "-".to_string()),
+            };
+
+            (location, tag.text, value, source)
+        })
+        .collect::<Vec<_>>();
+
+    if rows.is_empty() {
+        return "No placeholders\n".to_string();
+    }
+
+    let width = |column: fn(&(String, String, String, String)) -> &String, title: &str| {
+        rows.iter()
+            .map(|row| column(row).chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(title.len())
+    };
+
+    let location_width = width(|row| &row.0, "LINE");
+    let tag_width = width(|row| &row.1, "PLACEHOLDER");
+    let value_width = width(|row| &row.2, "VALUE");
+
+    let mut table = format!(
+        "{:location_width$}  {:tag_width$}  {:value_width$}  SOURCE\n",
+        "LINE",
+        "PLACEHOLDER",
+        "VALUE",
+        location_width = location_width,
+        tag_width = tag_width,
+        value_width = value_width
+    );
+
+    for (location, tag, value, source) in rows {
+        table.push_str(&format!(
+            "{:location_width$}  {:tag_width$}  {:value_width$}  {}\n",
+            location,
+            tag,
+            value,
+            source,
+            location_width = location_width,
+            tag_width = tag_width,
+            value_width = value_width
+        ));
+    }
+
+    table
+}
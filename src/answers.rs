@@ -0,0 +1,133 @@
+//! Files of answers, prefilling the keys of a template (`--answers`).
+//!
+//! An answers file is a flat TOML or JSON table mapping keys to their value,
+//! JSON being picked by the `.json` extension. `--save-answers` writes one in
+//! generated projects, so that they can be generated again without prompts.
+//! Single answers can also be given on the command line with `-D KEY=VALUE`.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::constants::ANSWERS_FILENAME;
+use crate::diagnostics::toml_diagnostic;
+
+/// Words marking a key as secret, even if the template doesn't list it in its
+/// `secrets`.
+const SECRET_WORDS: &[&str] = &["password", "secret", "token"];
+
+/// Read the answers in the file at `path`, as overriding keys.
+pub fn read_answers<P: AsRef<Path>>(
+    path: P,
+) -> Result<Vec<(String, String)>, Box<dyn std::error::Error>> {
+    let path = path.as_ref();
+
+    let content = fs::read_to_string(path).map_err(|error| {
+        format!(
+            "Couldn't read answers file {}: {}",
+            path.to_string_lossy(),
+            error
+        )
+    })?;
+
+    if path
+        .extension()
+        .is_some_and(|extension| extension == "json")
+    {
+        let answers: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&content)
+            .map_err(|error| {
+                format!("Invalid answers file {}: {}", path.to_string_lossy(), error)
+            })?;
+
+        return Ok(json_answers(answers));
+    }
+
+    let answers: toml::value::Table = toml::from_str(&content).map_err(|error| {
+        format!(
+            "Invalid answers file {}\n{}",
+            path.to_string_lossy(),
+            toml_diagnostic(path, &content, &error)
+        )
+    })?;
+
+    Ok(toml_answers(answers))
+}
+
+/// Parse an answer given on the command line as `KEY=VALUE`, for `-D`.
+pub fn parse_define(define: &str) -> Result<(String, String), String> {
+    match define.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!("Expected KEY=VALUE, got {:?}", define)),
+    }
+}
+
+/// Answers of a TOML table, as overriding keys.
+pub fn toml_answers(answers: toml::value::Table) -> Vec<(String, String)> {
+    answers
+        .into_iter()
+        .map(|(key, value)| match value {
+            toml::Value::String(value) => (key, value),
+            // the options picked for a multi-select variable
+            toml::Value::Array(values) => (
+                key,
+                values
+                    .iter()
+                    .map(|value| {
+                        value
+                            .as_str()
+                            .map_or_else(|| value.to_string(), str::to_string)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            value => (key, value.to_string()),
+        })
+        .collect()
+}
+
+/// Answers of a JSON object, as overriding keys.
+pub fn json_answers(answers: serde_json::Map<String, serde_json::Value>) -> Vec<(String, String)> {
+    answers
+        .into_iter()
+        .map(|(key, value)| match value {
+            serde_json::Value::String(value) => (key, value),
+            serde_json::Value::Array(values) => (
+                key,
+                values
+                    .iter()
+                    .map(|value| {
+                        value
+                            .as_str()
+                            .map_or_else(|| value.to_string(), str::to_string)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            value => (key, value.to_string()),
+        })
+        .collect()
+}
+
+/// Returns `true` when `key` must not be saved: it is listed in `secrets` or
+/// its name suggests a secret.
+pub fn is_secret(key: &str, secrets: &[String]) -> bool {
+    let lowercase = key.to_lowercase();
+
+    secrets.iter().any(|secret| secret == key)
+        || SECRET_WORDS.iter().any(|word| lowercase.contains(word))
+}
+
+/// Write `answers` in the answers file of the project in `project_dir`.
+pub fn write_answers<P: AsRef<Path>>(
+    project_dir: P,
+    answers: &BTreeMap<String, String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    fs::write(
+        project_dir.as_ref().join(ANSWERS_FILENAME),
+        toml::to_string(answers)?,
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,155 @@
+//! Extracting templates distributed as archives (tarballs and zip files).
+
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+
+use flate2::read::GzDecoder;
+
+use crate::types::is_template;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+/// Extract the archive in `bytes` into `destination`.
+///
+/// The format is detected from the content: gzipped tarballs, plain
+/// tarballs, and zip files are supported.
+pub fn extract<P: AsRef<Path>>(
+    bytes: &[u8],
+    destination: P,
+) -> Result<(), Box<dyn std::error::Error>> {
+    extract_matching(bytes, destination, &|_| true)
+}
+
+/// Extract the entries of the archive in `bytes` whose path in the archive
+/// `keep` accepts into `destination`, skipping the others.
+///
+/// Parent directories of the extracted files are created even if their own
+/// entries are skipped.
+pub fn extract_matching<P: AsRef<Path>>(
+    bytes: &[u8],
+    destination: P,
+    keep: &dyn Fn(&Path) -> bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let destination = destination.as_ref();
+
+    if bytes.starts_with(ZIP_MAGIC) {
+        extract_zip(bytes, destination, keep)
+    } else if bytes.starts_with(GZIP_MAGIC) {
+        extract_tar(GzDecoder::new(bytes), destination, keep)
+    } else {
+        extract_tar(bytes, destination, keep)
+    }
+}
+
+fn extract_tar<R: Read>(
+    reader: R,
+    destination: &Path,
+    keep: &dyn Fn(&Path) -> bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut archive = tar::Archive::new(reader);
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+
+        if !keep(&entry.path()?) {
+            continue;
+        }
+
+        // `unpack_in` refuses entries escaping the destination
+        entry.unpack_in(destination)?;
+    }
+
+    Ok(())
+}
+
+fn extract_zip(
+    bytes: &[u8],
+    destination: &Path,
+    keep: &dyn Fn(&Path) -> bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))?;
+
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+
+        // skip entries escaping the destination
+        let path = match file.enclosed_name() {
+            Some(path) if keep(path) => destination.join(path),
+            _ => continue,
+        };
+
+        if file.is_dir() {
+            fs::create_dir_all(&path)?;
+        } else {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            let mut contents = Vec::new();
+
+            file.read_to_end(&mut contents)?;
+
+            fs::write(&path, contents)?;
+
+            #[cfg(not(target_os = "windows"))]
+            if let Some(mode) = file.unix_mode() {
+                use std::os::unix::fs::PermissionsExt;
+
+                fs::set_permissions(&path, fs::Permissions::from_mode(mode))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the commit a tarball was built from, as recorded in its pax global
+/// header by `git archive` and GitHub's tarball API.
+pub fn tarball_commit(bytes: &[u8]) -> Option<String> {
+    if !bytes.starts_with(GZIP_MAGIC) {
+        return None;
+    }
+
+    let mut archive = tar::Archive::new(GzDecoder::new(bytes));
+
+    let mut entry = archive.entries().ok()?.next()?.ok()?;
+
+    if !entry.header().entry_type().is_pax_global_extensions() {
+        return None;
+    }
+
+    let mut extensions = String::new();
+
+    entry.read_to_string(&mut extensions).ok()?;
+
+    // records are formatted as "<length> <key>=<value>\n"
+    extensions.lines().find_map(|record| {
+        let (_length, pair) = record.split_once(' ')?;
+
+        pair.strip_prefix("comment=").map(str::to_string)
+    })
+}
+
+/// Returns the directory holding the template in an extracted archive.
+///
+/// Archives commonly wrap their content in a single top-level directory, in
+/// which case the template is looked for inside of it.
+pub fn template_root<P: AsRef<Path>>(extracted: P) -> PathBuf {
+    let extracted = extracted.as_ref();
+
+    if is_template(extracted) {
+        return extracted.to_path_buf();
+    }
+
+    let entries = fs::read_dir(extracted)
+        .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+        .unwrap_or_else(|_| Vec::new());
+
+    match entries.as_slice() {
+        [single] if single.is_dir() => single.clone(),
+        _ => extracted.to_path_buf(),
+    }
+}
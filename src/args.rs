@@ -1,39 +1,255 @@
 use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
+use project_init::answers::parse_define;
+use project_init::list::ListFormat;
+use project_init::pack::PackFormat;
+use project_init::types::{SortOrder, VersionControl};
 
 #[derive(Subcommand, Debug)]
 pub enum Subcommands {
     /// Fetch a template from github.
     #[clap(alias = "g")]
     Git {
-        /// User and repository name where the template is located
+        /// User and repository name where the template is located, prefixed by gl:, bb: or cb:
+        /// for GitLab, Bitbucket or Codeberg, or gist:ID for a gist
         #[clap(value_name = "USER/REPO")]
         repository: String,
         /// Project name to be used for project directory.
         #[clap(value_name = "NAME")]
         name: String,
-        /// Initialize project even if directory already exists.
+        /// Directory to generate the project in, defaults to a directory named after the
+        /// project
+        #[clap(long, short, value_name = "DIR")]
+        output: Option<PathBuf>,
+        /// Initialize project even if directory already exists, overwriting every file
+        /// instead of asking for each one that would change.
         #[clap(long, short)]
         force: bool,
+        /// Generate into an existing directory, only writing the files that don't exist
+        /// there yet
+        #[clap(long, conflicts_with = "force")]
+        merge: bool,
+        /// Git revision (branch, tag or commit) of the template to use
+        #[clap(long, value_name = "REV")]
+        rev: Option<String>,
+        /// Pin the checksum of the fetched template in the configuration, so that later
+        /// uses of this repository and revision fail if the template changed
+        #[clap(long)]
+        pin: bool,
+        /// Write a .pi.lock lockfile in the project, to generate it again identically with
+        /// `pi new --locked`
+        #[clap(long)]
+        lock: bool,
+        /// TOML or JSON file prefilling the keys of the template
+        #[clap(long, value_name = "FILE")]
+        answers: Option<PathBuf>,
+        /// Set a key of the template, taking precedence over the answers file and the
+        /// custom keys of the template and configuration
+        #[clap(short = 'D', value_name = "KEY=VALUE", parse(try_from_str = parse_define), multiple_occurrences = true)]
+        defines: Vec<(String, String)>,
+        /// Save the answers, secrets excluded, in .pi-answers.toml in the project
+        #[clap(long)]
+        save_answers: bool,
+        /// License of the project, overriding the template's and the configuration's: BSD3,
+        /// BSD, MIT, GPL3, ALL_RIGHTS_RESERVED or one in $HOME/.pi_templates/licenses/
+        #[clap(long, value_name = "LICENSE")]
+        license: Option<String>,
+        /// Version control to initialize the project with, overriding the template's and the
+        /// configuration's: git, mercurial, pijul or darcs
+        #[clap(long, value_name = "VCS", conflicts_with = "no-vcs")]
+        vcs: Option<VersionControl>,
+        /// Don't initialize version control, e.g. for a project inside an existing repository
+        #[clap(long)]
+        no_vcs: bool,
+        /// Don't run the template's hooks, the commands it runs in the project once generated
+        #[clap(long)]
+        no_hooks: bool,
+        /// Run the hooks of remote templates without asking first
+        #[clap(long, conflicts_with = "no-hooks")]
+        trust_hooks: bool,
+        /// Open the project in the editor of the configuration, or $VISUAL or $EDITOR, once
+        /// generated
+        #[clap(long)]
+        open: bool,
+        /// Only print the absolute path of the project on stdout, everything else going to
+        /// stderr, as in `cd "$(pi new rust foo --print-path)"`
+        #[clap(long)]
+        print_path: bool,
+        /// Render the project in memory and print what would be written, overwritten and
+        /// initialized, without touching the disk
+        #[clap(long, conflicts_with_all = &["pin", "open", "print-path"])]
+        dry_run: bool,
     },
     /// Use a template from a folder.
     #[clap(alias = "n")]
     New {
-        /// Directory containing your template, either in the current directory or in $HOME/.pi_templates/
+        /// Directory containing your template, either in the current directory or in $HOME/.pi_templates/, or the url of an archive containing it.
+        /// With --locked, a lockfile or a project containing one
         #[clap(value_name = "TEMPLATE_DIR")]
         directory: PathBuf,
-        // TODO: We should probably disambiguate between the name and the output dir at one point
-        /// Project name to be used for project directory.
+        /// Project name to be used for project directory. When only one argument is given,
+        /// it is the project name and the template is picked interactively
         #[clap(value_name = "NAME")]
-        name: String,
-        /// Initialize project even if directory already exists.
+        name: Option<String>,
+        /// Directory to generate the project in, defaults to a directory named after the
+        /// project
+        #[clap(long, short, value_name = "DIR")]
+        output: Option<PathBuf>,
+        /// Initialize project even if directory already exists, overwriting every file
+        /// instead of asking for each one that would change.
+        #[clap(long, short)]
+        force: bool,
+        /// Generate into an existing directory, only writing the files that don't exist
+        /// there yet
+        #[clap(long, conflicts_with = "force")]
+        merge: bool,
+        /// Write a .pi.lock lockfile in the project, to generate it again identically with
+        /// `pi new --locked`
+        #[clap(long)]
+        lock: bool,
+        /// TOML or JSON file prefilling the keys of the template
+        #[clap(long, value_name = "FILE")]
+        answers: Option<PathBuf>,
+        /// Set a key of the template, taking precedence over the answers file and the
+        /// custom keys of the template and configuration
+        #[clap(short = 'D', value_name = "KEY=VALUE", parse(try_from_str = parse_define), multiple_occurrences = true)]
+        defines: Vec<(String, String)>,
+        /// Save the answers, secrets excluded, in .pi-answers.toml in the project
+        #[clap(long)]
+        save_answers: bool,
+        /// License of the project, overriding the template's and the configuration's: BSD3,
+        /// BSD, MIT, GPL3, ALL_RIGHTS_RESERVED or one in $HOME/.pi_templates/licenses/
+        #[clap(long, value_name = "LICENSE")]
+        license: Option<String>,
+        /// Version control to initialize the project with, overriding the template's and the
+        /// configuration's: git, mercurial, pijul or darcs
+        #[clap(long, value_name = "VCS", conflicts_with = "no-vcs")]
+        vcs: Option<VersionControl>,
+        /// Don't initialize version control, e.g. for a project inside an existing repository
+        #[clap(long)]
+        no_vcs: bool,
+        /// Don't run the template's hooks, the commands it runs in the project once generated
+        #[clap(long)]
+        no_hooks: bool,
+        /// Run the hooks of remote templates without asking first
+        #[clap(long, conflicts_with = "no-hooks")]
+        trust_hooks: bool,
+        /// Open the project in the editor of the configuration, or $VISUAL or $EDITOR, once
+        /// generated
+        #[clap(long)]
+        open: bool,
+        /// Only print the absolute path of the project on stdout, everything else going to
+        /// stderr, as in `cd "$(pi new rust foo --print-path)"`
+        #[clap(long)]
+        print_path: bool,
+        /// Render the project in memory and print what would be written, overwritten and
+        /// initialized, without touching the disk
+        #[clap(long, conflicts_with_all = &["open", "print-path"])]
+        dry_run: bool,
+        /// Generate the project exactly as described by the lockfile given instead of a
+        /// template
+        #[clap(long)]
+        locked: bool,
+        /// Fetch a template missing locally from the templates repositories without asking
+        #[clap(long)]
+        fetch: bool,
+    },
+    /// Install a template directory or archive in $HOME/.pi_templates/
+    Add {
+        /// Template directory, zip file or tarball containing the template, or remote template
+        /// as taken by `pi git`
+        #[clap(value_name = "TEMPLATE")]
+        template: PathBuf,
+        /// Name to install the template under, instead of its own
+        #[clap(long = "as", value_name = "NAME")]
+        name: Option<String>,
+        /// Replace an installed template with the same name
         #[clap(long, short)]
         force: bool,
     },
+    /// Fetch templates installed by `pi add` again from where they were installed from
+    Update {
+        /// Names of the installed templates, all of those installed by `pi add` by default
+        #[clap(value_name = "TEMPLATE")]
+        templates: Vec<String>,
+    },
+    /// Delete a template installed in $HOME/.pi_templates/
+    #[clap(alias = "rm")]
+    Remove {
+        /// Name of the installed template
+        #[clap(value_name = "TEMPLATE")]
+        template: String,
+        /// Don't ask for confirmation
+        #[clap(long, short)]
+        yes: bool,
+    },
     /// List all the available templates remotely and in the $HOME/.pi_templates/ directory
     #[clap(alias = "ls")]
-    List,
+    List {
+        /// Only list templates tagged with TAG, can be repeated
+        #[clap(long, value_name = "TAG")]
+        tag: Vec<String>,
+        /// Only list templates generating projects in LANGUAGE
+        #[clap(long, value_name = "LANGUAGE")]
+        language: Option<String>,
+        /// Order remote templates by popularity, last update or name
+        #[clap(long, possible_values = ["popularity", "updated", "name"])]
+        sort: Option<SortOrder>,
+        /// Print templates as commands, aligned columns, or a JSON array for scripts
+        #[clap(
+            long,
+            value_name = "FORMAT",
+            possible_values = ["plain", "table", "json"],
+            default_value = "plain"
+        )]
+        format: ListFormat,
+        /// Shorthand for --format json
+        #[clap(long, conflicts_with = "format")]
+        json: bool,
+    },
+    /// List installed and previously used templates with upstream updates
+    Outdated,
+    /// Search templates matching a query in the templates repositories
+    #[clap(alias = "s")]
+    Search {
+        /// Text to look for in the name and description of templates
+        #[clap(value_name = "QUERY", required_unless_present = "tag")]
+        query: Option<String>,
+        /// Only show templates tagged with TAG, can be repeated
+        #[clap(long, value_name = "TAG")]
+        tag: Vec<String>,
+        /// Order templates by popularity, last update or name
+        #[clap(long, possible_values = ["popularity", "updated", "name"])]
+        sort: Option<SortOrder>,
+        /// Search GitHub repositories tagged with the `pi-template` topic instead
+        #[clap(long)]
+        github: bool,
+    },
+    /// Show the variables, license and files of a local or remote template
+    #[clap(alias = "show")]
+    Info {
+        /// Template directory, in the current directory or in $HOME/.pi_templates/,
+        /// or remote template, as taken by `pi git`
+        #[clap(value_name = "TEMPLATE")]
+        template: String,
+        /// Git revision of a remote template
+        #[clap(long)]
+        rev: Option<String>,
+    },
+    /// Validate a template and publish its entry to a templates repository
+    Publish {
+        /// Directory of the template to publish
+        #[clap(value_name = "TEMPLATE", default_value = ".")]
+        directory: PathBuf,
+        /// Templates repository to publish to, defaults to the configured `templates_repository`
+        #[clap(long, value_name = "PATH_OR_URL")]
+        repository: Option<String>,
+        /// Token authenticating the request to a remote templates repository,
+        /// defaults to the PI_PUBLISH_TOKEN environment variable
+        #[clap(long)]
+        token: Option<String>,
+    },
     /// Initialize the global configuration file in $HOME/.pi.toml
     #[clap(alias = "i")]
     Init {
@@ -44,10 +260,266 @@ pub enum Subcommands {
         #[clap(long, short)]
         no_prompt: bool,
     },
+    /// Manage the cache of template repositories cloned by `pi git`
+    Cache {
+        #[clap(subcommand)]
+        command: CacheCommand,
+    },
+    /// Read and write the keys of the global configuration file in $HOME/.pi.toml
+    Config {
+        #[clap(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Report the files of a generated project that changed since it was generated
+    #[clap(alias = "v")]
+    Verify {
+        /// Directory of the generated project
+        #[clap(value_name = "PROJECT_DIR", default_value = ".")]
+        directory: PathBuf,
+    },
+    /// Show how re-rendering the template of a generated project would change it, without
+    /// modifying anything
+    Diff {
+        /// Directory of the generated project
+        #[clap(value_name = "PROJECT_DIR", default_value = ".")]
+        directory: PathBuf,
+        /// Don't color the diff, even on a terminal
+        #[clap(long)]
+        no_color: bool,
+    },
+    /// Apply the current version of its template to a generated project, keeping the files
+    /// modified since unless told otherwise
+    Upgrade {
+        /// Directory of the generated project
+        #[clap(value_name = "PROJECT_DIR", default_value = ".")]
+        directory: PathBuf,
+        /// Upgrade to this branch, tag or commit of a git template
+        #[clap(long, value_name = "REV")]
+        rev: Option<String>,
+        /// Overwrite the files modified since with the version of the template, without asking
+        #[clap(long, short)]
+        force: bool,
+        /// Only report what the upgrade would change
+        #[clap(long, conflicts_with = "force")]
+        dry_run: bool,
+    },
+    /// Remove the files pi generated in a project, keeping the ones modified or added since
+    Clean {
+        /// Directory of the generated project
+        #[clap(value_name = "PROJECT_DIR")]
+        directory: PathBuf,
+        /// Also remove the generated files modified since
+        #[clap(long, short)]
+        force: bool,
+    },
+    /// Browse the installed and registry templates, and generate a project from one
+    #[cfg(feature = "tui")]
+    Tui,
+    /// Generate projects from the installed templates over HTTP
+    #[cfg(feature = "serve")]
+    Serve {
+        /// Address to listen on
+        #[clap(long, value_name = "ADDRESS", default_value = "127.0.0.1:8000")]
+        address: std::net::SocketAddr,
+        /// Directory to generate projects into when a request asks for their path instead
+        /// of a zip archive
+        #[clap(long, value_name = "DIR")]
+        output_dir: Option<PathBuf>,
+    },
+    /// Check a template and bundle it into an archive
+    Pack {
+        /// Directory of the template to pack
+        #[clap(value_name = "TEMPLATE_DIR", default_value = ".")]
+        directory: PathBuf,
+        /// Archive to write, defaults to <NAME>-<VERSION>.<FORMAT> next to the template
+        #[clap(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+        /// Format of the archive, tar.gz or zip
+        #[clap(long, default_value = "tar.gz")]
+        format: PackFormat,
+    },
+    /// Turn an existing project into a template, replacing its name, author and year with
+    /// the keys rendering them
+    Freeze {
+        /// Directory of the project
+        #[clap(value_name = "PROJECT_DIR")]
+        directory: PathBuf,
+        /// Name of the project, replaced by {{ project }}, defaults to the name of its directory
+        #[clap(long, value_name = "NAME")]
+        project: Option<String>,
+        /// Directory to create the template in, defaults to $HOME/.pi_templates/<NAME>
+        #[clap(long, short, value_name = "DIR")]
+        output: Option<PathBuf>,
+    },
+    /// Manage templates
+    Template {
+        #[clap(subcommand)]
+        command: TemplateCommand,
+    },
+    /// Replace pi with the binary of its latest GitHub release
+    SelfUpdate {
+        /// Only report whether a newer release is available
+        #[clap(long)]
+        check: bool,
+        /// Install the latest release even if it isn't newer
+        #[clap(long, short)]
+        force: bool,
+    },
+    /// Generate a template against the fixtures in its tests/ directory
+    #[clap(alias = "t")]
+    Test {
+        /// Directory containing the template, either in the current directory or in $HOME/.pi_templates/
+        #[clap(value_name = "TEMPLATE_DIR")]
+        directory: PathBuf,
+    },
+    /// Report every problem of a template without generating anything
+    Validate {
+        /// Directory containing the template, either in the current directory or in $HOME/.pi_templates/
+        #[clap(value_name = "TEMPLATE_DIR", default_value = ".")]
+        directory: PathBuf,
+    },
+    /// Generate a template in a sandbox again each time one of its files changes
+    Watch {
+        /// Directory containing the template
+        #[clap(value_name = "TEMPLATE_DIR")]
+        directory: PathBuf,
+        /// Scratch directory the project is generated in, removed on each change
+        #[clap(long, value_name = "DIR", default_value = "pi-watch")]
+        sandbox: PathBuf,
+        /// TOML or JSON file prefilling the keys of the template
+        #[clap(long, value_name = "FILE")]
+        answers: Option<PathBuf>,
+    },
+    /// Generate one project per name or matrix row from the same template
+    Batch {
+        /// Directory containing the template, either in the current directory or in $HOME/.pi_templates/
+        #[clap(value_name = "TEMPLATE_DIR")]
+        directory: PathBuf,
+        /// File listing the names of the projects, one per line
+        #[clap(
+            long,
+            value_name = "FILE",
+            required_unless_present = "matrix",
+            conflicts_with = "matrix"
+        )]
+        names_from: Option<PathBuf>,
+        /// TOML or JSON file listing the name and answers of each project
+        #[clap(long, value_name = "FILE")]
+        matrix: Option<PathBuf>,
+        /// TOML or JSON file prefilling the keys of every project
+        #[clap(long, value_name = "FILE")]
+        answers: Option<PathBuf>,
+        /// Directory the projects are generated in
+        #[clap(long, value_name = "DIR", default_value = ".")]
+        output_dir: PathBuf,
+        /// Overwrite the projects that already exist
+        #[clap(long, short)]
+        force: bool,
+        /// Save the answers, secrets excluded, in .pi-answers.toml in each project
+        #[clap(long)]
+        save_answers: bool,
+    },
+    /// Print a file of a template rendered, or the value each placeholder is substituted with
+    Render {
+        /// Directory containing the template, either in the current directory or in $HOME/.pi_templates/
+        #[clap(value_name = "TEMPLATE_DIR")]
+        directory: PathBuf,
+        /// File to render, relative to the template directory
+        #[clap(value_name = "FILE")]
+        file: PathBuf,
+        /// Project name the file is rendered for
+        #[clap(long, value_name = "NAME", default_value = "project")]
+        name: String,
+        /// TOML or JSON file prefilling the keys of the template
+        #[clap(long, value_name = "FILE")]
+        answers: Option<PathBuf>,
+        /// List each placeholder of the file with its value and where the value comes from,
+        /// instead of rendering it
+        #[clap(long)]
+        annotate: bool,
+    },
+    /// Any other subcommand is resolved to a `pi-<SUBCOMMAND>` executable on the PATH
+    #[clap(external_subcommand)]
+    External(Vec<String>),
+}
+
+impl Subcommands {
+    /// Whether only the path of the generated project must reach stdout.
+    pub fn prints_path(&self) -> bool {
+        matches!(
+            self,
+            Self::Git {
+                print_path: true,
+                ..
+            } | Self::New {
+                print_path: true,
+                ..
+            }
+        )
+    }
 }
+/// Subcommands of `pi template`.
+#[derive(Subcommand, Debug)]
+pub enum TemplateCommand {
+    /// Create a new template, with a commented template.toml and example files
+    New {
+        /// Name of the template
+        #[clap(value_name = "NAME")]
+        name: String,
+        /// Directory to create the template in, defaults to $HOME/.pi_templates/<NAME>
+        #[clap(long, short, value_name = "DIR")]
+        path: Option<PathBuf>,
+        /// Description of the template, asked for if not given
+        #[clap(long, short)]
+        description: Option<String>,
+    },
+}
+
+/// Subcommands of `pi cache`.
+#[derive(Subcommand, Debug)]
+pub enum CacheCommand {
+    /// List the cached repositories, with their size and when they were last fetched
+    #[clap(alias = "ls")]
+    List,
+    /// Delete the cache, the next uses of remote templates clone them again
+    Clean,
+    /// Print the path of the cache
+    Path,
+}
+
+/// Subcommands of `pi config`.
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Print the value of a key
+    Get {
+        /// Dotted key, such as author.name
+        #[clap(value_name = "KEY")]
+        key: String,
+    },
+    /// Set a key, keeping the comments and formatting of the file
+    Set {
+        /// Dotted key, such as author.name
+        #[clap(value_name = "KEY")]
+        key: String,
+        /// TOML value, such as true, 0o644 or ["a", "b"], taken as a string otherwise
+        #[clap(value_name = "VALUE")]
+        value: String,
+    },
+    /// Open the configuration file in the editor of the configuration, or $VISUAL or $EDITOR
+    Edit,
+}
+
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None, term_width = 80, after_help = "See 'man pi' for more information")]
 pub struct Args {
+    /// Browse the templates when omitted in a terminal
     #[clap(subcommand)]
-    pub subcommand: Subcommands,
+    pub subcommand: Option<Subcommands>,
+    /// Never prompt, questions take their default answer and missing keys are an error.
+    /// Implied when the CI environment variable is set
+    #[clap(long, global = true)]
+    pub no_input: bool,
+    /// Print the keys available to templates, with where their value comes from
+    #[clap(long, short, global = true)]
+    pub verbose: bool,
 }
@@ -0,0 +1,108 @@
+//! `pi batch`, generating many projects from one template in a single run.
+//!
+//! The projects are listed in a file of names, one per line, or in a matrix
+//! file giving the answers of each project along with its name. The template
+//! is parsed once and its files read and checked once for every project.
+
+use std::fs;
+use std::path::Path;
+
+use crate::answers::{json_answers, toml_answers};
+use crate::diagnostics::toml_diagnostic;
+
+/// A project to generate, with the answers it adds to the common ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchEntry {
+    pub name: String,
+    pub keys: Vec<(String, String)>,
+}
+
+/// Read the names in the file at `path`, one per line. Blank lines and lines
+/// starting with `#` are skipped.
+pub fn read_names<P: AsRef<Path>>(path: P) -> Result<Vec<BatchEntry>, Box<dyn std::error::Error>> {
+    let path = path.as_ref();
+
+    let content = fs::read_to_string(path).map_err(|error| {
+        format!(
+            "Couldn't read names file {}: {}",
+            path.to_string_lossy(),
+            error
+        )
+    })?;
+
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|name| BatchEntry {
+            name: name.to_string(),
+            keys: Vec::new(),
+        })
+        .collect())
+}
+
+/// Read the rows of the matrix file at `path`: an array of tables, each
+/// giving the `name` of a project and its answers, in TOML under `project`
+/// or in JSON, picked by the `.json` extension.
+pub fn read_matrix<P: AsRef<Path>>(path: P) -> Result<Vec<BatchEntry>, Box<dyn std::error::Error>> {
+    let path = path.as_ref();
+
+    let content = fs::read_to_string(path).map_err(|error| {
+        format!(
+            "Couldn't read matrix file {}: {}",
+            path.to_string_lossy(),
+            error
+        )
+    })?;
+
+    let rows = if path
+        .extension()
+        .is_some_and(|extension| extension == "json")
+    {
+        let rows: Vec<serde_json::Map<String, serde_json::Value>> = serde_json::from_str(&content)
+            .map_err(|error| {
+                format!("Invalid matrix file {}: {}", path.to_string_lossy(), error)
+            })?;
+
+        rows.into_iter().map(json_answers).collect::<Vec<_>>()
+    } else {
+        #[derive(serde::Deserialize)]
+        struct Matrix {
+            #[serde(default)]
+            project: Vec<toml::value::Table>,
+        }
+
+        let matrix: Matrix = toml::from_str(&content).map_err(|error| {
+            format!(
+                "Invalid matrix file {}\n{}",
+                path.to_string_lossy(),
+                toml_diagnostic(path, &content, &error)
+            )
+        })?;
+
+        matrix
+            .project
+            .into_iter()
+            .map(toml_answers)
+            .collect::<Vec<_>>()
+    };
+
+    rows.into_iter()
+        .enumerate()
+        .map(|(index, mut keys)| {
+            let name = match keys.iter().position(|(key, _)| key == "name") {
+                Some(position) => keys.remove(position).1,
+                None => {
+                    return Err(format!(
+                        "Row {} of matrix file {} has no name",
+                        index + 1,
+                        path.to_string_lossy()
+                    )
+                    .into())
+                }
+            };
+
+            Ok(BatchEntry { name, keys })
+        })
+        .collect()
+}
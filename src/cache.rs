@@ -0,0 +1,189 @@
+//! Local cache of remote templates, in `$XDG_CACHE_HOME/pi` (usually
+//! `~/.cache/pi`).
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Local};
+use url::Url;
+use walkdir::WalkDir;
+
+use crate::constants::{CACHE_DIRECTORY, INDEXES_CACHE_DIRECTORY, REPOSITORIES_CACHE_DIRECTORY};
+use crate::http::Validators;
+use crate::manifest::checksum;
+
+/// Root of pi's cache, if the platform has a cache directory.
+pub fn cache_directory() -> Option<PathBuf> {
+    dirs::cache_dir().map(|cache| cache.join(CACHE_DIRECTORY))
+}
+
+/// Directory caching the clone of the git repository at `url`, laid out as
+/// `repos/<host>/<user>/<repo>`.
+pub fn repository_cache_path(url: &Url) -> Option<PathBuf> {
+    let mut path = cache_directory()?
+        .join(REPOSITORIES_CACHE_DIRECTORY)
+        .join(url.host_str().unwrap_or("local"));
+
+    for segment in url.path_segments()?.filter(|segment| !segment.is_empty()) {
+        // never let a segment escape the cache
+        if segment == ".." || segment == "." {
+            return None;
+        }
+
+        path.push(segment.trim_end_matches(".git"));
+    }
+
+    Some(path)
+}
+
+/// File caching the templates repository at `url`.
+pub fn index_cache_path(url: &Url) -> Option<PathBuf> {
+    Some(
+        cache_directory()?
+            .join(INDEXES_CACHE_DIRECTORY)
+            .join(format!("{}.json", checksum(url.as_str().as_bytes()))),
+    )
+}
+
+/// File keeping the [`Validators`] of the cached templates repository at
+/// `url`.
+fn index_validators_path(url: &Url) -> Option<PathBuf> {
+    index_cache_path(url).map(|path| path.with_extension("validators.json"))
+}
+
+/// Keep a copy of the templates repository at `url`, along with the
+/// `validators` to check whether it changed.
+pub fn store_index(url: &Url, bytes: &[u8], validators: &Validators) -> std::io::Result<()> {
+    let (path, validators_path) = match (index_cache_path(url), index_validators_path(url)) {
+        (Some(path), Some(validators_path)) => (path, validators_path),
+        _ => return Ok(()),
+    };
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(path, bytes)?;
+
+    fs::write(validators_path, serde_json::to_vec(validators)?)
+}
+
+/// Mark the cached copy of the templates repository at `url` as up to date.
+pub fn touch_index(url: &Url) -> std::io::Result<()> {
+    match index_cache_path(url) {
+        Some(path) => fs::File::options()
+            .write(true)
+            .open(path)?
+            .set_modified(SystemTime::now()),
+        None => Ok(()),
+    }
+}
+
+/// Returns the validators of the cached copy of the templates repository at
+/// `url`, empty if there is no such copy.
+pub fn cached_index_validators(url: &Url) -> Validators {
+    match (index_cache_path(url), index_validators_path(url)) {
+        (Some(path), Some(validators_path)) if path.is_file() => fs::read(validators_path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default(),
+        _ => Validators::default(),
+    }
+}
+
+/// Returns the cached copy of the templates repository at `url`, and the
+/// date it was cached.
+pub fn cached_index(url: &Url) -> Option<(Vec<u8>, String)> {
+    let path = index_cache_path(url)?;
+
+    let bytes = fs::read(&path).ok()?;
+
+    Some((bytes, modification_date(&path)?))
+}
+
+/// Date `path` was last modified, formatted for messages.
+pub fn modification_date<P: AsRef<Path>>(path: P) -> Option<String> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+
+    Some(
+        DateTime::<Local>::from(modified)
+            .format("%Y-%m-%d %H:%M")
+            .to_string(),
+    )
+}
+
+/// A repository cloned in the cache by `pi git`.
+#[derive(Debug)]
+pub struct CachedRepository {
+    /// Path of the clone, `<host>/<user>/<repo>`, relative to the cache
+    pub name: PathBuf,
+    pub path: PathBuf,
+    /// Size of the clone in bytes, its history included
+    pub size: u64,
+    /// Date the clone was last fetched
+    pub fetched_at: Option<String>,
+}
+
+/// Repositories cloned in the cache, by name.
+pub fn cached_repositories() -> Vec<CachedRepository> {
+    let root = match cache_directory() {
+        Some(cache) => cache.join(REPOSITORIES_CACHE_DIRECTORY),
+        None => return Vec::new(),
+    };
+
+    let mut repositories = clones(&root)
+        .into_iter()
+        .map(|path| CachedRepository {
+            name: path.strip_prefix(&root).unwrap_or(&path).to_path_buf(),
+            size: directory_size(&path),
+            // git updates FETCH_HEAD on every fetch
+            fetched_at: modification_date(path.join(".git").join("FETCH_HEAD"))
+                .or_else(|| modification_date(path.join(".git"))),
+            path,
+        })
+        .collect::<Vec<_>>();
+
+    repositories.sort_by(|left, right| left.name.cmp(&right.name));
+
+    repositories
+}
+
+/// Delete pi's cache, the cloned repositories and the copies of templates
+/// repositories alike. Returns the number of bytes freed.
+pub fn clean_cache() -> std::io::Result<u64> {
+    let cache = match cache_directory() {
+        Some(cache) if cache.exists() => cache,
+        _ => return Ok(0),
+    };
+
+    let size = directory_size(&cache);
+
+    fs::remove_dir_all(&cache)?;
+
+    Ok(size)
+}
+
+/// Git repositories under `root`.
+pub fn clones(root: &Path) -> Vec<PathBuf> {
+    WalkDir::new(root)
+        .min_depth(1)
+        .max_depth(4)
+        .into_iter()
+        .filter_entry(|entry| entry.file_type().is_dir() && entry.file_name() != ".git")
+        .flatten()
+        .filter(|entry| entry.path().join(".git").is_dir())
+        .map(|entry| entry.into_path())
+        .collect()
+}
+
+/// Total size of the files under `path`, in bytes.
+pub fn directory_size<P: AsRef<Path>>(path: P) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
@@ -0,0 +1,96 @@
+//! `pi clean`, removing what pi generated in a project.
+//!
+//! Only the files recorded in the project's manifest are removed, and only if
+//! they are unchanged since they were generated, so that no work is lost.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::constants::{ANSWERS_FILENAME, LOCKFILE_FILENAME, MANIFEST_DIRECTORY};
+use crate::manifest::{checksum, Manifest};
+use crate::provenance::Provenance;
+use crate::verify::project_files;
+
+/// What [`clean`] did.
+#[derive(Debug, Default)]
+pub struct CleanReport {
+    /// Generated files removed
+    pub removed: Vec<PathBuf>,
+    /// Generated files kept because they changed since
+    pub modified: Vec<PathBuf>,
+    /// Files pi didn't generate, left in place
+    pub added: Vec<PathBuf>,
+    /// Whether the project directory itself was removed, being empty
+    pub removed_project: bool,
+}
+
+/// Remove the files and directories pi generated in the project located in
+/// `project_dir`, along with pi's own files. Generated files modified since
+/// are kept, unless `force` is set.
+pub fn clean<P: AsRef<Path>>(
+    project_dir: P,
+    force: bool,
+) -> Result<CleanReport, Box<dyn std::error::Error>> {
+    let project_dir = project_dir.as_ref();
+
+    let manifest = Manifest::from_project(project_dir)?;
+
+    let mut report = CleanReport::default();
+
+    for file in &manifest.files {
+        let path = project_dir.join(&file.path);
+
+        let contents = match fs::read(&path) {
+            Ok(contents) => contents,
+            Err(_) => continue,
+        };
+
+        if force || checksum(&contents) == file.checksum {
+            fs::remove_file(&path)?;
+
+            remove_empty_parents(project_dir, &file.path);
+
+            report.removed.push(file.path.clone());
+        } else {
+            report.modified.push(file.path.clone());
+        }
+    }
+
+    for own_file in [LOCKFILE_FILENAME, ANSWERS_FILENAME] {
+        let _ = fs::remove_file(project_dir.join(own_file));
+    }
+
+    let _ = fs::remove_file(Manifest::path(project_dir));
+    let _ = fs::remove_file(Provenance::path(project_dir));
+    let _ = fs::remove_dir(project_dir.join(MANIFEST_DIRECTORY));
+
+    // deepest directories first, those still holding files are kept
+    let mut directories = manifest.directories.clone();
+
+    directories.sort_by_key(|directory| std::cmp::Reverse(directory.components().count()));
+
+    for directory in directories {
+        if fs::remove_dir(project_dir.join(&directory)).is_ok() {
+            remove_empty_parents(project_dir, &directory);
+        }
+    }
+
+    report.added = project_files(project_dir)
+        .into_iter()
+        .filter(|path| !report.modified.contains(path))
+        .collect();
+
+    report.removed_project = fs::remove_dir(project_dir).is_ok();
+
+    Ok(report)
+}
+
+/// Remove the directories holding `path`, relative to `root`, that are left
+/// empty.
+fn remove_empty_parents(root: &Path, path: &Path) {
+    for parent in path.ancestors().skip(1) {
+        if parent.as_os_str().is_empty() || fs::remove_dir(root.join(parent)).is_err() {
+            break;
+        }
+    }
+}
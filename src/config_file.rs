@@ -0,0 +1,165 @@
+//! Programmatic edits of the global configuration file.
+//!
+//! Writes go through [`toml_edit`] so that the comments and formatting of a
+//! hand-written `$HOME/.pi.toml` survive pi modifying some of its keys.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use toml_edit::{ArrayOfTables, DocumentMut, Item, Table, TableLike, Value};
+
+use crate::types::Config;
+
+/// An editable view of a TOML configuration file.
+#[derive(Debug)]
+pub struct ConfigFile {
+    path: PathBuf,
+    document: DocumentMut,
+}
+
+impl ConfigFile {
+    /// Open the configuration file at `path`, starting from an empty document
+    /// if it doesn't exist yet.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref().to_path_buf();
+
+        let document = if path.exists() {
+            fs::read_to_string(&path)?.parse::<DocumentMut>()?
+        } else {
+            DocumentMut::new()
+        };
+
+        Ok(Self { path, document })
+    }
+
+    /// Path of the configuration file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the item at the dotted `key` (e.g. `author.name`), if any.
+    pub fn get(&self, key: &str) -> Option<&Item> {
+        let mut item = self.document.as_item();
+
+        for segment in key.split('.') {
+            item = item.as_table_like()?.get(segment)?;
+        }
+
+        Some(item)
+    }
+
+    /// Set the dotted `key` to `value`, creating the intermediate tables if
+    /// they don't exist, and keeping the decoration of an existing value.
+    pub fn set<V: Into<Value>>(&mut self, key: &str, value: V) -> Result<(), String> {
+        let (table, last) = self.parent_table_mut(key)?;
+
+        let mut value = value.into();
+
+        match table.get_mut(last) {
+            Some(Item::Value(existing)) => {
+                *value.decor_mut() = existing.decor().clone();
+
+                *existing = value;
+            }
+            _ => {
+                table.insert(last, Item::Value(value));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Append `table` to the array of tables at the dotted `key`, creating
+    /// it if it doesn't exist.
+    pub fn push_table(&mut self, key: &str, table: Table) -> Result<(), String> {
+        let (parent, last) = self.parent_table_mut(key)?;
+
+        parent
+            .entry(last)
+            .or_insert(Item::ArrayOfTables(ArrayOfTables::new()))
+            .as_array_of_tables_mut()
+            .ok_or_else(|| format!("Key '{}' is not an array of tables", key))?
+            .push(table);
+
+        Ok(())
+    }
+
+    /// Remove the dotted `key`, returning the removed item if it existed.
+    pub fn remove(&mut self, key: &str) -> Option<Item> {
+        let (parent, last) = match key.rsplit_once('.') {
+            Some((parent, last)) => (Some(parent), last),
+            None => (None, key),
+        };
+
+        let mut table: &mut dyn TableLike = self.document.as_table_mut();
+
+        for segment in parent.into_iter().flat_map(|parent| parent.split('.')) {
+            table = table.get_mut(segment)?.as_table_like_mut()?;
+        }
+
+        table.remove(last)
+    }
+
+    /// Parse the configuration as edited so far, to check that pi still
+    /// accepts it before saving it.
+    pub fn check(&self) -> Result<Config, String> {
+        Config::parse(&self.path, &self.document.to_string())
+    }
+
+    /// Write the configuration file back to disk.
+    pub fn save(&self) -> std::io::Result<()> {
+        fs::write(&self.path, self.document.to_string())
+    }
+
+    /// Returns the table that should hold the last segment of the dotted
+    /// `key`, along with that segment.
+    fn parent_table_mut<'a, 'k>(
+        &'a mut self,
+        key: &'k str,
+    ) -> Result<(&'a mut dyn TableLike, &'k str), String> {
+        let (parent, last) = match key.rsplit_once('.') {
+            Some((parent, last)) => (Some(parent), last),
+            None => (None, key),
+        };
+
+        let mut table: &mut dyn TableLike = self.document.as_table_mut();
+
+        for segment in parent.into_iter().flat_map(|parent| parent.split('.')) {
+            let item = table.entry(segment).or_insert_with(|| {
+                let mut new_table = Table::new();
+
+                new_table.set_implicit(true);
+
+                Item::Table(new_table)
+            });
+
+            table = item
+                .as_table_like_mut()
+                .ok_or_else(|| format!("Key '{}' in '{}' is not a table", segment, key))?;
+        }
+
+        Ok((table, last))
+    }
+}
+
+/// Parse `text`, given on the command line, as a TOML value such as `true`,
+/// `0o644` or `["a", "b"]`, taking it as a string otherwise.
+pub fn parse_value(text: &str) -> Value {
+    text.parse::<Value>().unwrap_or_else(|_| Value::from(text))
+}
+
+/// Render `item`, found at the dotted `key`, as `pi config get` prints it:
+/// strings without their quotes, and tables as TOML.
+pub fn format_item(key: &str, item: &Item) -> String {
+    match item {
+        Item::Value(Value::String(string)) => string.value().clone(),
+        Item::Value(value) => value.clone().decorated("", "").to_string(),
+        _ => {
+            let mut document = DocumentMut::new();
+
+            document.insert(key.rsplit('.').next().unwrap_or(key), item.clone());
+
+            document.to_string().trim_end().to_string()
+        }
+    }
+}
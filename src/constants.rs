@@ -1,12 +1,94 @@
+use std::time::Duration;
+
 use lazy_static::lazy_static;
 use url::Url;
 
 lazy_static! {
     pub static ref GITHUB_URL: Url = "https://github.com".parse().unwrap();
+    pub static ref GITHUB_API_URL: Url = "https://api.github.com".parse().unwrap();
+    pub static ref GITLAB_URL: Url = "https://gitlab.com".parse().unwrap();
+    pub static ref BITBUCKET_URL: Url = "https://bitbucket.org".parse().unwrap();
+    pub static ref CODEBERG_URL: Url = "https://codeberg.org".parse().unwrap();
 }
 
+/// Environment variable overriding the `github_base_url` configuration key.
+pub const GITHUB_BASE_URL_VARIABLE: &str = "PI_GITHUB_BASE_URL";
+
+/// Environment variable holding a GitHub token, used before the
+/// `github_token` configuration key.
+pub const GITHUB_TOKEN_VARIABLE: &str = "GITHUB_TOKEN";
+
+/// Environment variable holding a Unix timestamp used instead of the current
+/// time, for reproducible output, see <https://reproducible-builds.org/specs/source-date-epoch/>.
+pub const SOURCE_DATE_EPOCH_VARIABLE: &str = "SOURCE_DATE_EPOCH";
+
+/// Name of the file describing a template, created by `pi template new`.
 pub const TEMPLATE_FILENAME: &str = "template.toml";
 
+/// Names a template's description file can have, by order of precedence, for
+/// templates living alongside tools that already use `template.toml`. The
+/// extension gives the format.
+pub const TEMPLATE_FILENAMES: &[&str] = &[
+    TEMPLATE_FILENAME,
+    "pi.toml",
+    "template.yaml",
+    "template.yml",
+    "template.json",
+];
+
 pub const GLOBAL_CONFIG_FILENAME: &str = ".pi.toml";
 
 pub const GLOBAL_TEMPLATE_DIRECTORY: &str = ".pi_templates";
+
+/// Directory of `$HOME/.pi_templates/` holding license texts, looked up by
+/// license name before the builtin ones.
+pub const LICENSES_DIRECTORY: &str = "licenses";
+
+/// Directory of a repository holding several templates, one per subdirectory.
+pub const MONOREPO_TEMPLATES_DIRECTORY: &str = "templates";
+
+/// GitHub topic identifying repositories containing a pi template.
+pub const TEMPLATE_TOPIC: &str = "pi-template";
+
+/// GitHub repository pi is released from, used by `pi self-update`.
+pub const RELEASES_REPOSITORY: &str = "legion-labs/project-init";
+
+/// File at the root of a template listing, in gitignore syntax, the files
+/// left out whenever the template directory is walked.
+pub const PIIGNORE_FILENAME: &str = ".piignore";
+
+/// Description of a template packed by `pi pack`, at the root of the archive.
+pub const PACKAGE_MANIFEST_FILENAME: &str = "pi-package.toml";
+
+/// Directory, inside generated projects, holding pi's own files.
+pub const MANIFEST_DIRECTORY: &str = ".pi";
+
+pub const MANIFEST_FILENAME: &str = "manifest.toml";
+
+/// Record of where a project was generated from, next to its manifest.
+pub const PROVENANCE_FILENAME: &str = "source.toml";
+
+/// Lockfile written at the root of generated projects by `--lock`.
+pub const LOCKFILE_FILENAME: &str = ".pi.lock";
+
+/// Answers file written at the root of generated projects by `--save-answers`.
+pub const ANSWERS_FILENAME: &str = ".pi-answers.toml";
+
+/// Directories created by version control tools, never considered part of a
+/// generated project.
+pub const VERSION_CONTROL_DIRECTORIES: &[&str] = &[".git", ".hg", ".pijul", "_darcs"];
+
+/// Directory holding pi's cache, inside the platform's cache directory.
+pub const CACHE_DIRECTORY: &str = "pi";
+
+/// Directory of the cache holding clones of template repositories.
+pub const REPOSITORIES_CACHE_DIRECTORY: &str = "repos";
+
+/// Directory of the cache holding copies of remote templates repositories.
+pub const INDEXES_CACHE_DIRECTORY: &str = "indexes";
+
+/// Maximum time a remote templates repository gets to answer.
+pub const TEMPLATES_REPOSITORY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Format of the `date` key, month-day-year without padding.
+pub const DEFAULT_DATE_FORMAT: &str = "%-m-%-d-%Y";
@@ -0,0 +1,143 @@
+//! Templates depending on other templates.
+//!
+//! A template lists the templates it builds upon in its `dependencies`,
+//! each of them is rendered into the project before the template itself, so
+//! that the template's files take precedence.
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use futures::future::LocalBoxFuture;
+use futures::FutureExt;
+
+use crate::fetch::{fetch_template, parse_archive_url, parse_remote, FetchedTemplate};
+use crate::http::HttpFetcher;
+use crate::manifest::TemplateSource;
+use crate::types::{is_template, Config, Project};
+
+/// A template some other template depends on, kept on disk as long as it is
+/// needed.
+#[derive(Debug)]
+pub struct Dependency {
+    pub source: TemplateSource,
+    pub project: Project,
+    _template: FetchedTemplate,
+}
+
+/// Fetch the dependencies of `project`, fetched from `source`, and theirs,
+/// ordered so that each template comes after the templates it depends on.
+///
+/// Templates depended upon several times are only returned once, and
+/// dependency cycles are an error.
+pub async fn resolve_dependencies<H: AsRef<Path>>(
+    home: H,
+    config: &Config,
+    source: &TemplateSource,
+    project: &Project,
+    fetcher: &dyn HttpFetcher,
+) -> Result<Vec<Dependency>, Box<dyn std::error::Error>> {
+    let mut resolver = Resolver {
+        home: home.as_ref(),
+        config,
+        fetcher,
+        stack: vec![source.to_string()],
+        seen: HashSet::new(),
+        resolved: Vec::new(),
+    };
+
+    resolver.visit(project).await?;
+
+    Ok(resolver.resolved)
+}
+
+/// Where the dependency `value` of the template in `directory` comes from:
+/// a template directory, relative to `directory`, in the current directory or
+/// in `$HOME/.pi_templates/`, an archive, or a remote template as taken by
+/// `pi git`, optionally followed by `#REV`.
+pub fn dependency_source(
+    home: &Path,
+    directory: &Path,
+    value: &str,
+    config: &Config,
+) -> Result<TemplateSource, Box<dyn std::error::Error>> {
+    let relative = directory.join(value);
+
+    if is_template(&relative) {
+        return Ok(TemplateSource::from_path(relative));
+    }
+
+    if let Some(path) = Project::find(directory, value).or_else(|| Project::find(home, value)) {
+        return Ok(TemplateSource::from_path(path));
+    }
+
+    if let Some(url) = parse_archive_url(value) {
+        return Ok(TemplateSource::Archive { url });
+    }
+
+    let (value, rev) = match value.rsplit_once('#') {
+        Some((value, rev)) => (value, Some(rev.to_string())),
+        None => (value, None),
+    };
+
+    parse_remote(value, rev, config)
+        .map_err(|error| format!("Invalid template dependency {}: {}", value, error).into())
+}
+
+struct Resolver<'a> {
+    home: &'a Path,
+    config: &'a Config,
+    fetcher: &'a dyn HttpFetcher,
+    /// Templates being resolved, from the root template to the current one
+    stack: Vec<String>,
+    seen: HashSet<String>,
+    resolved: Vec<Dependency>,
+}
+
+impl<'a> Resolver<'a> {
+    fn visit<'b>(
+        &'b mut self,
+        project: &'b Project,
+    ) -> LocalBoxFuture<'b, Result<(), Box<dyn std::error::Error>>> {
+        async move {
+            for value in &project.dependencies {
+                let source = dependency_source(self.home, &project.path, value, self.config)?;
+
+                let key = source.to_string();
+
+                if self.stack.contains(&key) {
+                    return Err(format!(
+                        "Template dependency cycle: {} -> {}",
+                        self.stack.join(" -> "),
+                        key
+                    )
+                    .into());
+                }
+
+                if self.seen.contains(&key) {
+                    continue;
+                }
+
+                let template = fetch_template(&source, self.config, self.fetcher)
+                    .await
+                    .map_err(|error| format!("Couldn't fetch dependency {}: {}", value, error))?;
+
+                let dependency = template.project(&source);
+
+                self.stack.push(key.clone());
+                self.visit(&dependency).await?;
+                self.stack.pop();
+
+                self.seen.insert(key);
+
+                self.resolved.push(Dependency {
+                    source,
+                    project: dependency,
+                    _template: template,
+                });
+            }
+
+            Ok(())
+        }
+        .boxed_local()
+    }
+}
@@ -0,0 +1,170 @@
+//! Human friendly reports for errors found in configuration files and
+//! templates.
+
+use std::fmt::Write;
+use std::path::Path;
+
+use regex::Regex;
+
+/// Render a TOML parsing `error` found in the file at `path`, whose content is
+/// `source`, showing the offending line with a caret under the column and a
+/// hint when one is available.
+pub fn toml_diagnostic<P: AsRef<Path>>(path: P, source: &str, error: &toml::de::Error) -> String {
+    let full_message = error.to_string();
+
+    // the position is rendered separately
+    let message = match full_message.rfind(" at line ") {
+        Some(index) if error.line_col().is_some() => &full_message[..index],
+        _ => full_message.as_str(),
+    };
+
+    let mut diagnostic = format!("error: {}\n", message);
+
+    match error.line_col() {
+        Some((line, column)) => {
+            let gutter = snippet(&mut diagnostic, path, source, line, column);
+
+            if let Some(hint) = hint(message) {
+                let _ = writeln!(diagnostic, "{} = hint: {}", gutter, hint);
+            }
+        }
+        None => {
+            let _ = writeln!(diagnostic, " --> {}", path.as_ref().to_string_lossy());
+
+            if let Some(hint) = hint(message) {
+                let _ = writeln!(diagnostic, " = hint: {}", hint);
+            }
+        }
+    }
+
+    diagnostic
+}
+
+/// Render the error `message` of a YAML or JSON template description at
+/// `path`, whose content is `source`, pointing at the zero based line and
+/// column of `position` when known.
+pub fn data_diagnostic<P: AsRef<Path>>(
+    path: P,
+    source: &str,
+    message: &str,
+    position: Option<(usize, usize)>,
+) -> String {
+    // the position is rendered separately
+    let message = match position {
+        Some(_) => Regex::new(r" at line \d+ column \d+")
+            .unwrap()
+            .replace(message, "")
+            .into_owned(),
+        None => message.to_string(),
+    };
+
+    let mut diagnostic = format!("error: {}\n", message);
+
+    let gutter = match position {
+        Some((line, column)) => snippet(&mut diagnostic, path, source, line, column),
+        None => {
+            let _ = writeln!(diagnostic, " --> {}", path.as_ref().to_string_lossy());
+
+            String::new()
+        }
+    };
+
+    if let Some(hint) = hint(&message) {
+        let _ = writeln!(diagnostic, "{} = hint: {}", gutter, hint);
+    }
+
+    diagnostic
+}
+
+/// Render an error `message` about the template at `path`, whose content is
+/// `source`, pointing at the byte `offset` where the problem lies.
+pub fn template_diagnostic<P: AsRef<Path>>(
+    path: P,
+    source: &str,
+    offset: usize,
+    message: &str,
+) -> String {
+    let mut diagnostic = format!("error: {}\n", message);
+
+    let before = &source[..offset.min(source.len())];
+
+    let line = before.matches('\n').count();
+
+    let column = before
+        .rsplit('\n')
+        .next()
+        .map(|start| start.chars().count())
+        .unwrap_or_default();
+
+    snippet(&mut diagnostic, path, source, line, column);
+
+    diagnostic
+}
+
+/// Append the location `path:line:column` and the offending line of `source`
+/// with a caret under the column, both zero based, to `diagnostic`. Returns
+/// the gutter, to align further notes.
+fn snippet<P: AsRef<Path>>(
+    diagnostic: &mut String,
+    path: P,
+    source: &str,
+    line: usize,
+    column: usize,
+) -> String {
+    let line_number = (line + 1).to_string();
+
+    let gutter = " ".repeat(line_number.len());
+
+    let content = source.lines().nth(line).unwrap_or_default();
+
+    let _ = writeln!(
+        diagnostic,
+        "{}--> {}:{}:{}",
+        gutter,
+        path.as_ref().to_string_lossy(),
+        line + 1,
+        column + 1
+    );
+    let _ = writeln!(diagnostic, "{} |", gutter);
+    let _ = writeln!(diagnostic, "{} | {}", line_number, content);
+    let _ = writeln!(
+        diagnostic,
+        "{} | {}^",
+        gutter,
+        caret_padding(content, column)
+    );
+
+    gutter
+}
+
+/// Whitespace aligning the caret under `column`, keeping tabs so that the
+/// caret lines up with the source line.
+fn caret_padding(content: &str, column: usize) -> String {
+    content
+        .chars()
+        .take(column)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect()
+}
+
+/// Suggest a fix for the most common mistakes.
+fn hint(message: &str) -> Option<&'static str> {
+    if message.starts_with("missing field") {
+        Some("this key is required, add it to the file")
+    } else if message.starts_with("unknown variant") {
+        Some("the value must be one of the variants listed above")
+    } else if message.starts_with("invalid TOML value") || message.contains("found an identifier") {
+        Some("strings must be surrounded by double quotes, e.g. license = \"MIT\"")
+    } else if message.starts_with("expected an equals") {
+        Some("keys and values are separated by `=`, e.g. name = \"value\"")
+    } else if message.starts_with("invalid type") || message.starts_with("expected") {
+        Some("check the type of the value, strings must be quoted and lists use [ ]")
+    } else if message.starts_with("redefinition of table") || message.contains("duplicate key") {
+        Some("each table and key can only be defined once")
+    } else if message.starts_with("unterminated string") || message.starts_with("newline in string")
+    {
+        Some("close the string with a matching quote, or use \"\"\" for multiline strings")
+    } else {
+        None
+    }
+}
@@ -0,0 +1,46 @@
+//! Differences between files, shown to the user as unified diffs.
+
+use std::path::Path;
+
+use similar::TextDiff;
+
+/// Unified diff turning `old` into `new`, both being the contents of the file
+/// at `path`. Binary contents are only reported as differing.
+pub fn unified_diff(path: &Path, old: &[u8], new: &[u8]) -> String {
+    let path = path.to_string_lossy();
+
+    match (std::str::from_utf8(old), std::str::from_utf8(new)) {
+        (Ok(old), Ok(new)) => TextDiff::from_lines(old, new)
+            .unified_diff()
+            .header(&format!("a/{}", path), &format!("b/{}", path))
+            .to_string(),
+        _ => format!("Binary files a/{0} and b/{0} differ\n", path),
+    }
+}
+
+/// Color the lines of the unified diff `diff` for a terminal: removals in red,
+/// additions in green and hunk headers in cyan.
+pub fn colorize(diff: &str) -> String {
+    diff.split_inclusive('\n')
+        .map(|line| {
+            let color = if line.starts_with("---") || line.starts_with("+++") {
+                "1"
+            } else if line.starts_with('-') {
+                "31"
+            } else if line.starts_with('+') {
+                "32"
+            } else if line.starts_with("@@") {
+                "36"
+            } else {
+                return line.to_string();
+            };
+
+            let (text, newline) = match line.strip_suffix('\n') {
+                Some(text) => (text, "\n"),
+                None => (line, ""),
+            };
+
+            format!("\x1b[{}m{}\x1b[0m{}", color, text, newline)
+        })
+        .collect()
+}
@@ -0,0 +1,91 @@
+//! Opening a generated project in the user's editor, for `--open`, and the
+//! global configuration, for `pi config edit`.
+
+use std::env;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+use crate::types::Config;
+
+/// Editors running in the terminal, given only the entry file since they
+/// can't open a directory alongside it.
+const TERMINAL_EDITORS: &[&str] = &["vi", "vim", "nvim", "emacs", "nano", "hx", "kak", "micro"];
+
+/// Command of the editor to open projects with: the `editor` of the
+/// configuration, else `$VISUAL` or `$EDITOR`.
+pub fn editor_command(config: &Config) -> Option<String> {
+    config
+        .editor
+        .clone()
+        .or_else(|| env::var("VISUAL").ok())
+        .or_else(|| env::var("EDITOR").ok())
+        .filter(|command| !command.trim().is_empty())
+}
+
+/// Open the project in `directory` with the editor `command`, along with
+/// its `entry` file, relative to the project, if any.
+///
+/// `command` may hold arguments, like `code --new-window`. The editor runs
+/// from the project directory and pi waits for it, so that terminal editors
+/// get the terminal.
+pub fn open_project(command: &str, directory: &Path, entry: Option<&Path>) -> io::Result<()> {
+    let mut words = command.split_whitespace();
+
+    let program = words
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Empty editor command"))?;
+
+    let mut editor = Command::new(program);
+
+    editor.args(words).current_dir(directory);
+
+    let entry = entry.filter(|entry| directory.join(entry).is_file());
+
+    if entry.is_none() || !is_terminal_editor(program) {
+        editor.arg(".");
+    }
+
+    if let Some(entry) = entry {
+        editor.arg(entry);
+    }
+
+    wait_for(command, &mut editor)
+}
+
+/// Open the file at `path` with the editor `command`, waiting for it to exit.
+pub fn edit_file(command: &str, path: &Path) -> io::Result<()> {
+    let mut words = command.split_whitespace();
+
+    let program = words
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Empty editor command"))?;
+
+    let mut editor = Command::new(program);
+
+    editor.args(words).arg(path);
+
+    wait_for(command, &mut editor)
+}
+
+fn wait_for(command: &str, editor: &mut Command) -> io::Result<()> {
+    let status = editor.status()?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "{} exited with {}",
+            command, status
+        )))
+    }
+}
+
+fn is_terminal_editor(program: &str) -> bool {
+    let name = Path::new(program)
+        .file_stem()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    TERMINAL_EDITORS.contains(&name.as_str())
+}
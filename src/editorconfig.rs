@@ -0,0 +1,449 @@
+//! Applying the `.editorconfig` of a generated project to its text files.
+//!
+//! The `.editorconfig` files read are the one the template generates at the
+//! root of the project, or the one already there, and those of the
+//! directories above it until one is marked `root = true`, closer files
+//! taking precedence. Their `indent_style`, `indent_size`, `tab_width`,
+//! `end_of_line` and `insert_final_newline` properties are applied to the
+//! rendered files.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+use crate::plan::{FileKind, Plan};
+
+/// Name of the files holding the rules, in a directory or any of its parents.
+pub const EDITORCONFIG_FILENAME: &str = ".editorconfig";
+
+/// Largest numeric range, as in `{1..10}`, expanded in a section's glob.
+const MAX_RANGE: i64 = 1000;
+
+/// A parsed `.editorconfig` file.
+#[derive(Debug)]
+pub struct EditorConfig {
+    /// Directory holding the file, the globs of its sections are relative to
+    directory: PathBuf,
+    /// Whether the files of the parent directories are ignored
+    root: bool,
+    sections: Vec<Section>,
+}
+
+#[derive(Debug)]
+struct Section {
+    pattern: Regex,
+    properties: BTreeMap<String, String>,
+}
+
+impl EditorConfig {
+    /// Parse the `.editorconfig` file in `directory` holding `text`.
+    ///
+    /// Sections whose glob can't be understood are skipped, like lines that
+    /// are neither a section, a property nor a comment.
+    pub fn parse(text: &str, directory: &Path) -> Self {
+        let mut config = EditorConfig {
+            directory: directory.to_path_buf(),
+            root: false,
+            sections: Vec::new(),
+        };
+
+        // properties of a section that was skipped are skipped too
+        let mut in_section = false;
+        let mut skipped = false;
+
+        for line in text.lines() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with(['#', ';']) {
+                continue;
+            }
+
+            if let Some(glob) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                in_section = true;
+
+                match glob_regex(glob) {
+                    Some(pattern) => {
+                        skipped = false;
+
+                        config.sections.push(Section {
+                            pattern,
+                            properties: BTreeMap::new(),
+                        });
+                    }
+                    None => skipped = true,
+                }
+
+                continue;
+            }
+
+            let (key, value) = match line.split_once(['=', ':']) {
+                Some((key, value)) => (key.trim().to_lowercase(), value.trim().to_lowercase()),
+                None => continue,
+            };
+
+            if !in_section {
+                config.root |= key == "root" && value == "true";
+            } else if let (Some(section), false) = (config.sections.last_mut(), skipped) {
+                section.properties.insert(key, value);
+            }
+        }
+
+        config
+    }
+
+    /// Read the `.editorconfig` file in `directory`, if there is one.
+    fn read(directory: &Path) -> Option<Self> {
+        let text = fs::read_to_string(directory.join(EDITORCONFIG_FILENAME)).ok()?;
+
+        Some(Self::parse(&text, directory))
+    }
+}
+
+/// Returns a regex matching the paths, relative to the directory of the
+/// `.editorconfig` file, that the section `glob` applies to.
+///
+/// Globs without a `/` match file names at any depth, `*` and `?` don't match
+/// `/` unlike `**`, and `{a,b}`, `{1..3}`, `[abc]` and `[!abc]` are supported.
+fn glob_regex(glob: &str) -> Option<Regex> {
+    let (prefix, glob) = match glob.strip_prefix('/') {
+        Some(glob) => ("", glob),
+        None if glob.contains('/') => ("", glob),
+        None => ("(?:.*/)?", glob),
+    };
+
+    let chars = glob.chars().collect::<Vec<_>>();
+
+    let mut position = 0;
+
+    let pattern = glob_pattern(&chars, &mut position, false)?;
+
+    Regex::new(&format!("^{}{}$", prefix, pattern)).ok()
+}
+
+/// Convert the glob in `chars` from `position` to a regex, up to the end or,
+/// inside braces, to the `,` or `}` ending the current alternative.
+fn glob_pattern(chars: &[char], position: &mut usize, in_braces: bool) -> Option<String> {
+    let mut pattern = String::new();
+
+    while let Some(&c) = chars.get(*position) {
+        match c {
+            ',' | '}' if in_braces => return Some(pattern),
+            '\\' => {
+                *position += 1;
+
+                if let Some(&escaped) = chars.get(*position) {
+                    pattern.push_str(&regex::escape(&escaped.to_string()));
+                }
+            }
+            '*' if chars.get(*position + 1) == Some(&'*') => {
+                *position += 1;
+
+                pattern.push_str(".*");
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            '[' => {
+                let end = chars[*position..].iter().position(|&c| c == ']')?;
+
+                let class = chars[*position + 1..*position + end]
+                    .iter()
+                    .collect::<String>();
+
+                let class = match class.strip_prefix('!') {
+                    Some(class) => format!("^{}", class),
+                    None => class,
+                };
+
+                pattern.push_str(&format!("[{}]", class.replace('\\', "\\\\")));
+
+                *position += end;
+            }
+            '{' => {
+                pattern.push_str(&braces(chars, position)?);
+
+                continue;
+            }
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+
+        *position += 1;
+    }
+
+    (!in_braces).then_some(pattern)
+}
+
+/// Convert the braces at `position` to a regex, leaving `position` after them.
+fn braces(chars: &[char], position: &mut usize) -> Option<String> {
+    let end = chars[*position..].iter().position(|&c| c == '}')?;
+
+    let inside = chars[*position + 1..*position + end]
+        .iter()
+        .collect::<String>();
+
+    // numeric ranges, as in {1..3}
+    if let Some((start, stop)) = inside.split_once("..") {
+        if let (Ok(start), Ok(stop)) = (start.parse::<i64>(), stop.parse::<i64>()) {
+            if (stop - start).abs() > MAX_RANGE {
+                return None;
+            }
+
+            *position += end + 1;
+
+            let numbers = (start.min(stop)..=start.max(stop))
+                .map(|number| number.to_string())
+                .collect::<Vec<_>>();
+
+            return Some(format!("(?:{})", numbers.join("|")));
+        }
+    }
+
+    let mut alternatives = Vec::new();
+
+    loop {
+        *position += 1;
+
+        alternatives.push(glob_pattern(chars, position, true)?);
+
+        if chars.get(*position) == Some(&'}') {
+            *position += 1;
+
+            break;
+        }
+    }
+
+    // a single alternative isn't an alternation, the braces are literal
+    if alternatives.len() == 1 {
+        return Some(format!("\\{{{}\\}}", alternatives[0]));
+    }
+
+    Some(format!("(?:{})", alternatives.join("|")))
+}
+
+/// Returns the `.editorconfig` files applying to the project generated in
+/// `output`, from the farthest to the closest.
+///
+/// `generated` is the `.editorconfig` the template generates at the root of
+/// the project, if any, which replaces the one already there.
+pub fn editorconfigs(output: &Path, generated: Option<&str>) -> Vec<EditorConfig> {
+    let output = if output.is_absolute() {
+        output.to_path_buf()
+    } else {
+        env::current_dir().unwrap_or_default().join(output)
+    };
+
+    let mut configs = Vec::new();
+
+    let project = match generated {
+        Some(text) => EditorConfig::parse(text, &output),
+        None => match EditorConfig::read(&output) {
+            Some(config) => config,
+            None => EditorConfig {
+                directory: output.clone(),
+                root: false,
+                sections: Vec::new(),
+            },
+        },
+    };
+
+    let mut root = project.root;
+
+    configs.push(project);
+
+    for directory in output.ancestors().skip(1) {
+        if root {
+            break;
+        }
+
+        if let Some(config) = EditorConfig::read(directory) {
+            root = config.root;
+
+            configs.push(config);
+        }
+    }
+
+    configs.reverse();
+
+    configs
+}
+
+/// Returns the properties applying to the file at `path`, as resolved from
+/// `configs`, ordered from the farthest to the closest.
+pub fn properties(configs: &[EditorConfig], path: &Path) -> BTreeMap<String, String> {
+    let mut properties = BTreeMap::new();
+
+    for config in configs {
+        let relative = match path.strip_prefix(&config.directory) {
+            Ok(relative) => relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+            Err(_) => continue,
+        };
+
+        for section in &config.sections {
+            if section.pattern.is_match(&relative) {
+                properties.extend(section.properties.clone());
+            }
+        }
+    }
+
+    properties.retain(|_, value| value != "unset");
+
+    properties
+}
+
+/// Apply the indentation, line ending and final newline `properties` to
+/// `text`.
+///
+/// Only the whitespace indenting lines is converted between tabs and spaces,
+/// alignment inside lines is left as is.
+pub fn apply(text: &str, properties: &BTreeMap<String, String>) -> String {
+    let indent_size = properties
+        .get("indent_size")
+        .and_then(|size| size.parse::<usize>().ok());
+
+    let tab_width = properties
+        .get("tab_width")
+        .and_then(|width| width.parse::<usize>().ok())
+        .or(indent_size)
+        .unwrap_or(8)
+        .max(1);
+
+    let indent_size = indent_size.unwrap_or(tab_width).max(1);
+
+    let end_of_line = match properties.get("end_of_line").map(String::as_str) {
+        Some("lf") => Some("\n"),
+        Some("crlf") => Some("\r\n"),
+        Some("cr") => Some("\r"),
+        _ => None,
+    };
+
+    let mut lines = split_lines(text);
+
+    for (line, ending) in &mut lines {
+        if let Some(style) = properties.get("indent_style") {
+            *line = reindent(line, style, indent_size, tab_width);
+        }
+
+        if let (Some(end_of_line), false) = (end_of_line, ending.is_empty()) {
+            *ending = end_of_line.to_string();
+        }
+    }
+
+    match properties.get("insert_final_newline").map(String::as_str) {
+        Some("true") => {
+            if let Some((line, ending)) = lines.last_mut() {
+                if ending.is_empty() && !line.is_empty() {
+                    *ending = end_of_line.unwrap_or("\n").to_string();
+                }
+            }
+        }
+        Some("false") => {
+            if let Some((_, ending)) = lines.last_mut() {
+                ending.clear();
+            }
+        }
+        _ => {}
+    }
+
+    lines
+        .into_iter()
+        .map(|(line, ending)| format!("{}{}", line, ending))
+        .collect()
+}
+
+/// Split `text` in lines along with their line ending, empty for the last
+/// line if it doesn't end with one.
+fn split_lines(text: &str) -> Vec<(String, String)> {
+    let mut lines = Vec::new();
+
+    let mut rest = text;
+
+    while let Some(index) = rest.find(['\n', '\r']) {
+        let ending = if rest[index..].starts_with("\r\n") {
+            "\r\n"
+        } else {
+            &rest[index..index + 1]
+        };
+
+        lines.push((rest[..index].to_string(), ending.to_string()));
+
+        rest = &rest[index + ending.len()..];
+    }
+
+    if !rest.is_empty() {
+        lines.push((rest.to_string(), String::new()));
+    }
+
+    lines
+}
+
+/// Indent `line` with tabs or spaces, keeping the width of its indentation.
+fn reindent(line: &str, style: &str, indent_size: usize, tab_width: usize) -> String {
+    let content = line.trim_start_matches([' ', '\t']);
+
+    let indentation = &line[..line.len() - content.len()];
+
+    let width = indentation.chars().fold(0, |width, c| match c {
+        '\t' => (width / tab_width + 1) * tab_width,
+        _ => width + 1,
+    });
+
+    let indentation = match style {
+        "space" => " ".repeat(width),
+        "tab" => {
+            // indentation levels become tabs, what is left aligns with spaces
+            format!(
+                "{}{}",
+                "\t".repeat(width / indent_size),
+                " ".repeat(width % indent_size)
+            )
+        }
+        _ => return line.to_string(),
+    };
+
+    format!("{}{}", indentation, content)
+}
+
+/// Apply the `.editorconfig` rules to the rendered text files of `plan`, to be
+/// generated in `output`.
+pub fn apply_editorconfig(plan: &mut Plan, output: &Path) {
+    let generated = plan
+        .file(EDITORCONFIG_FILENAME)
+        .and_then(|file| String::from_utf8(file.contents.clone()).ok());
+
+    let configs = editorconfigs(output, generated.as_deref());
+
+    if configs.iter().all(|config| config.sections.is_empty()) {
+        return;
+    }
+
+    let root = configs
+        .last()
+        .map(|config| config.directory.clone())
+        .unwrap_or_default();
+
+    for file in &mut plan.files {
+        // blank files and assets are left alone
+        if matches!(file.kind, FileKind::Blank | FileKind::Asset) {
+            continue;
+        }
+
+        let properties = properties(&configs, &root.join(&file.path));
+
+        if properties.is_empty() {
+            continue;
+        }
+
+        if let Ok(text) = std::str::from_utf8(&file.contents) {
+            file.contents = apply(text, &properties).into_bytes();
+        }
+    }
+}
@@ -0,0 +1,691 @@
+//! Fetching remote templates.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use git2::build::{CheckoutBuilder, RepoBuilder};
+use git2::{AutotagOption, Cred, FetchOptions, RemoteCallbacks};
+use serde::Deserialize;
+use tempdir::TempDir;
+use tracing::warn;
+use url::Url;
+
+use crate::archive::{extract_matching, tarball_commit, template_root};
+use crate::cache::{modification_date, repository_cache_path};
+use crate::constants::{
+    BITBUCKET_URL, CODEBERG_URL, GITLAB_URL, MONOREPO_TEMPLATES_DIRECTORY, PIIGNORE_FILENAME,
+    TEMPLATE_FILENAME, TEMPLATE_FILENAMES,
+};
+use crate::http::HttpFetcher;
+use crate::manifest::{template_checksum, TemplateSource};
+use crate::piignore::template_files;
+use crate::pins::{check_pin, pinned_checksum};
+use crate::signature::check_signature;
+use crate::tls::git_certificate_check;
+use crate::types::{is_template, Config, Project};
+
+/// Fetch depth turning a shallow clone into a complete one, libgit2's
+/// `GIT_FETCH_DEPTH_UNSHALLOW`.
+const GIT_FETCH_DEPTH_UNSHALLOW: i32 = i32::MAX;
+
+/// Prefix selecting a GitHub gist instead of a repository, e.g. `gist:ID`.
+pub const GIST_PREFIX: &str = "gist:";
+
+/// A template available on the local filesystem, possibly in a temporary
+/// directory removed when this is dropped.
+#[derive(Debug)]
+pub struct FetchedTemplate {
+    _directory: Option<TempDir>,
+    path: PathBuf,
+    commit: Option<String>,
+}
+
+impl FetchedTemplate {
+    /// Directory containing the template.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Commit the template was fetched at, when it is known.
+    pub fn commit(&self) -> Option<&str> {
+        self.commit.as_deref()
+    }
+
+    /// Returns `source` with its rev replaced by the commit the template was
+    /// fetched at, so that fetching it again yields the same template.
+    pub fn resolved_source(&self, source: &TemplateSource) -> TemplateSource {
+        match (source, &self.commit) {
+            (TemplateSource::Git { url, template, .. }, Some(commit)) => TemplateSource::Git {
+                url: url.clone(),
+                rev: Some(commit.clone()),
+                template: template.clone(),
+            },
+            (source, _) => source.clone(),
+        }
+    }
+
+    /// Read the template's `template.toml`.
+    ///
+    /// Gists can't contain directories, so when a gist template doesn't list
+    /// its templates, every other file of the gist is one.
+    pub fn project(&self, source: &TemplateSource) -> Project {
+        let mut project = Project::from_path(&self.path, &self.path);
+
+        if let TemplateSource::Gist { .. } = source {
+            if project.files.templates.is_none() {
+                let templates = template_files(&self.path)
+                    .into_iter()
+                    .filter(|name| {
+                        name.as_os_str() != PIIGNORE_FILENAME
+                            && !TEMPLATE_FILENAMES
+                                .iter()
+                                .any(|file| name.as_os_str() == *file)
+                    })
+                    .collect::<Vec<PathBuf>>();
+
+                project.files.templates = Some(templates);
+            }
+        }
+
+        project
+    }
+}
+
+/// Parse a remote template given on the command line: `gist:ID` for a gist,
+/// `USER/REPO` for a GitHub repository, or `gl:`, `bb:` and `cb:` followed by
+/// `USER/REPO` for GitLab, Bitbucket and Codeberg, optionally at the git `rev`.
+///
+/// Repositories holding several templates take the template's name after a
+/// colon, as in `USER/REPO:TEMPLATE`.
+pub fn parse_remote(
+    value: &str,
+    rev: Option<String>,
+    config: &Config,
+) -> Result<TemplateSource, url::ParseError> {
+    if let Some(id) = value.strip_prefix(GIST_PREFIX) {
+        return Ok(TemplateSource::Gist { id: id.to_string() });
+    }
+
+    let (host, repository): (Url, &str) = match value.split_once(':') {
+        Some(("gl", repository)) => (GITLAB_URL.clone(), repository),
+        Some(("bb", repository)) => (BITBUCKET_URL.clone(), repository),
+        Some(("cb", repository)) => (CODEBERG_URL.clone(), repository),
+        _ => (config.github_url(), value),
+    };
+
+    // a template name never contains a slash, unlike the rest of a url
+    let (repository, template) = match repository.rsplit_once(':') {
+        Some((repository, template)) if !template.contains('/') => {
+            (repository, Some(template.to_string()))
+        }
+        _ => (repository, None),
+    };
+
+    Ok(TemplateSource::Git {
+        url: host.join(repository)?,
+        rev,
+        template,
+    })
+}
+
+/// Compare repository urls, ignoring a trailing slash or `.git`.
+pub fn same_repository(left: &Url, right: &Url) -> bool {
+    let normalize = |url: &Url| {
+        url.as_str()
+            .trim_end_matches('/')
+            .trim_end_matches(".git")
+            .to_string()
+    };
+
+    normalize(left) == normalize(right)
+}
+
+/// Extensions of the archive formats templates can be distributed as.
+pub const ARCHIVE_EXTENSIONS: &[&str] = &[".zip", ".tar.gz", ".tgz", ".tar"];
+
+/// Returns the file name of the archive at `path` without its extension, if
+/// it is named like an archive.
+pub fn archive_stem<P: AsRef<Path>>(path: P) -> Option<String> {
+    let file_name = path.as_ref().file_name()?.to_str()?;
+
+    ARCHIVE_EXTENSIONS
+        .iter()
+        .find_map(|extension| file_name.strip_suffix(extension))
+        .filter(|stem| !stem.is_empty())
+        .map(str::to_string)
+}
+
+/// Returns the url of `template` if it points to an archive, either served
+/// over HTTP or a local file, rather than to a local directory.
+pub fn parse_archive_url<P: AsRef<Path>>(template: P) -> Option<Url> {
+    if template.as_ref().is_file() && archive_stem(&template).is_some() {
+        return Url::from_file_path(template.as_ref().canonicalize().ok()?).ok();
+    }
+
+    let url = template.as_ref().to_str()?.parse::<Url>().ok()?;
+
+    match url.scheme() {
+        "http" | "https" => Some(url),
+        _ => None,
+    }
+}
+
+/// Make the template described by `source` available locally, refusing it
+/// when it doesn't pass [`check_fetched`].
+pub async fn fetch_template(
+    source: &TemplateSource,
+    config: &Config,
+    fetcher: &dyn HttpFetcher,
+) -> Result<FetchedTemplate, Box<dyn std::error::Error>> {
+    let template = fetch_source(source, config, fetcher).await?;
+
+    check_fetched(&template, source, config, fetcher).await?;
+
+    Ok(template)
+}
+
+/// Check the `template` fetched from `source` against its pinned checksum,
+/// if any, and against its signature, according to the `signatures`
+/// configuration.
+pub async fn check_fetched(
+    template: &FetchedTemplate,
+    source: &TemplateSource,
+    config: &Config,
+    fetcher: &dyn HttpFetcher,
+) -> Result<(), String> {
+    if pinned_checksum(config, source).is_some() {
+        let checksum = template_checksum(template.path())
+            .map_err(|error| format!("Couldn't compute the template's checksum: {}", error))?;
+
+        check_pin(config, source, &checksum)?;
+    }
+
+    check_signature(config, source, template.path(), fetcher).await
+}
+
+async fn fetch_source(
+    source: &TemplateSource,
+    config: &Config,
+    fetcher: &dyn HttpFetcher,
+) -> Result<FetchedTemplate, Box<dyn std::error::Error>> {
+    match source {
+        TemplateSource::Path { path } => Ok(FetchedTemplate {
+            _directory: None,
+            path: path.clone(),
+            commit: None,
+        }),
+        TemplateSource::Git { url, rev, template } => {
+            let mut repository =
+                fetch_repository(url, rev.as_deref(), template.as_deref(), config, fetcher).await?;
+
+            if let Some(template) = template {
+                let templates = monorepo_templates(&repository.path);
+
+                if !templates.contains(template) {
+                    return Err(format!(
+                        "No template named {} in {}, available templates: {}",
+                        template,
+                        url,
+                        templates.join(", ")
+                    )
+                    .into());
+                }
+
+                repository.path = repository
+                    .path
+                    .join(MONOREPO_TEMPLATES_DIRECTORY)
+                    .join(template);
+            }
+
+            Ok(repository)
+        }
+        TemplateSource::Archive { url } => download_archive(url, None, fetcher).await,
+        TemplateSource::Gist { id } => {
+            let directory = download_gist(id, config, fetcher).await?;
+
+            Ok(FetchedTemplate {
+                path: directory.path().to_path_buf(),
+                _directory: Some(directory),
+                commit: None,
+            })
+        }
+    }
+}
+
+/// Make the git repository at `url` available locally, at `rev` if given.
+///
+/// Repositories already cloned in the cache are only fetched. Others are
+/// downloaded as tarballs from GitHub, and cloned in the cache otherwise.
+/// When only the monorepo `template` is needed, only its directory is checked
+/// out or extracted, see [`sparse_paths`].
+async fn fetch_repository(
+    url: &Url,
+    rev: Option<&str>,
+    template: Option<&str>,
+    config: &Config,
+    fetcher: &dyn HttpFetcher,
+) -> Result<FetchedTemplate, Box<dyn std::error::Error>> {
+    let token = config.github_token_for(url);
+
+    let paths = sparse_paths(template);
+
+    let mut cache_path = repository_cache_path(url);
+
+    if let Some(cached) = cache_path
+        .as_ref()
+        .filter(|path| path.join(".git").is_dir())
+    {
+        match update_cached_repository(url, rev, token.as_deref(), &paths, cached) {
+            Ok(commit) => {
+                return Ok(FetchedTemplate {
+                    _directory: None,
+                    path: cached.clone(),
+                    commit: Some(commit),
+                })
+            }
+            Err(error) => {
+                warn!("Couldn't use the cached clone of {}: {}", url, error);
+
+                cache_path = None;
+            }
+        }
+    }
+
+    // GitHub serves repositories as tarballs, much faster than a clone
+    if let Some(tarball_url) = github_tarball_url(url, rev, config) {
+        // tarballs wrap the repository in a directory named after the commit
+        let keep = |path: &Path| {
+            let mut components = path.components();
+
+            components.next();
+
+            paths.is_empty() || is_sparse_path(components.as_path(), &paths)
+        };
+
+        match download_archive_matching(&tarball_url, token.as_deref(), fetcher, &keep).await {
+            Ok(template) => return Ok(template),
+            Err(error) => warn!("{}, falling back to git clone", error),
+        }
+    }
+
+    if let Some(cache_path) = cache_path {
+        let commit = update_cached_repository(url, rev, token.as_deref(), &paths, &cache_path)?;
+
+        return Ok(FetchedTemplate {
+            _directory: None,
+            path: cache_path,
+            commit: Some(commit),
+        });
+    }
+
+    let directory = clone_repository(url, rev, token.as_deref(), &paths)?;
+
+    let commit = git2::Repository::open(directory.path())
+        .and_then(|repository| Ok(repository.head()?.peel_to_commit()?.id()))
+        .map(|commit| commit.to_string())
+        .ok();
+
+    Ok(FetchedTemplate {
+        path: directory.path().to_path_buf(),
+        _directory: Some(directory),
+        commit,
+    })
+}
+
+/// Paths of a repository to check out when only the monorepo `template` is
+/// needed: its directory, and the manifests of the others to list them if
+/// it doesn't exist. Empty when the whole repository is needed.
+///
+/// Monorepos can be gigabytes, while a template is usually a few files. git
+/// can't fetch part of a commit, so clones only get the last commit, whole,
+/// while GitHub tarballs only keep these paths.
+pub fn sparse_paths(template: Option<&str>) -> Vec<String> {
+    match template {
+        Some(template) => std::iter::once(format!("{}/{}", MONOREPO_TEMPLATES_DIRECTORY, template))
+            .chain(
+                TEMPLATE_FILENAMES
+                    .iter()
+                    .map(|name| format!("{}/*/{}", MONOREPO_TEMPLATES_DIRECTORY, name)),
+            )
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Returns `true` if `path`, relative to the root of a repository, is one of
+/// the [`sparse_paths`] or inside of one.
+fn is_sparse_path(path: &Path, paths: &[String]) -> bool {
+    let components = path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>();
+
+    paths.iter().any(|sparse| {
+        let patterns = sparse.split('/').collect::<Vec<_>>();
+
+        components.len() >= patterns.len()
+            && patterns
+                .iter()
+                .zip(&components)
+                .all(|(pattern, component)| *pattern == "*" || pattern == component)
+    })
+}
+
+/// Checkout options restricted to `paths` unless empty, forced so that the
+/// checked out files always match the commit.
+fn checkout_builder(paths: &[String]) -> CheckoutBuilder<'_> {
+    let mut checkout = CheckoutBuilder::new();
+
+    checkout.force();
+
+    for path in paths {
+        checkout.path(path);
+    }
+
+    checkout
+}
+
+/// Clone the repository at `url` in `path`, or fetch it if it was already
+/// cloned there, then check out `rev`, or the default branch, and return the
+/// checked out commit.
+///
+/// Only `paths` are checked out if given, see [`sparse_paths`].
+pub fn update_cached_repository(
+    url: &Url,
+    rev: Option<&str>,
+    token: Option<&str>,
+    paths: &[String],
+    path: &Path,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let repository = if path.join(".git").is_dir() {
+        let repository = git2::Repository::open(path)?;
+
+        let mut options = fetch_options(token, paths);
+
+        options.download_tags(AutotagOption::All);
+
+        let fetched = repository.find_remote("origin").and_then(|mut remote| {
+            remote.fetch(
+                &["+refs/heads/*:refs/remotes/origin/*"],
+                Some(&mut options),
+                None,
+            )
+        });
+
+        // work offline from the last fetch
+        if let Err(error) = fetched {
+            let date = modification_date(path.join(".git").join("FETCH_HEAD"))
+                .or_else(|| modification_date(path.join(".git")))
+                .unwrap_or_else(|| String::from("an unknown date"));
+
+            warn!(
+                "Failed to fetch repository at {}: {}, using cached version from {}",
+                url,
+                error.message(),
+                date
+            );
+        }
+
+        repository
+    } else {
+        // start over from a partial clone
+        if path.exists() {
+            fs::remove_dir_all(path)?;
+        }
+
+        fs::create_dir_all(path)?;
+
+        RepoBuilder::new()
+            .fetch_options(fetch_options(token, paths))
+            .with_checkout(checkout_builder(paths))
+            .clone(url.as_str(), path)
+            .map_err(|error| format!("Failed to clone repository at {}: {}", url, error))?
+    };
+
+    // branches only exist as remote branches after a fetch
+    let object = match rev {
+        Some(rev) => repository
+            .revparse_single(&format!("origin/{}", rev))
+            .or_else(|_| revparse(&repository, rev, token))
+            .map_err(|error| format!("Couldn't find revision {} in {}: {}", rev, url, error))?,
+        None => repository.revparse_single("origin/HEAD")?,
+    };
+
+    let commit = object.peel_to_commit()?;
+
+    repository.set_head_detached(commit.id())?;
+
+    repository.checkout_head(Some(checkout_builder(paths).remove_untracked(true)))?;
+
+    Ok(commit.id().to_string())
+}
+
+/// Names of the templates in the `templates/` directory of the repository in
+/// `root`, empty if it doesn't hold several templates.
+pub fn monorepo_templates<P: AsRef<Path>>(root: P) -> Vec<String> {
+    let mut templates = fs::read_dir(root.as_ref().join(MONOREPO_TEMPLATES_DIRECTORY))
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| is_template(entry.path()))
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .collect::<Vec<String>>()
+        })
+        .unwrap_or_default();
+
+    templates.sort();
+
+    templates
+}
+
+/// Returns the url of the GitHub API serving the repository at `url` as a
+/// tarball, if it is hosted on GitHub.
+pub fn github_tarball_url(url: &Url, rev: Option<&str>, config: &Config) -> Option<Url> {
+    if url.host_str() != config.github_url().host_str() {
+        return None;
+    }
+
+    let repository = url.path().trim_matches('/').trim_end_matches(".git");
+
+    let path = match rev {
+        Some(rev) => format!("repos/{}/tarball/{}", repository, rev),
+        None => format!("repos/{}/tarball", repository),
+    };
+
+    config.github_api_url().join(&path).ok()
+}
+
+/// Clone the git repository at `url` in a temporary directory, removed when
+/// the returned [`TempDir`] is dropped, and check out `rev` if given.
+///
+/// Only `paths` are checked out if given, see [`sparse_paths`].
+pub fn clone_repository(
+    url: &Url,
+    rev: Option<&str>,
+    token: Option<&str>,
+    paths: &[String],
+) -> Result<TempDir, Box<dyn std::error::Error>> {
+    // name the temporary directory after the repository
+    let dir_name = url.path().trim_matches('/').replace('/', "-");
+
+    let tmp_directory = TempDir::new(&dir_name)
+        .map_err(|error| format!("Failed to create temporary directory: {}", error))?;
+
+    let repository = RepoBuilder::new()
+        .fetch_options(fetch_options(token, paths))
+        .with_checkout(checkout_builder(paths))
+        .clone(url.as_str(), tmp_directory.path())
+        .map_err(|error| format!("Failed to clone repository at {}: {}", url, error))?;
+
+    if let Some(rev) = rev {
+        let object = revparse(&repository, rev, token)
+            .map_err(|error| format!("Couldn't find revision {} in {}: {}", rev, url, error))?;
+
+        repository.checkout_tree(&object, Some(&mut checkout_builder(paths)))?;
+
+        repository.set_head_detached(object.peel_to_commit()?.id())?;
+    }
+
+    Ok(tmp_directory)
+}
+
+/// Find `rev` in `repository`, fetching the whole history of shallow clones
+/// when it isn't among the fetched commits, like an older pinned commit.
+fn revparse<'r>(
+    repository: &'r git2::Repository,
+    rev: &str,
+    token: Option<&str>,
+) -> Result<git2::Object<'r>, git2::Error> {
+    let found = repository.revparse_single(rev);
+
+    if found.is_ok() || !repository.is_shallow() {
+        return found;
+    }
+
+    let mut options = fetch_options(token, &[]);
+
+    options
+        .depth(GIT_FETCH_DEPTH_UNSHALLOW)
+        .download_tags(AutotagOption::All);
+
+    repository.find_remote("origin")?.fetch(
+        &["+refs/heads/*:refs/remotes/origin/*"],
+        Some(&mut options),
+        None,
+    )?;
+
+    repository.revparse_single(rev)
+}
+
+/// Options for git fetches, authenticated with `token` over HTTPS if given,
+/// following the TLS options of the configuration.
+///
+/// Only the last commit is fetched when only `paths` are checked out, see
+/// [`sparse_paths`].
+fn fetch_options<'a>(token: Option<&'a str>, paths: &[String]) -> FetchOptions<'a> {
+    let mut options = FetchOptions::new();
+
+    if !paths.is_empty() {
+        options.depth(1);
+    }
+
+    let mut callbacks = RemoteCallbacks::new();
+
+    if let Some(token) = token {
+        callbacks.credentials(move |_url, _username, _allowed| {
+            Cred::userpass_plaintext("x-access-token", token)
+        });
+    }
+
+    git_certificate_check(&mut callbacks);
+
+    options.remote_callbacks(callbacks);
+
+    options
+}
+
+/// Download the archive at `url`, authenticated with the bearer `token` if
+/// given, and extract it in a temporary directory.
+pub async fn download_archive(
+    url: &Url,
+    token: Option<&str>,
+    fetcher: &dyn HttpFetcher,
+) -> Result<FetchedTemplate, Box<dyn std::error::Error>> {
+    download_archive_matching(url, token, fetcher, &|_| true).await
+}
+
+/// Download the archive at `url` as [`download_archive`] does, extracting
+/// only the entries `keep` accepts.
+async fn download_archive_matching(
+    url: &Url,
+    token: Option<&str>,
+    fetcher: &dyn HttpFetcher,
+    keep: &dyn Fn(&Path) -> bool,
+) -> Result<FetchedTemplate, Box<dyn std::error::Error>> {
+    let bytes = if url.scheme() == "file" {
+        let path = url
+            .to_file_path()
+            .map_err(|_| format!("Invalid archive path {}", url))?;
+
+        fs::read(&path).map_err(|error| format!("Failed to read {}: {}", url, error))?
+    } else {
+        fetcher
+            .get_with_token(url, token)
+            .await
+            .map_err(|error| format!("Failed to download {}: {}", url, error))?
+    };
+
+    let tmp_directory = TempDir::new("pi-archive")
+        .map_err(|error| format!("Failed to create temporary directory: {}", error))?;
+
+    extract_matching(&bytes, tmp_directory.path(), keep)
+        .map_err(|error| format!("Failed to extract {}: {}", url, error))?;
+
+    Ok(FetchedTemplate {
+        path: template_root(tmp_directory.path()),
+        _directory: Some(tmp_directory),
+        commit: tarball_commit(&bytes),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct Gist {
+    files: BTreeMap<String, GistFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GistFile {
+    raw_url: Url,
+    #[serde(default)]
+    truncated: bool,
+    content: Option<String>,
+}
+
+/// Download every file of the gist `id` in a temporary directory.
+pub async fn download_gist(
+    id: &str,
+    config: &Config,
+    fetcher: &dyn HttpFetcher,
+) -> Result<TempDir, Box<dyn std::error::Error>> {
+    let gist_url = config.github_api_url().join(&format!("gists/{}", id))?;
+
+    let body = fetcher
+        .get_with_token(&gist_url, config.github_token().as_deref())
+        .await
+        .map_err(|error| format!("Failed to download gist {}: {}", id, error))?;
+
+    let gist: Gist = serde_json::from_slice(&body)
+        .map_err(|error| format!("Unexpected response for gist {}: {}", id, error))?;
+
+    if !TEMPLATE_FILENAMES
+        .iter()
+        .any(|name| gist.files.contains_key(*name))
+    {
+        return Err(format!("Gist {} doesn't contain a {}", id, TEMPLATE_FILENAME).into());
+    }
+
+    let tmp_directory = TempDir::new(&format!("gist-{}", id))
+        .map_err(|error| format!("Failed to create temporary directory: {}", error))?;
+
+    for (filename, file) in gist.files {
+        // gist file names can't contain directories, but don't trust the API
+        let filename = Path::new(&filename)
+            .file_name()
+            .ok_or_else(|| format!("Invalid file name {} in gist {}", filename, id))?
+            .to_owned();
+
+        let contents = match file.content {
+            // large files are truncated in the API response
+            Some(content) if !file.truncated => content.into_bytes(),
+            _ => fetcher
+                .get(&file.raw_url)
+                .await
+                .map_err(|error| format!("Failed to download {}: {}", file.raw_url, error))?,
+        };
+
+        fs::write(tmp_directory.path().join(filename), contents)?;
+    }
+
+    Ok(tmp_directory)
+}
@@ -0,0 +1,247 @@
+//! Running a template against the fixtures bundled with it (`pi test`).
+//!
+//! Fixtures live in the `tests/` directory of a template, each `tests/*.toml`
+//! file is a set of answers used to generate the template in a temporary
+//! directory. When a `tests/<FIXTURE>/` directory exists next to the fixture
+//! it holds the expected output, which must match the generated project.
+//! Otherwise the fixture only checks that every placeholder was resolved.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use tempdir::TempDir;
+use toml::value::{Table, Value};
+use walkdir::WalkDir;
+
+use crate::constants::VERSION_CONTROL_DIRECTORIES;
+use crate::render::unresolved_keys;
+use crate::types::{Config, Project};
+use crate::util::{init_helper, template_keys, InitOptions};
+
+/// Directory, relative to the template, containing the fixtures.
+pub const FIXTURES_DIRECTORY: &str = "tests";
+
+/// A set of answers used to generate a template.
+#[derive(Debug, Deserialize)]
+pub struct Fixture {
+    /// Name of the generated project, defaults to the fixture's file name
+    pub name: Option<String>,
+    /// Keys overriding every other key when rendering the template
+    #[serde(default)]
+    pub keys: Table,
+}
+
+impl Fixture {
+    /// Returns the keys of the fixture as strings.
+    pub fn string_keys(&self) -> Vec<(String, String)> {
+        self.keys
+            .iter()
+            .map(|(key, value)| match value {
+                Value::String(value) => (key.clone(), value.clone()),
+                value => (key.clone(), value.to_string()),
+            })
+            .collect()
+    }
+}
+
+/// Run every fixture of the template in `directory`, printing a report, and
+/// return whether all of them passed.
+pub fn run_fixtures<H: AsRef<Path>, D: AsRef<Path>>(home: H, directory: D) -> bool {
+    let template_path = Project::from_path(&home, &directory).path;
+
+    let fixtures_path = template_path.join(FIXTURES_DIRECTORY);
+
+    let mut fixtures = match fs::read_dir(&fixtures_path) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "toml"))
+            .collect::<Vec<PathBuf>>(),
+        Err(_error) => {
+            println!("No fixtures found in {}", fixtures_path.to_string_lossy());
+
+            return false;
+        }
+    };
+
+    fixtures.sort();
+
+    println!(
+        "running {} fixture(s) for template {}",
+        fixtures.len(),
+        template_path.to_string_lossy()
+    );
+
+    let mut failed = 0;
+
+    for fixture_path in &fixtures {
+        let fixture_name = fixture_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let failures = run_fixture(&home, &directory, fixture_path);
+
+        if failures.is_empty() {
+            println!("fixture {} ... ok", fixture_name);
+        } else {
+            failed += 1;
+
+            println!("fixture {} ... FAILED", fixture_name);
+
+            for failure in failures {
+                println!("  - {}", failure);
+            }
+        }
+    }
+
+    println!(
+        "test result: {}. {} passed; {} failed",
+        if failed == 0 { "ok" } else { "FAILED" },
+        fixtures.len() - failed,
+        failed
+    );
+
+    failed == 0
+}
+
+/// Generate the template for a single fixture, returning the list of
+/// failures.
+fn run_fixture<H: AsRef<Path>, D: AsRef<Path>>(
+    home: H,
+    directory: D,
+    fixture_path: &Path,
+) -> Vec<String> {
+    let fixture: Fixture = match fs::read_to_string(fixture_path)
+        .map_err(|error| error.to_string())
+        .and_then(|content| toml::from_str(&content).map_err(|error| error.to_string()))
+    {
+        Ok(fixture) => fixture,
+        Err(error) => return vec![format!("invalid fixture: {}", error)],
+    };
+
+    let fixture_name = fixture_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let name = fixture.name.clone().unwrap_or_else(|| fixture_name.clone());
+
+    let keys = fixture.string_keys();
+
+    // fixtures don't depend on the user's global configuration
+    let config = Config::default();
+
+    let project = match Project::read(&home, &directory) {
+        Ok(project) => project,
+        Err(error) => return vec![error],
+    };
+
+    let mut failures = unresolved_in_project(&name, &config, &project, &keys);
+
+    let tmp_directory = match TempDir::new("pi-test") {
+        Ok(tmp_directory) => tmp_directory,
+        Err(error) => return vec![format!("couldn't create temporary directory: {}", error)],
+    };
+
+    let options = InitOptions {
+        output: tmp_directory.path().join(&name),
+        force: true,
+        keys,
+        ..Default::default()
+    };
+
+    if let Err(error) = init_helper(&name, &config, &project, &options) {
+        failures.push(format!("generation failed: {}", error));
+
+        return failures;
+    }
+
+    let expected_path = fixture_path.with_file_name(&fixture_name);
+
+    if expected_path.is_dir() {
+        failures.extend(compare_directories(&expected_path, &options.output));
+    }
+
+    failures
+}
+
+/// Returns a failure for each placeholder without a value in the file names
+/// and templates of `project`.
+fn unresolved_in_project(
+    name: &str,
+    config: &Config,
+    project: &Project,
+    keys: &[(String, String)],
+) -> Vec<String> {
+    let mut hash = match template_keys(name, config, project, keys) {
+        Ok(hash) => hash,
+        Err(error) => return vec![error],
+    };
+
+    // `files` is only known once files are created, but always exists
+    hash = hash.insert("files", "");
+
+    unresolved_keys(project, &hash)
+        .into_iter()
+        .map(|unresolved| {
+            format!(
+                "unresolved key '{}' in {} {}",
+                unresolved.key,
+                if unresolved.in_path {
+                    "path"
+                } else {
+                    "template"
+                },
+                unresolved.path.to_string_lossy()
+            )
+        })
+        .collect()
+}
+
+/// Compare the files of the `expected` and `actual` directories, returning
+/// one failure per difference.
+fn compare_directories(expected: &Path, actual: &Path) -> Vec<String> {
+    let expected_files = relative_files(expected);
+
+    let actual_files = relative_files(actual);
+
+    let mut failures = Vec::new();
+
+    for file in &expected_files {
+        if !actual_files.contains(file) {
+            failures.push(format!("missing file {}", file.to_string_lossy()));
+        } else if fs::read(expected.join(file)).ok() != fs::read(actual.join(file)).ok() {
+            failures.push(format!(
+                "file {} differs from the expected output",
+                file.to_string_lossy()
+            ));
+        }
+    }
+
+    for file in &actual_files {
+        if !expected_files.contains(file) {
+            failures.push(format!("unexpected file {}", file.to_string_lossy()));
+        }
+    }
+
+    failures
+}
+
+/// List the files in `root`, relative to it, skipping version control
+/// directories.
+fn relative_files(root: &Path) -> Vec<PathBuf> {
+    WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            !VERSION_CONTROL_DIRECTORIES
+                .iter()
+                .any(|directory| entry.file_name() == *directory)
+        })
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.path().strip_prefix(root).ok().map(Path::to_path_buf))
+        .collect()
+}
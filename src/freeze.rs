@@ -0,0 +1,295 @@
+//! `pi freeze`, turning an existing project into a template.
+//!
+//! The files of the project are copied, the name of the project, the
+//! author's name and email and the current year being replaced by the keys
+//! rendering them back, and a `template.toml` listing them is written.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[cfg(not(target_os = "windows"))]
+use std::os::unix::fs::PermissionsExt;
+
+use case::CaseExt;
+use chrono::Datelike;
+use heck::{ToKebabCase, ToShoutySnakeCase, ToSnakeCase, ToUpperCamelCase};
+use ignore::WalkBuilder;
+use tracing::warn;
+
+use crate::constants::{MANIFEST_DIRECTORY, TEMPLATE_FILENAME, VERSION_CONTROL_DIRECTORIES};
+use crate::types::Config;
+use crate::util::now;
+
+/// Values shorter than this are left alone, they would match everywhere.
+const MIN_REPLACED_LENGTH: usize = 3;
+
+/// What [`freeze`] made of the files of a project.
+#[derive(Debug, Default)]
+pub struct FrozenTemplate {
+    /// Files where values were replaced by keys, rendered when generating
+    pub templates: Vec<PathBuf>,
+    /// Executable templates
+    pub scripts: Vec<PathBuf>,
+    /// Files copied as they are
+    pub assets: Vec<PathBuf>,
+    /// Directories, created before the files
+    pub directories: Vec<PathBuf>,
+}
+
+/// Turn the project in `source`, named `name`, into a template in
+/// `destination`, which must not exist or be empty.
+///
+/// Files ignored by the project's `.gitignore` are left out. Files already
+/// holding mustache tags are copied as they are, since rendering them would
+/// change them.
+pub fn freeze(
+    source: &Path,
+    destination: &Path,
+    name: &str,
+    config: &Config,
+) -> Result<FrozenTemplate, Box<dyn std::error::Error>> {
+    if fs::read_dir(destination).is_ok_and(|mut entries| entries.next().is_some()) {
+        return Err(format!(
+            "{} already exists and isn't empty",
+            destination.to_string_lossy()
+        )
+        .into());
+    }
+
+    fs::create_dir_all(destination)?;
+
+    let replacements = replacements(name, config);
+
+    let replace = |text: &str| replace_values(text, &replacements);
+
+    let destination_path = destination.canonicalize().ok();
+
+    let walker = WalkBuilder::new(source)
+        .hidden(false)
+        .require_git(false)
+        .sort_by_file_name(|left, right| left.cmp(right))
+        .filter_entry(move |entry| {
+            entry.depth() == 0
+                || !(VERSION_CONTROL_DIRECTORIES
+                    .iter()
+                    .chain(&[MANIFEST_DIRECTORY])
+                    .any(|directory| entry.file_name() == *directory)
+                    || entry.path().canonicalize().ok() == destination_path)
+        })
+        .build();
+
+    let mut frozen = FrozenTemplate::default();
+
+    for entry in walker {
+        let entry = entry?;
+
+        let relative = match entry.path().strip_prefix(source) {
+            Ok(relative) if !relative.as_os_str().is_empty() => relative.to_path_buf(),
+            _ => continue,
+        };
+
+        let path = PathBuf::from(replace(&relative.to_string_lossy()));
+
+        let target = destination.join(&path);
+
+        if entry
+            .file_type()
+            .is_some_and(|file_type| file_type.is_dir())
+        {
+            fs::create_dir_all(&target)?;
+
+            frozen.directories.push(path);
+
+            continue;
+        }
+
+        let bytes = fs::read(entry.path())?;
+
+        let text = match String::from_utf8(bytes) {
+            Ok(text) if !text.contains("{{") && !text.contains("}}") => text,
+            Ok(text) => {
+                if replace(&text) != text {
+                    warn!(
+                        "{} already holds mustache tags, copying it without replacing values",
+                        relative.to_string_lossy()
+                    );
+                }
+
+                fs::write(&target, text)?;
+
+                frozen.assets.push(path);
+
+                continue;
+            }
+            Err(error) => {
+                fs::write(&target, error.into_bytes())?;
+
+                frozen.assets.push(path);
+
+                continue;
+            }
+        };
+
+        let replaced = replace(&text);
+
+        let changed = replaced != text || path != relative;
+
+        fs::write(&target, replaced)?;
+
+        if is_executable(entry.path()) {
+            #[cfg(not(target_os = "windows"))]
+            fs::set_permissions(&target, fs::Permissions::from_mode(0o755))?;
+
+            frozen.scripts.push(path);
+        } else if changed {
+            frozen.templates.push(path);
+        } else {
+            frozen.assets.push(path);
+        }
+    }
+
+    fs::write(
+        destination.join(TEMPLATE_FILENAME),
+        template_toml(name, source, &frozen),
+    )?;
+
+    Ok(frozen)
+}
+
+/// Values to replace in the project `name`, along with the key rendering
+/// them, longest first so that the longest value matching is replaced.
+fn replacements(name: &str, config: &Config) -> Vec<(String, &'static str)> {
+    let mut replacements = vec![
+        (name.to_string(), "project"),
+        (name.to_capitalized(), "Project"),
+        (name.to_upper_camel_case(), "ProjectCamelCase"),
+        (name.to_snake_case(), "project_snake"),
+        (name.to_kebab_case(), "project-kebab"),
+        (name.to_shouty_snake_case(), "PROJECT_SHOUTY"),
+        (now().year().to_string(), "year"),
+    ];
+
+    if let Some(ref author) = config.author {
+        replacements.push((author.email.clone(), "email"));
+        replacements.push((author.name.clone(), "name"));
+    }
+
+    let mut unique: Vec<(String, &str)> = Vec::new();
+
+    for (value, key) in replacements {
+        if value.chars().count() >= MIN_REPLACED_LENGTH
+            && !unique.iter().any(|(existing, _)| *existing == value)
+        {
+            unique.push((value, key));
+        }
+    }
+
+    unique.sort_by_key(|(value, _)| std::cmp::Reverse(value.len()));
+
+    unique
+}
+
+/// Replace the values of `replacements` found in `text` by the keys
+/// rendering them. Values are only replaced as whole identifiers, so that a
+/// project named `core` leaves `score` alone.
+fn replace_values(text: &str, replacements: &[(String, &str)]) -> String {
+    let is_identifier = |character: char| character.is_alphanumeric() || character == '_';
+
+    let mut replaced = String::with_capacity(text.len());
+
+    let mut rest = text;
+
+    let mut previous = None;
+
+    while let Some(character) = rest.chars().next() {
+        // values can only start at the beginning of an identifier
+        let found = if previous.is_some_and(is_identifier) {
+            None
+        } else {
+            replacements.iter().find(|(value, _)| {
+                rest.strip_prefix(value.as_str())
+                    .is_some_and(|after| !after.chars().next().is_some_and(is_identifier))
+            })
+        };
+
+        match found {
+            Some((value, key)) => {
+                replaced.push_str(&format!("{{{{ {} }}}}", key));
+
+                previous = value.chars().last();
+
+                rest = &rest[value.len()..];
+            }
+            None => {
+                replaced.push(character);
+
+                previous = Some(character);
+
+                rest = &rest[character.len_utf8()..];
+            }
+        }
+    }
+
+    replaced
+}
+
+fn is_executable(path: &Path) -> bool {
+    #[cfg(not(target_os = "windows"))]
+    {
+        fs::metadata(path).is_ok_and(|metadata| metadata.permissions().mode() & 0o111 != 0)
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        let _ = path;
+
+        false
+    }
+}
+
+/// `template.toml` listing the files of the `frozen` template.
+fn template_toml(name: &str, source: &Path, frozen: &FrozenTemplate) -> String {
+    let list = |paths: &[PathBuf]| {
+        let paths = paths
+            .iter()
+            .map(|path| toml_string(&path.to_string_lossy().replace('\\', "/")))
+            .collect::<Vec<_>>();
+
+        format!("[{}]", paths.join(", "))
+    };
+
+    format!(
+        r#"# Template frozen from {source} by `pi freeze`, see `man pi` for every option.
+
+[template]
+name = {name}
+description = {description}
+tags = []
+
+[files]
+# directories created, holding the files below or empty
+directories = {directories}
+# files whose name and content are rendered with the keys
+templates = {templates}
+# templates made executable
+scripts = {scripts}
+# files copied as they are, their names are rendered
+assets = {assets}
+
+[config]
+version = "0.1.0"
+"#,
+        source = source.to_string_lossy(),
+        name = toml_string(name),
+        description = toml_string(&format!("Projects like {}", name)),
+        directories = list(&frozen.directories),
+        templates = list(&frozen.templates),
+        scripts = list(&frozen.scripts),
+        assets = list(&frozen.assets),
+    )
+}
+
+/// `value` as a TOML string.
+fn toml_string(value: &str) -> String {
+    toml::Value::String(value.to_string()).to_string()
+}
@@ -0,0 +1,70 @@
+//! Access to the GitHub API.
+
+use serde::Deserialize;
+use url::Url;
+
+use crate::constants::TEMPLATE_TOPIC;
+use crate::http::HttpFetcher;
+use crate::types::{Config, SortOrder};
+
+/// A repository returned by the GitHub search API.
+#[derive(Debug, Deserialize)]
+pub struct GithubRepository {
+    pub full_name: String,
+    pub description: Option<String>,
+    pub stargazers_count: u64,
+    pub html_url: Url,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResults {
+    items: Vec<GithubRepository>,
+}
+
+/// Search GitHub for repositories tagged with the pi template topic and
+/// matching `query` and every topic in `tags`, most starred first unless
+/// another `order` is given.
+pub async fn search_templates(
+    query: &str,
+    tags: &[String],
+    order: Option<SortOrder>,
+    config: &Config,
+    fetcher: &dyn HttpFetcher,
+) -> Result<Vec<GithubRepository>, Box<dyn std::error::Error>> {
+    let mut url = config.github_api_url().join("search/repositories")?;
+
+    let mut qualifiers = format!("{} topic:{}", query, TEMPLATE_TOPIC);
+
+    for tag in tags {
+        qualifiers.push_str(&format!(" topic:{}", tag));
+    }
+
+    url.query_pairs_mut()
+        .append_pair("q", qualifiers.trim_start())
+        .append_pair(
+            "sort",
+            match order {
+                Some(SortOrder::Updated) => "updated",
+                _ => "stars",
+            },
+        )
+        .append_pair("order", "desc");
+
+    let body = fetcher
+        .get_with_token(&url, config.github_token().as_deref())
+        .await
+        .map_err(|error| format!("GitHub search failed: {}", error))?;
+
+    let results: SearchResults = serde_json::from_slice(&body)
+        .map_err(|error| format!("Unexpected response from GitHub: {}", error))?;
+
+    let mut repositories = results.items;
+
+    // the search API can't sort by name
+    if order == Some(SortOrder::Name) {
+        repositories.sort_by_key(|repository| repository.full_name.to_lowercase());
+    }
+
+    Ok(repositories)
+}
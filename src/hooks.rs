@@ -0,0 +1,110 @@
+//! Commands a template runs in the project once it is generated, like
+//! `gh repo create {{github_username}}/{{project}} --private`.
+//!
+//! Hooks are split into words the way a shell would, quotes included, then
+//! the keys of the project are substituted in each word, see
+//! [`Plan::hooks`](crate::plan::Plan::hooks). They run without a shell, so
+//! that a value is always a single argument, whatever it holds.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use tracing::info;
+
+/// A rendered hook: the program to run, then its arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hook {
+    pub words: Vec<String>,
+}
+
+impl fmt::Display for Hook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", shell_words::join(&self.words))
+    }
+}
+
+/// Split `hook` into words and substitute the `keys` of the project in them.
+///
+/// Only plain keys are substituted, unknown keys and sections are an error.
+pub fn render_hook(hook: &str, keys: &BTreeMap<String, String>) -> Result<Hook, String> {
+    let words = shell_words::split(hook)
+        .map_err(|error| format!("Invalid hook {}: {}", hook, error))?
+        .iter()
+        .map(|word| substitute_keys(word, keys))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|error| format!("Invalid hook {}: {}", hook, error))?;
+
+    if words.is_empty() {
+        return Err(String::from("Hooks can't be empty"));
+    }
+
+    Ok(Hook { words })
+}
+
+/// Replace the `{{key}}`, `{{{key}}}` and `{{&key}}` placeholders of `word`
+/// with the values of `keys`, as they are.
+fn substitute_keys(word: &str, keys: &BTreeMap<String, String>) -> Result<String, String> {
+    let mut substituted = String::with_capacity(word.len());
+
+    let mut rest = word;
+
+    while let Some(start) = rest.find("{{") {
+        substituted.push_str(&rest[..start]);
+
+        rest = &rest[start + 2..];
+
+        let (tag, end) = match rest.strip_prefix('{') {
+            Some(triple) => (triple.find("}}}").map(|end| &triple[..end]), 4),
+            None => (rest.find("}}").map(|end| &rest[..end]), 2),
+        };
+
+        let tag = tag.ok_or_else(|| String::from("a placeholder is never closed"))?;
+
+        let name = tag.trim().trim_start_matches('&').trim();
+
+        if name.starts_with(|sigil| "#^/!>=".contains(sigil)) {
+            return Err(format!(
+                "`{{{{{}}}}}` isn't a key, hooks only take keys",
+                tag
+            ));
+        }
+
+        let value = keys
+            .get(name)
+            .ok_or_else(|| format!("no value for key {}", name))?;
+
+        substituted.push_str(value);
+
+        rest = &rest[tag.len() + end..];
+    }
+
+    substituted.push_str(rest);
+
+    Ok(substituted)
+}
+
+/// Run the rendered `hooks` one after the other from `directory`, stopping
+/// at the first failing one.
+///
+/// Their output goes to stderr, so that stdout only holds what pi prints.
+pub fn run_hooks(hooks: &[Hook], directory: &Path) -> Result<(), String> {
+    for hook in hooks {
+        info!("Running {}", hook);
+
+        let status = Command::new(&hook.words[0])
+            .args(&hook.words[1..])
+            .current_dir(directory)
+            .stdout(Stdio::from(io::stderr()))
+            .status()
+            .map_err(|error| format!("Couldn't run hook {}: {}", hook, error))?;
+
+        if !status.success() {
+            return Err(format!("Hook {} failed with {}", hook, status));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,267 @@
+//! HTTP access to remote resources, such as templates repositories.
+//!
+//! Everything that goes over the network does so through the [`HttpFetcher`]
+//! trait, embedders can provide their own implementation (custom auth,
+//! instrumentation, ...) instead of relying on the default reqwest client.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+#[cfg(feature = "reqwest")]
+use crate::tls::pem_certificates;
+use crate::types::Config;
+#[cfg(feature = "reqwest")]
+use crate::types::TlsConfig;
+
+pub type HttpError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Headers identifying a version of a resource, sent back to the server to
+/// only download the resource again if it changed.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Validators {
+    /// Value of the `ETag` header
+    pub etag: Option<String>,
+    /// Value of the `Last-Modified` header
+    pub last_modified: Option<String>,
+}
+
+/// Response to a conditional request.
+#[derive(Debug)]
+pub enum Conditional {
+    /// The resource changed, or the server doesn't support conditional
+    /// requests
+    Modified {
+        body: Vec<u8>,
+        validators: Validators,
+    },
+    /// The resource is unchanged since the version the validators identify
+    NotModified,
+}
+
+/// Minimal interface used to fetch remote resources over HTTP.
+#[async_trait]
+pub trait HttpFetcher: Send + Sync {
+    /// Fetch the resource located at `url` and return its body.
+    async fn get(&self, url: &Url) -> Result<Vec<u8>, HttpError>;
+
+    /// Fetch the resource located at `url`, authenticated with the bearer
+    /// `token` if any, and return its body.
+    async fn get_with_token(&self, url: &Url, token: Option<&str>) -> Result<Vec<u8>, HttpError> {
+        let _ = token;
+
+        self.get(url).await
+    }
+
+    /// Fetch the resource located at `url` unless it is still the version
+    /// identified by `validators`.
+    async fn get_conditional(
+        &self,
+        url: &Url,
+        validators: &Validators,
+    ) -> Result<Conditional, HttpError> {
+        let _ = validators;
+
+        Ok(Conditional::Modified {
+            body: self.get(url).await?,
+            validators: Validators::default(),
+        })
+    }
+
+    /// Send the json document `body` to `url`, authenticated with the bearer
+    /// `token` if any, and return the response's body.
+    async fn post_json(
+        &self,
+        url: &Url,
+        body: Vec<u8>,
+        token: Option<&str>,
+    ) -> Result<Vec<u8>, HttpError> {
+        let _ = (body, token);
+
+        Err(format!("Sending data isn't supported, can't post to {}", url).into())
+    }
+}
+
+/// Default [`HttpFetcher`] implementation, backed by reqwest.
+#[cfg(feature = "reqwest")]
+#[derive(Debug)]
+pub struct ReqwestFetcher {
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "reqwest")]
+impl Default for ReqwestFetcher {
+    fn default() -> Self {
+        // some APIs, like GitHub's, reject requests without a user agent
+        let client = reqwest::Client::builder()
+            .user_agent(concat!("pi/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .unwrap_or_default();
+
+        Self { client }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl From<reqwest::Client> for ReqwestFetcher {
+    fn from(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+#[async_trait]
+impl HttpFetcher for ReqwestFetcher {
+    async fn get(&self, url: &Url) -> Result<Vec<u8>, HttpError> {
+        let response = self
+            .client
+            .get(url.as_str())
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    async fn get_with_token(&self, url: &Url, token: Option<&str>) -> Result<Vec<u8>, HttpError> {
+        let mut request = self.client.get(url.as_str());
+
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+
+        let response = request.send().await?.error_for_status()?;
+
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    async fn get_conditional(
+        &self,
+        url: &Url,
+        validators: &Validators,
+    ) -> Result<Conditional, HttpError> {
+        use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+
+        let mut request = self.client.get(url.as_str());
+
+        if let Some(etag) = &validators.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+
+        if let Some(last_modified) = &validators.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+
+        let response = request.send().await?;
+
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(Conditional::NotModified);
+        }
+
+        let response = response.error_for_status()?;
+
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value: &reqwest::header::HeaderValue| value.to_str().ok())
+                .map(str::to_string)
+        };
+
+        let validators = Validators {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        };
+
+        Ok(Conditional::Modified {
+            body: response.bytes().await?.to_vec(),
+            validators,
+        })
+    }
+
+    async fn post_json(
+        &self,
+        url: &Url,
+        body: Vec<u8>,
+        token: Option<&str>,
+    ) -> Result<Vec<u8>, HttpError> {
+        let mut request = self
+            .client
+            .post(url.as_str())
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body);
+
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+
+        let response = request.send().await?.error_for_status()?;
+
+        Ok(response.bytes().await?.to_vec())
+    }
+}
+
+/// Fetcher used when pi is built without HTTP support, every request fails.
+#[cfg(not(feature = "reqwest"))]
+#[derive(Debug, Default)]
+pub struct DisabledFetcher;
+
+#[cfg(not(feature = "reqwest"))]
+#[async_trait]
+impl HttpFetcher for DisabledFetcher {
+    async fn get(&self, url: &Url) -> Result<Vec<u8>, HttpError> {
+        Err(format!("pi was built without HTTP support, can't fetch {}", url).into())
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl ReqwestFetcher {
+    /// Client trusting the extra root certificates of `tls`, or accepting any
+    /// certificate if it is insecure.
+    pub fn with_tls(tls: &TlsConfig) -> Result<Self, HttpError> {
+        let mut builder = reqwest::Client::builder()
+            .user_agent(concat!("pi/", env!("CARGO_PKG_VERSION")))
+            .danger_accept_invalid_certs(tls.insecure);
+
+        if let Some(ca_bundle) = &tls.ca_bundle {
+            let bundle = std::fs::read(ca_bundle).map_err(|error| {
+                format!(
+                    "Couldn't read CA bundle {}: {}",
+                    ca_bundle.to_string_lossy(),
+                    error
+                )
+            })?;
+
+            for certificate in pem_certificates(&bundle) {
+                builder =
+                    builder.add_root_certificate(reqwest::Certificate::from_pem(&certificate)?);
+            }
+        }
+
+        Ok(Self {
+            client: builder.build()?,
+        })
+    }
+}
+
+/// Returns the [`HttpFetcher`] pi uses when none is provided.
+pub fn default_fetcher() -> Box<dyn HttpFetcher> {
+    #[cfg(feature = "reqwest")]
+    {
+        Box::new(ReqwestFetcher::default())
+    }
+
+    #[cfg(not(feature = "reqwest"))]
+    {
+        Box::new(DisabledFetcher)
+    }
+}
+
+/// Returns the [`HttpFetcher`] following the TLS options of `config`.
+pub fn configured_fetcher(config: &Config) -> Result<Box<dyn HttpFetcher>, HttpError> {
+    match &config.tls {
+        #[cfg(feature = "reqwest")]
+        Some(tls) => Ok(Box::new(ReqwestFetcher::with_tls(tls)?)),
+        _ => Ok(default_fetcher()),
+    }
+}
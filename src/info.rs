@@ -0,0 +1,138 @@
+//! Description of a template, shown by `pi info` before generating anything.
+
+use std::fmt::Display;
+
+use toml::Value;
+
+use crate::types::{Config, CustomKeys, Project};
+use crate::util::check_template;
+
+/// Name the tree of files is rooted at, standing for the generated project.
+const PROJECT_PLACEHOLDER: &str = "<project>";
+
+/// What a template asks for and what it creates.
+#[derive(Debug)]
+pub struct TemplateInfo {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub version: Option<String>,
+    pub tags: Vec<String>,
+    /// Main programming language of generated projects
+    pub language: Option<String>,
+    /// Keys declared by the template, with their default value
+    pub variables: Vec<(String, String)>,
+    /// License of the generated project, from the template or the global
+    /// configuration
+    pub license: Option<String>,
+    /// Version control the generated project is initialized with
+    pub version_control: Option<String>,
+    /// Tools the generated project needs
+    pub requires: Vec<String>,
+    /// Files and directories the template produces, drawn as a tree
+    pub tree: String,
+}
+
+impl TemplateInfo {
+    /// Describe `project`, rendering it in memory to list the files it
+    /// produces. Fails with a report if the template is broken.
+    pub fn from_project(config: &Config, project: &Project) -> Result<Self, String> {
+        let plan = check_template(config, project)?;
+
+        let metadata = project.template.as_ref();
+
+        let mut variables: Vec<(String, String)> = match project.custom_keys {
+            Some(CustomKeys {
+                toml: Value::Table(ref keys),
+            }) => keys
+                .iter()
+                .map(|(key, value)| {
+                    let value = match value {
+                        Value::String(value) => value.clone(),
+                        value => value.to_string(),
+                    };
+
+                    (key.clone(), value)
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        variables.extend(
+            project
+                .variables
+                .iter()
+                .map(|(key, variable)| (key.clone(), variable.default.clone().unwrap_or_default())),
+        );
+
+        let version_control = project
+            .config
+            .as_ref()
+            .and_then(|config| config.version_control.as_ref())
+            .or(config.version_control.as_ref());
+
+        Ok(Self {
+            name: project.template_name(),
+            description: metadata.and_then(|metadata| metadata.description.clone()),
+            version: metadata
+                .and_then(|metadata| metadata.version.clone())
+                .or_else(|| {
+                    project
+                        .config
+                        .as_ref()
+                        .and_then(|config| config.version.clone())
+                }),
+            tags: metadata
+                .map(|metadata| metadata.tags.clone())
+                .unwrap_or_default(),
+            language: metadata.and_then(|metadata| metadata.language.clone()),
+            variables,
+            license: project
+                .license
+                .as_ref()
+                .or(config.license.as_ref())
+                .map(|license| license.to_string()),
+            version_control: version_control.map(|version_control| version_control.to_string()),
+            requires: project.requires.clone(),
+            tree: plan.tree(PROJECT_PLACEHOLDER),
+        })
+    }
+}
+
+impl Display for TemplateInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let unset = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+
+        writeln!(f, "Name: {}", unset(&self.name))?;
+        writeln!(f, "Description: {}", unset(&self.description))?;
+        writeln!(f, "Version: {}", unset(&self.version))?;
+
+        if !self.tags.is_empty() {
+            writeln!(f, "Tags: {}", self.tags.join(", "))?;
+        }
+
+        if let Some(ref language) = self.language {
+            writeln!(f, "Language: {}", language)?;
+        }
+
+        writeln!(f, "License: {}", unset(&self.license))?;
+        writeln!(f, "Version control: {}", unset(&self.version_control))?;
+
+        if !self.requires.is_empty() {
+            writeln!(f, "Requires: {}", self.requires.join(", "))?;
+        }
+
+        if self.variables.is_empty() {
+            writeln!(f, "Variables: -")?;
+        } else {
+            writeln!(f, "Variables:")?;
+
+            for (key, default) in &self.variables {
+                writeln!(f, "  {} = {:?}", key, default)?;
+            }
+        }
+
+        writeln!(f, "Files:")?;
+
+        write!(f, "{}", self.tree)
+    }
+}
@@ -0,0 +1,329 @@
+//! Installing templates in `$HOME/.pi_templates/`, where `pi new` finds them
+//! from anywhere.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use url::Url;
+
+use crate::constants::{GLOBAL_TEMPLATE_DIRECTORY, TEMPLATE_FILENAME};
+use crate::fetch::{
+    archive_stem, check_fetched, download_archive, fetch_template, monorepo_templates,
+    FetchedTemplate,
+};
+use crate::http::HttpFetcher;
+use crate::manifest::{file_checksum, template_checksum, TemplateSource};
+use crate::names::validate_name;
+use crate::pack::PackageManifest;
+use crate::piignore::template_files;
+use crate::provenance::Provenance;
+use crate::types::{is_template, Config, Project};
+use crate::util::check_template;
+use crate::verify::Drift;
+
+/// Fetch the template from `source`, a template directory, an archive or a
+/// remote template, check it, and install it in `$HOME/.pi_templates/` under
+/// `name`, or else the name recorded by `pi pack`, the template's own name, or
+/// the name of its source.
+///
+/// Only the files of the template are copied, leaving out version control
+/// directories and the files its `.piignore` matches, and its source is
+/// recorded for `pi update`. An installed template with the same name is only
+/// replaced with `force`.
+pub async fn add_template<H: AsRef<Path>>(
+    home: H,
+    source: &TemplateSource,
+    name: Option<&str>,
+    force: bool,
+    config: &Config,
+    fetcher: &dyn HttpFetcher,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let (template, packed_name) = fetch_installable(source, config, fetcher).await?;
+
+    let project = Project::read(template.path(), template.path())?;
+
+    check_template(config, &project)?;
+
+    let name = match (name, packed_name) {
+        (Some(name), _) => name.to_string(),
+        (None, Some(name)) => name,
+        (None, None) => match project.template_name() {
+            Some(name) if !matches!(source, TemplateSource::Git { .. }) => name,
+            // clones are cached in directories named after their url
+            _ => source_name(source)
+                .ok_or_else(|| format!("Couldn't name the template {}, use --as", source))?,
+        },
+    };
+
+    validate_name(&name, &[])
+        .map_err(|error| format!("Invalid template name {:?}: {}", name, error))?;
+
+    let destination = home.as_ref().join(GLOBAL_TEMPLATE_DIRECTORY).join(&name);
+
+    if destination.exists() {
+        if destination.canonicalize().ok() == template.path().canonicalize().ok() {
+            return Err(format!("Template {} is already installed", name).into());
+        }
+
+        if !force {
+            return Err(format!(
+                "Template {} is already installed in {}, rerun with -f or --force to replace it",
+                name,
+                destination.to_string_lossy()
+            )
+            .into());
+        }
+
+        fs::remove_dir_all(&destination)?;
+    }
+
+    copy_template(template.path(), &destination)?;
+
+    Provenance::new(
+        project.template_name(),
+        source.clone(),
+        template.commit().map(str::to_string),
+    )
+    .write(&destination)?;
+
+    Ok(destination)
+}
+
+/// Make the template of `source` available locally, checking that there is
+/// one. Returns it along with the name `pi pack` recorded, for archives.
+async fn fetch_installable(
+    source: &TemplateSource,
+    config: &Config,
+    fetcher: &dyn HttpFetcher,
+) -> Result<(FetchedTemplate, Option<String>), Box<dyn std::error::Error>> {
+    let (template, packed_name) = match source {
+        TemplateSource::Archive { url } => {
+            let (template, packed_name) = download_template_archive(url, fetcher).await?;
+
+            check_fetched(&template, source, config, fetcher).await?;
+
+            (template, packed_name)
+        }
+        _ => (fetch_template(source, config, fetcher).await?, None),
+    };
+
+    if !is_template(template.path()) {
+        let templates = monorepo_templates(template.path());
+
+        if !templates.is_empty() {
+            return Err(format!(
+                "{} holds several templates, pick one of: {}",
+                source,
+                templates.join(", ")
+            )
+            .into());
+        }
+
+        return Err(format!(
+            "{} doesn't hold a template, its {} is missing",
+            source, TEMPLATE_FILENAME
+        )
+        .into());
+    }
+
+    Ok((template, packed_name))
+}
+
+/// Name of the template fetched from `source`, when it has no other.
+fn source_name(source: &TemplateSource) -> Option<String> {
+    match source {
+        TemplateSource::Path { path } => Some(path.file_name()?.to_string_lossy().into_owned()),
+        TemplateSource::Git {
+            template: Some(template),
+            ..
+        } => Some(template.clone()),
+        TemplateSource::Git { url, .. } => url
+            .path_segments()?
+            .rfind(|segment| !segment.is_empty())
+            .map(|segment| segment.trim_end_matches(".git").to_string()),
+        TemplateSource::Gist { id } => Some(id.clone()),
+        TemplateSource::Archive { url } => archive_stem(url.path()),
+    }
+}
+
+/// Download and extract the template archive at `url`, checking it against
+/// its package manifest when it was made by `pi pack`. Returns the template
+/// along with its name, recorded by `pi pack` or else the archive's name.
+async fn download_template_archive(
+    url: &Url,
+    fetcher: &dyn HttpFetcher,
+) -> Result<(FetchedTemplate, Option<String>), Box<dyn std::error::Error>> {
+    let mut name = archive_stem(url.path());
+
+    let template = download_archive(url, None, fetcher).await?;
+
+    // archives made by `pi pack` carry the checksum of the template
+    if let Some(manifest) = PackageManifest::from_directory(template.path()) {
+        let manifest = manifest.map_err(|error| format!("Invalid package manifest: {}", error))?;
+
+        if template_checksum(template.path())? != manifest.checksum {
+            return Err(format!(
+                "{} doesn't match the checksum of its package manifest, it may be corrupted",
+                url
+            )
+            .into());
+        }
+
+        name = Some(manifest.name);
+    }
+
+    Ok((template, name))
+}
+
+/// What `pi update` changed in an installed template.
+#[derive(Debug)]
+pub struct TemplateUpdate {
+    pub directory: PathBuf,
+    /// Commits the template was installed and updated at, for git templates
+    pub old_commit: Option<String>,
+    pub new_commit: Option<String>,
+    /// Files of the template modified, deleted or added upstream
+    pub changes: Vec<Drift>,
+}
+
+/// Fetch the template `name` installed in `$HOME/.pi_templates/` again from
+/// the source it was installed from, and replace the installed copy if it
+/// changed.
+pub async fn update_template<H: AsRef<Path>>(
+    home: H,
+    name: &str,
+    config: &Config,
+    fetcher: &dyn HttpFetcher,
+) -> Result<TemplateUpdate, Box<dyn std::error::Error>> {
+    let directory = installed_template(home, name)?;
+
+    if !Provenance::path(&directory).is_file() {
+        return Err(format!(
+            "Template {} wasn't installed by pi add, its source is unknown",
+            name
+        )
+        .into());
+    }
+
+    let provenance = Provenance::from_project(&directory)?;
+
+    let (template, _) = fetch_installable(&provenance.source, config, fetcher).await?;
+
+    let project = Project::read(template.path(), template.path())?;
+
+    check_template(config, &project)?;
+
+    let changes = template_changes(&directory, template.path())?;
+
+    let update = TemplateUpdate {
+        directory,
+        old_commit: provenance.commit,
+        new_commit: template.commit().map(str::to_string),
+        changes,
+    };
+
+    if !update.changes.is_empty() {
+        fs::remove_dir_all(&update.directory)?;
+
+        copy_template(template.path(), &update.directory)?;
+    }
+
+    Provenance::new(
+        project.template_name(),
+        provenance.source,
+        update.new_commit.clone(),
+    )
+    .write(&update.directory)?;
+
+    Ok(update)
+}
+
+/// Differences between the files of the templates in `old` and `new`.
+fn template_changes(old: &Path, new: &Path) -> std::io::Result<Vec<Drift>> {
+    let checksums = |root: &Path| -> std::io::Result<BTreeMap<PathBuf, String>> {
+        template_files(root)
+            .into_iter()
+            .map(|path| Ok((path.clone(), file_checksum(root.join(&path))?)))
+            .collect()
+    };
+
+    let old = checksums(old)?;
+    let new = checksums(new)?;
+
+    let mut changes = Vec::new();
+
+    for (path, checksum) in &new {
+        match old.get(path) {
+            Some(old_checksum) if old_checksum == checksum => {}
+            Some(_) => changes.push(Drift::Modified(path.clone())),
+            None => changes.push(Drift::Added(path.clone())),
+        }
+    }
+
+    for path in old.keys().filter(|path| !new.contains_key(*path)) {
+        changes.push(Drift::Deleted(path.clone()));
+    }
+
+    changes.sort();
+
+    Ok(changes)
+}
+
+/// Returns the directory of the template `name` installed in
+/// `$HOME/.pi_templates/`.
+pub fn installed_template<H: AsRef<Path>>(
+    home: H,
+    name: &str,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let templates = home.as_ref().join(GLOBAL_TEMPLATE_DIRECTORY);
+
+    // the name must not reach outside of the templates directory
+    if validate_name(name, &[]).is_err() {
+        return Err(format!("{:?} isn't the name of an installed template", name).into());
+    }
+
+    let directory = templates.join(name);
+
+    if !is_template(&directory) {
+        return Err(format!(
+            "No template named {} installed in {}",
+            name,
+            templates.to_string_lossy()
+        )
+        .into());
+    }
+
+    Ok(directory)
+}
+
+/// Delete the template `name` from `$HOME/.pi_templates/`, returning the
+/// directory it was installed in.
+pub fn remove_template<H: AsRef<Path>>(
+    home: H,
+    name: &str,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let directory = installed_template(home, name)?;
+
+    fs::remove_dir_all(&directory)
+        .map_err(|error| format!("Couldn't remove {}: {}", directory.to_string_lossy(), error))?;
+
+    Ok(directory)
+}
+
+/// Copy the files of the template in `source` to `destination`.
+fn copy_template(source: &Path, destination: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(destination)?;
+
+    for path in template_files(source) {
+        let target = destination.join(&path);
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::copy(source.join(&path), &target)?;
+    }
+
+    Ok(())
+}
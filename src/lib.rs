@@ -0,0 +1,65 @@
+//! Library behind the `pi` binary.
+//!
+//! Everything `pi` does is available here, so that it can be embedded in
+//! other tools, and so that templates can be tested from Rust with the
+//! [`testing`] module.
+
+pub mod annotate;
+pub mod answers;
+pub mod archive;
+pub mod batch;
+pub mod cache;
+pub mod clean;
+pub mod config_file;
+pub mod constants;
+pub mod dependencies;
+pub mod diagnostics;
+pub mod diff;
+pub mod editor;
+pub mod editorconfig;
+pub mod fetch;
+pub mod fixtures;
+pub mod freeze;
+pub mod github;
+pub mod hooks;
+pub mod http;
+pub mod includes;
+pub mod info;
+pub mod install;
+pub mod list;
+pub mod lockfile;
+pub mod manifest;
+pub mod names;
+pub mod outdated;
+pub mod pack;
+pub mod picker;
+pub mod piignore;
+pub mod pins;
+pub mod plan;
+pub mod plugins;
+pub mod prompt;
+pub mod provenance;
+pub mod publish;
+pub mod render;
+pub mod repo;
+pub mod requirements;
+pub mod scaffold;
+pub mod script;
+pub mod self_update;
+#[cfg(feature = "serve")]
+pub mod serve;
+pub mod signature;
+pub mod stdout;
+pub mod testing;
+pub mod tls;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod types;
+pub mod upgrade;
+pub mod util;
+pub mod validate;
+pub mod variables;
+pub mod verify;
+pub mod warnings;
+pub mod watch;
+pub mod workspace;
@@ -0,0 +1,200 @@
+//! Structured output of `pi list`, for editor plugins and scripts.
+
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::http::HttpFetcher;
+use crate::types::{
+    has_language, has_tags, sort_entries, Config, Project, SortOrder, TemplateMetadata,
+    TemplateRepository, TemplateRepositoryEntry,
+};
+
+/// Format `pi list` prints templates in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListFormat {
+    /// One `pi new` or `pi git` command per template, under a heading per
+    /// source
+    Plain,
+    /// Aligned columns, one template per row
+    Table,
+    /// A JSON array of [`ListedTemplate`]
+    Json,
+}
+
+impl FromStr for ListFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "plain" => Ok(Self::Plain),
+            "table" => Ok(Self::Table),
+            "json" => Ok(Self::Json),
+            _ => Err(format!(
+                "Unknown format {}, expected plain, table or json",
+                value
+            )),
+        }
+    }
+}
+
+/// A template listed by `pi list`.
+#[derive(Debug, Serialize)]
+pub struct ListedTemplate {
+    pub name: String,
+    /// `local` for installed templates, the templates repository otherwise
+    pub source: String,
+    /// Directory of installed templates, repository url of remote ones
+    pub location: String,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    /// Main programming language of generated projects
+    pub language: Option<String>,
+    /// Latest released version of remote templates
+    pub version: Option<String>,
+    /// Command generating a project from the template
+    pub command: String,
+}
+
+impl ListedTemplate {
+    /// The template installed in `directory`, if it is tagged with every one
+    /// of `tags` and written in `language`, when given.
+    pub fn local(directory: &Path, tags: &[String], language: Option<&str>) -> Option<Self> {
+        let name = directory.file_name()?.to_string_lossy().into_owned();
+
+        let metadata = TemplateMetadata::from_directory(directory);
+
+        let template_tags = metadata
+            .as_ref()
+            .map(|metadata| metadata.tags.clone())
+            .unwrap_or_default();
+
+        let template_language = metadata
+            .as_ref()
+            .and_then(|metadata| metadata.language.clone());
+
+        if !has_tags(&template_tags, tags) || !has_language(template_language.as_deref(), language)
+        {
+            return None;
+        }
+
+        Some(Self {
+            command: format!("pi new {}", name),
+            name,
+            source: "local".to_string(),
+            location: directory.to_string_lossy().into_owned(),
+            description: metadata.and_then(|metadata| metadata.description),
+            tags: template_tags,
+            language: template_language,
+            version: None,
+        })
+    }
+
+    /// The template of `entry`, listed in `repository`.
+    pub fn remote(repository: &TemplateRepository, entry: &TemplateRepositoryEntry) -> Self {
+        Self {
+            name: entry.name.clone(),
+            source: repository.to_string(),
+            location: entry.repository.to_string(),
+            description: Some(entry.description.clone()),
+            tags: entry.tags.clone(),
+            language: entry.language.clone(),
+            version: entry
+                .latest_version()
+                .map(|version| version.version.clone()),
+            command: format!(
+                "pi git {}",
+                entry.repository.path().get(1..).unwrap_or_default()
+            ),
+        }
+    }
+}
+
+/// Every template installed in `$HOME/.pi_templates/`, then every template
+/// of the templates repositories of `config`, tagged with every one of `tags`
+/// and written in `language`, when given.
+///
+/// Remote templates are ordered by `sort` within their repository, and
+/// repositories that don't answer within `timeout` are reported and skipped.
+pub async fn list_templates<H: AsRef<Path>>(
+    home: H,
+    config: &Config,
+    tags: &[String],
+    language: Option<&str>,
+    sort: Option<SortOrder>,
+    fetcher: &dyn HttpFetcher,
+    timeout: Duration,
+) -> Vec<ListedTemplate> {
+    let mut templates = Project::installed(home)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|directory| ListedTemplate::local(directory, tags, language))
+        .collect::<Vec<_>>();
+
+    let repositories = config.all_templates_repositories();
+
+    for (repository, entries) in TemplateRepository::read_all(&repositories, fetcher, timeout).await
+    {
+        let mut entries = entries
+            .into_iter()
+            .filter(|entry| entry.has_tags(tags) && entry.has_language(language))
+            .collect::<Vec<_>>();
+
+        if let Some(sort) = sort {
+            sort_entries(&mut entries, sort);
+        }
+
+        templates.extend(
+            entries
+                .iter()
+                .map(|entry| ListedTemplate::remote(repository, entry)),
+        );
+    }
+
+    templates
+}
+
+/// `templates` as aligned columns under a header.
+pub fn table(templates: &[ListedTemplate]) -> String {
+    let header = ["NAME", "SOURCE", "VERSION", "DESCRIPTION"];
+
+    let rows = templates
+        .iter()
+        .map(|template| {
+            [
+                template.name.clone(),
+                template.source.clone(),
+                template.version.clone().unwrap_or_default(),
+                template.description.clone().unwrap_or_default(),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let widths = (0..header.len())
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].chars().count())
+                .chain([header[column].len()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+
+    let mut table = String::new();
+
+    for row in [header.map(str::to_string)].iter().chain(&rows) {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+
+    table
+}
@@ -0,0 +1,110 @@
+//! Project lockfile, `.pi.lock`, pinning everything needed to generate a
+//! project again exactly as it was.
+//!
+//! Unlike the [`Manifest`](crate::manifest::Manifest), which describes what
+//! was generated, the lockfile describes how to generate it: the template
+//! source at a resolved commit, the template's checksum, and the answers.
+//! `pi new --locked` reproduces the project from it on another machine.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::answers::is_secret;
+use crate::constants::LOCKFILE_FILENAME;
+use crate::fetch::FetchedTemplate;
+use crate::manifest::{template_checksum, TemplateSource};
+use crate::plan::Plan;
+
+/// A template, pinned to its exact contents.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockedTemplate {
+    /// Checksum of the template's contents, see
+    /// [`template_checksum`](crate::manifest::template_checksum)
+    pub checksum: String,
+    /// Where the template comes from, at the commit it was fetched at
+    pub source: TemplateSource,
+}
+
+impl LockedTemplate {
+    /// Lock the `template` fetched from `source`.
+    pub fn from_fetched(template: &FetchedTemplate, source: &TemplateSource) -> io::Result<Self> {
+        Ok(Self {
+            source: template.resolved_source(source),
+            checksum: template_checksum(template.path())?,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Lockfile {
+    /// Version of pi that wrote the lockfile
+    pub pi_version: String,
+    /// Name of the generated project
+    pub name: String,
+    /// Keys the templates were rendered with, secrets excluded
+    #[serde(default)]
+    pub answers: BTreeMap<String, String>,
+    pub template: LockedTemplate,
+}
+
+impl Lockfile {
+    /// Build the lockfile of a project generated from `plan`, leaving out
+    /// the answers to `secrets`, see [`is_secret`].
+    pub fn new(name: &str, template: LockedTemplate, plan: &Plan, secrets: &[String]) -> Self {
+        Self {
+            pi_version: env!("CARGO_PKG_VERSION").to_string(),
+            name: name.to_string(),
+            answers: plan
+                .keys
+                .iter()
+                .filter(|(key, _)| !is_secret(key, secrets))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+            template,
+        }
+    }
+
+    /// Path of the lockfile inside the project located in `project_dir`.
+    pub fn path<P: AsRef<Path>>(project_dir: P) -> PathBuf {
+        project_dir.as_ref().join(LOCKFILE_FILENAME)
+    }
+
+    /// Read the lockfile at `path`, or inside the project located at `path`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = if path.as_ref().is_dir() {
+            Self::path(path)
+        } else {
+            path.as_ref().to_path_buf()
+        };
+
+        let content = fs::read_to_string(&path).map_err(|error| {
+            format!(
+                "Couldn't read lockfile {}: {}",
+                path.to_string_lossy(),
+                error
+            )
+        })?;
+
+        Ok(toml::from_str(&content)?)
+    }
+
+    /// Write the lockfile inside the project located in `project_dir`.
+    pub fn write<P: AsRef<Path>>(&self, project_dir: P) -> Result<(), Box<dyn std::error::Error>> {
+        fs::write(Self::path(project_dir), toml::to_string(self)?)?;
+
+        Ok(())
+    }
+
+    /// Returns the answers as overriding keys, suitable for rendering the
+    /// template exactly as it was generated.
+    pub fn overrides(&self) -> Vec<(String, String)> {
+        self.answers
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+}
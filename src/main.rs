@@ -1,51 +1,170 @@
 //! Source file for the binary.
 
-use std::fs::read_dir;
-use std::fs::File;
-use std::io::Write;
+use std::env;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 use args::Args;
-use args::Subcommands;
-use clap::StructOpt;
-use git2::Repository;
-use tempdir::TempDir;
-use tracing::error;
-use tracing_subscriber::FmtSubscriber;
-use types::Config;
-use types::Project;
-
-use crate::constants::{
-    GITHUB_URL, GLOBAL_CONFIG_FILENAME, GLOBAL_TEMPLATE_DIRECTORY, TEMPLATE_FILENAME,
+use args::{CacheCommand, ConfigCommand, Subcommands, TemplateCommand};
+use clap::{IntoApp, StructOpt};
+use project_init::annotate::annotate as annotate_template;
+use project_init::answers::read_answers;
+use project_init::batch::{read_matrix, read_names};
+use project_init::cache::{cache_directory, cached_repositories, clean_cache, directory_size};
+use project_init::clean::clean;
+use project_init::config_file::{format_item, parse_value, ConfigFile};
+use project_init::constants::{
+    GLOBAL_CONFIG_FILENAME, GLOBAL_TEMPLATE_DIRECTORY, TEMPLATES_REPOSITORY_TIMEOUT,
 };
-use crate::types::Author;
-use crate::util::init_helper;
+use project_init::dependencies::{resolve_dependencies, Dependency};
+use project_init::diff::colorize;
+use project_init::editor::{edit_file, editor_command, open_project};
+use project_init::fetch::{
+    archive_stem, fetch_template, monorepo_templates, parse_archive_url, parse_remote,
+    FetchedTemplate,
+};
+use project_init::freeze::freeze;
+use project_init::github::search_templates;
+use project_init::http::{configured_fetcher, HttpFetcher};
+use project_init::info::TemplateInfo;
+use project_init::install::{add_template, installed_template, remove_template, update_template};
+use project_init::list::{list_templates, table, ListFormat, ListedTemplate};
+use project_init::lockfile::{LockedTemplate, Lockfile};
+use project_init::manifest::{template_checksum, TemplateSource};
+use project_init::names::validate_name;
+use project_init::outdated::outdated_templates;
+use project_init::pack::pack;
+use project_init::picker::{candidates, pick, Candidate};
+use project_init::pins::{pinned_checksum, record_pin};
+use project_init::plan::{human_size, read_umask, Plan};
+use project_init::prompt::{ci_detected, confirm, input, is_interactive, set_interactive};
+use project_init::provenance::Provenance;
+use project_init::render::{checked_template, render_file};
+use project_init::requirements::{hints, missing};
+use project_init::scaffold::{scaffold_template, Scaffold};
+use project_init::self_update::{install_release, latest_release};
+#[cfg(feature = "serve")]
+use project_init::serve::{serve, Server};
+use project_init::stdout::{reserve_stdout, ReservedStdout};
+use project_init::tls::configure_git_transport;
+#[cfg(feature = "tui")]
+use project_init::tui::browse;
+use project_init::types::{
+    is_template, sort_entries, Author, Config, License, Project, TemplateRepository,
+};
+use project_init::upgrade::{apply_upgrade, plan_upgrade};
+#[cfg(feature = "serve")]
+use project_init::util::check_template;
+use project_init::util::{
+    describe_generation, init_helper, requested_license, template_keys_with_sources, InitOptions,
+};
+use project_init::validate::validate;
+use project_init::verify::{project_diff, verify};
+use project_init::warnings::{defer_warnings, is_deferred, summary, take_warnings, WarningsLayer};
+use project_init::watch::Watch;
+use project_init::{fixtures, plugins, publish};
+use semver::Version;
+use tracing::Level;
+use tracing::{error, warn};
+use tracing_subscriber::filter::filter_fn;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::{fmt, registry, Layer};
 
 mod args;
-mod constants;
-mod includes;
-mod render;
-mod repo;
-mod types;
-mod util;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tracing::subscriber::set_global_default(FmtSubscriber::default())?;
+    // deferred warnings are only reported once a project is generated
+    let logs = fmt::layer().with_filter(filter_fn(|metadata| {
+        *metadata.level() <= Level::INFO && !is_deferred(metadata)
+    }));
+
+    tracing::subscriber::set_global_default(registry().with(WarningsLayer).with(logs))?;
 
     let args = Args::parse();
 
+    // `pi serve` and `pi batch` create files from several threads
+    read_umask();
+
+    // logs included, nothing but the path of the project may reach stdout
+    let stdout = args
+        .subcommand
+        .as_ref()
+        .is_some_and(Subcommands::prints_path)
+        .then(reserve_stdout_or_exit);
+
+    set_interactive(!args.no_input && !ci_detected());
+
     let home = dirs::home_dir().ok_or("Couldn't determine home directory")?;
 
-    let config = Config::from_path(&home.join(GLOBAL_CONFIG_FILENAME));
+    let global_config_path = home.join(GLOBAL_CONFIG_FILENAME);
+
+    // `pi config` must be able to fix a malformed configuration
+    let config = if matches!(args.subcommand, Some(Subcommands::Config { .. })) {
+        std::fs::read_to_string(&global_config_path)
+            .ok()
+            .and_then(|content| Config::parse(&global_config_path, &content).ok())
+            .unwrap_or_default()
+    } else {
+        Config::from_path(&global_config_path)
+    };
 
-    match args.subcommand {
+    if let Err(error) = configure_git_transport(&config) {
+        error!("{}", error);
+
+        std::process::exit(1);
+    }
+
+    let subcommand = match args.subcommand {
+        Some(subcommand) => subcommand,
+        #[cfg(feature = "tui")]
+        None if is_interactive() && io::stdout().is_terminal() => Subcommands::Tui,
+        None => {
+            Args::into_app().print_help()?;
+
+            std::process::exit(2);
+        }
+    };
+
+    #[cfg(feature = "tui")]
+    let subcommand = match subcommand {
+        Subcommands::Tui => browse_templates(&home, &config).await,
+        subcommand => subcommand,
+    };
+
+    match subcommand {
         Subcommands::Git {
             repository,
             name,
+            output,
             force,
+            merge,
+            rev,
+            pin,
+            lock,
+            answers,
+            defines,
+            save_answers,
+            license,
+            vcs,
+            no_vcs,
+            no_hooks,
+            trust_hooks,
+            open,
+            print_path: _,
+            dry_run,
         } => {
-            let repository_url = match GITHUB_URL.join(&repository) {
-                Ok(repository_url) => repository_url,
+            defer_warnings(true);
+
+            let mut answers = read_answers_or_exit(answers.as_deref());
+
+            answers.extend(defines);
+
+            let license = license.as_deref().map(license_or_exit);
+
+            let source = match parse_remote(&repository, rev, &config) {
+                Ok(source) => source,
                 Err(_) => {
                     error!("Failed to resolve the repository url");
 
@@ -53,69 +172,455 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             };
 
-            // create a temporary directory to hold the template
-            let dir_name = repository.replace("/", "-");
+            // fetch into a temporary directory
+            let fetcher = http_fetcher(&config);
 
-            let tmp_directory = match TempDir::new(&dir_name) {
-                Ok(tmp_directory) => tmp_directory,
-                Err(_) => {
-                    error!("Failed to create temporary directory");
+            let template = match fetch_template(&source, &config, fetcher.as_ref()).await {
+                Ok(template) => template,
+                Err(error) => {
+                    error!("{}", error);
 
                     std::process::exit(1);
                 }
             };
 
-            // clone into the temporary directory
-            let directory = tmp_directory.path();
+            // repositories holding several templates need one to be picked
+            if !is_template(template.path()) {
+                let templates = monorepo_templates(template.path());
 
-            if Repository::clone(repository_url.as_str(), directory).is_err() {
-                error!("Failed to clone repository at {}", repository_url);
+                if !templates.is_empty() {
+                    println!("{} holds several templates, pick one of:", repository);
 
-                std::process::exit(1);
-            };
+                    for name in templates {
+                        println!("- pi git {}:{}", repository, name);
+                    }
+
+                    std::process::exit(1);
+                }
+            }
+
+            // pinned templates were checked against their pin when fetched
+            if pin && pinned_checksum(&config, &source).is_none() {
+                let checksum = match template_checksum(template.path()) {
+                    Ok(checksum) => checksum,
+                    Err(error) => {
+                        error!("Couldn't compute the template's checksum: {}", error);
+
+                        std::process::exit(1);
+                    }
+                };
+
+                if let TemplateSource::Git { rev: None, .. } = source {
+                    warn!("Pinning a template without --rev, the pin breaks as soon as its default branch changes");
+                }
 
-            // get the parsed TOML file from the repo.
-            let project = Project::from_path(".", &directory);
+                if let Err(error) =
+                    record_pin(home.join(GLOBAL_CONFIG_FILENAME), &source, &checksum)
+                {
+                    error!("Couldn't pin the template: {}", error);
+
+                    std::process::exit(1);
+                }
+            }
+
+            // get the parsed TOML file from the template.
+            let mut project = template.project(&source);
+
+            if license.is_some() {
+                project.license = license;
+            }
+
+            let name = checked_name(name, &project);
+
+            let lock = lock.then(|| lock_template(&template, &source));
 
             // initialize the project
-            init_helper(&name, config, project, force)?;
+            let options = InitOptions {
+                output: output.unwrap_or_else(|| PathBuf::from(&name)),
+                force,
+                merge,
+                keys: answers,
+                dependencies: resolve_or_exit(&home, &config, &source, &project, fetcher.as_ref())
+                    .await,
+                source: Some(source),
+                commit: template.commit().map(str::to_string),
+                manifest: true,
+                lock,
+                prompt: true,
+                save_answers,
+                verbose: args.verbose,
+                dry_run,
+                version_control: vcs,
+                no_version_control: no_vcs,
+                run_hooks: !no_hooks,
+                trust_hooks,
+            };
+
+            let editor = open.then(|| editor_command(&config));
+
+            let started = Instant::now();
+
+            match init_helper(&name, &config, &project, &options) {
+                Ok(plan) if dry_run => {
+                    defer_warnings(false);
+
+                    print!(
+                        "{}",
+                        describe_generation(&config, &project, &options, &plan)
+                    );
+
+                    eprint!("{}", summary(&take_warnings()));
+                }
+                Ok(plan) => {
+                    finished(&options.output, &plan, started.elapsed());
+
+                    if let Some(editor) = editor {
+                        open_in_editor(editor, &options.output, &plan);
+                    }
+
+                    if let Some(stdout) = stdout {
+                        print_project_path(stdout, &options.output);
+                    }
+                }
+                Err(error) => {
+                    error!("{}", error);
 
-            println!("Finished initializing project in {}", name);
+                    std::process::exit(1);
+                }
+            }
         }
 
         Subcommands::New {
             directory,
             name,
+            output,
             force,
+            merge,
+            lock,
+            answers,
+            defines,
+            save_answers,
+            license,
+            vcs,
+            no_vcs,
+            no_hooks,
+            trust_hooks,
+            open,
+            print_path: _,
+            dry_run,
+            locked,
+            fetch,
         } => {
-            let project = Project::from_path(&home, &directory);
+            defer_warnings(true);
+
+            let mut answers = read_answers_or_exit(answers.as_deref());
+
+            answers.extend(defines);
+
+            let license = license.as_deref().map(license_or_exit);
+
+            let fetcher = http_fetcher(&config);
+
+            // a single argument is the project's name, its template is picked
+            let (name, picked) = match name {
+                Some(name) => (name, None),
+                None if locked => {
+                    error!("--locked needs both a lockfile and the name of the project");
+
+                    std::process::exit(1);
+                }
+                None => (
+                    directory.to_string_lossy().into_owned(),
+                    Some(pick_template(&home, &config, fetcher.as_ref()).await),
+                ),
+            };
+
+            let lockfile = locked.then(|| match Lockfile::from_path(&directory) {
+                Ok(lockfile) => lockfile,
+                Err(error) => {
+                    error!("{}", error);
+
+                    std::process::exit(1);
+                }
+            });
+
+            // archives served over HTTP are downloaded first
+            let source = match (picked, &lockfile, parse_archive_url(&directory)) {
+                (Some(source), _, _) => source,
+                (None, Some(lockfile), _) => lockfile.template.source.clone(),
+                (None, None, Some(url)) => TemplateSource::Archive { url },
+                (None, None, None) => match Project::find(&home, &directory) {
+                    Some(path) => TemplateSource::from_path(path),
+                    None => registry_source(&directory, &config, fetch, fetcher.as_ref())
+                        .await
+                        .unwrap_or_else(|| {
+                            TemplateSource::from_path(Project::locate(&home, &directory))
+                        }),
+                },
+            };
+
+            let template = match fetch_template(&source, &config, fetcher.as_ref()).await {
+                Ok(template) => template,
+                Err(error) => {
+                    error!("{}", error);
+
+                    std::process::exit(1);
+                }
+            };
+
+            let mut project = template.project(&source);
+
+            if license.is_some() {
+                project.license = license;
+            }
+
+            let name = checked_name(name, &project);
+
+            let lock = (lock || locked).then(|| lock_template(&template, &source));
+
+            let keys = match (&lockfile, &lock) {
+                (Some(lockfile), Some(lock)) => {
+                    if lock.checksum != lockfile.template.checksum {
+                        error!(
+                            "Template {} doesn't match the lockfile, expected checksum {} but got {}",
+                            source, lockfile.template.checksum, lock.checksum
+                        );
+
+                        std::process::exit(1);
+                    }
+
+                    answers.into_iter().chain(lockfile.overrides()).collect()
+                }
+                _ => answers,
+            };
+
+            let options = InitOptions {
+                output: output.unwrap_or_else(|| PathBuf::from(&name)),
+                force,
+                merge,
+                keys,
+                dependencies: resolve_or_exit(&home, &config, &source, &project, fetcher.as_ref())
+                    .await,
+                source: Some(source),
+                commit: template.commit().map(str::to_string),
+                manifest: true,
+                lock,
+                prompt: true,
+                save_answers,
+                verbose: args.verbose,
+                dry_run,
+                version_control: vcs,
+                no_version_control: no_vcs,
+                run_hooks: !no_hooks,
+                trust_hooks,
+            };
+
+            let editor = open.then(|| editor_command(&config));
+
+            let started = Instant::now();
+
+            match init_helper(&name, &config, &project, &options) {
+                Ok(plan) if dry_run => {
+                    defer_warnings(false);
+
+                    print!(
+                        "{}",
+                        describe_generation(&config, &project, &options, &plan)
+                    );
+
+                    eprint!("{}", summary(&take_warnings()));
+                }
+                Ok(plan) => {
+                    finished(&options.output, &plan, started.elapsed());
+
+                    if let Some(editor) = editor {
+                        open_in_editor(editor, &options.output, &plan);
+                    }
+
+                    if let Some(stdout) = stdout {
+                        print_project_path(stdout, &options.output);
+                    }
+                }
+                Err(error) => {
+                    error!("{}", error);
+
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Subcommands::Add {
+            template,
+            name,
+            force,
+        } => {
+            let source = if template.is_dir() {
+                TemplateSource::from_path(&template)
+            } else if let Some(url) = parse_archive_url(&template) {
+                TemplateSource::Archive { url }
+            } else if archive_stem(&template).is_some() {
+                error!("No template archive at {}", template.to_string_lossy());
+
+                std::process::exit(1);
+            } else {
+                match parse_remote(&template.to_string_lossy(), None, &config) {
+                    Ok(source) => source,
+                    Err(error) => {
+                        error!("Invalid template {}: {}", template.to_string_lossy(), error);
+
+                        std::process::exit(1);
+                    }
+                }
+            };
+
+            match add_template(
+                &home,
+                &source,
+                name.as_deref(),
+                force,
+                &config,
+                http_fetcher(&config).as_ref(),
+            )
+            .await
+            {
+                Ok(destination) => {
+                    println!("Installed template in {}", destination.to_string_lossy())
+                }
+                Err(error) => {
+                    error!("{}", error);
+
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Subcommands::Update { templates } => {
+            let templates = if templates.is_empty() {
+                // every template installed by `pi add`
+                Project::installed(&home)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|path| Provenance::path(path).is_file())
+                    .filter_map(|path| Some(path.file_name()?.to_string_lossy().into_owned()))
+                    .collect()
+            } else {
+                templates
+            };
+
+            if templates.is_empty() {
+                println!("No template installed by pi add to update");
+            }
+
+            let fetcher = http_fetcher(&config);
+
+            let mut failed = false;
+
+            for name in templates {
+                match update_template(&home, &name, &config, fetcher.as_ref()).await {
+                    Ok(update) if update.changes.is_empty() => {
+                        println!("{} is up to date", name);
+                    }
+                    Ok(update) => {
+                        match (&update.old_commit, &update.new_commit) {
+                            (Some(old), Some(new)) if old != new => println!(
+                                "Updated {} ({} -> {})",
+                                name,
+                                old.get(..7).unwrap_or(old),
+                                new.get(..7).unwrap_or(new)
+                            ),
+                            _ => println!("Updated {}", name),
+                        }
+
+                        for change in &update.changes {
+                            println!("  {}", change);
+                        }
+                    }
+                    Err(error) => {
+                        error!("Couldn't update {}: {}", name, error);
+
+                        failed = true;
+                    }
+                }
+            }
+
+            if failed {
+                std::process::exit(1);
+            }
+        }
+
+        Subcommands::Remove { template, yes } => {
+            let directory = match installed_template(&home, &template) {
+                Ok(directory) => directory,
+                Err(error) => {
+                    error!("{}", error);
+
+                    std::process::exit(1);
+                }
+            };
+
+            let question = format!("Delete {}?", directory.to_string_lossy());
+
+            if !yes && !confirm(&question, false) {
+                println!("Nothing removed, pass --yes to remove without asking");
+
+                std::process::exit(1);
+            }
+
+            match remove_template(&home, &template) {
+                Ok(directory) => println!("Removed {}", directory.to_string_lossy()),
+                Err(error) => {
+                    error!("{}", error);
+
+                    std::process::exit(1);
+                }
+            }
+        }
 
-            init_helper(&name, config, project, force)?;
+        Subcommands::List {
+            tag,
+            language,
+            sort,
+            format,
+            json,
+        } if json || format != ListFormat::Plain => {
+            let templates = list_templates(
+                &home,
+                &config,
+                &tag,
+                language.as_deref(),
+                sort,
+                http_fetcher(&config).as_ref(),
+                TEMPLATES_REPOSITORY_TIMEOUT,
+            )
+            .await;
 
-            println!("Finished initializing project in {}", name);
+            if json || format == ListFormat::Json {
+                println!("{}", serde_json::to_string_pretty(&templates)?);
+            } else {
+                print!("{}", table(&templates));
+            }
         }
 
-        Subcommands::List => {
+        Subcommands::List {
+            tag,
+            language,
+            sort,
+            ..
+        } => {
             let local_templates_directory = home.join(GLOBAL_TEMPLATE_DIRECTORY);
 
-            match read_dir(&local_templates_directory) {
-                Ok(directories) => {
+            match Project::installed(&home) {
+                Ok(templates) => {
                     println!(
                         "Local templates located in {}",
                         local_templates_directory.to_string_lossy()
                     );
 
-                    for directory in directories.flatten() {
-                        let directory_path = directory.path();
-
-                        if directory_path.is_dir() {
-                            if let Some(directory_name) = directory_path.file_name() {
-                                let template_toml_path = directory_path.join(TEMPLATE_FILENAME);
-
-                                if template_toml_path.is_file() {
-                                    println!("- pi new {}", directory_name.to_string_lossy());
-                                }
-                            }
+                    for template in templates {
+                        if let Some(template) =
+                            ListedTemplate::local(&template, &tag, language.as_deref())
+                        {
+                            println!("- {}", template.command);
                         }
                     }
                 }
@@ -128,30 +633,223 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
 
-            match config.templates_repository {
-                Some(templates_repository) => {
-                    println!("Remote templates located in {}", templates_repository);
+            let templates_repositories = config.all_templates_repositories();
 
-                    let entries = templates_repository.read().await;
+            if templates_repositories.is_empty() {
+                println!("No templates repository found in config")
+            }
 
-                    if entries.is_empty() {
-                        println!("No templates found in repository {}", templates_repository);
-                    } else {
-                        for entry in entries {
-                            println!("- pi git {}", entry);
-                        }
+            let fetcher = http_fetcher(&config);
+
+            let sources = TemplateRepository::read_all(
+                &templates_repositories,
+                fetcher.as_ref(),
+                TEMPLATES_REPOSITORY_TIMEOUT,
+            )
+            .await;
+
+            for (templates_repository, entries) in sources {
+                println!("Remote templates located in {}", templates_repository);
+
+                let mut entries = entries
+                    .into_iter()
+                    .filter(|entry| entry.has_tags(&tag) && entry.has_language(language.as_deref()))
+                    .collect::<Vec<_>>();
+
+                if let Some(sort) = sort {
+                    sort_entries(&mut entries, sort);
+                }
+
+                if entries.is_empty() {
+                    println!("No templates found in repository {}", templates_repository);
+                } else {
+                    for entry in entries {
+                        println!("- pi git {}", entry);
                     }
                 }
+            }
+        }
+
+        Subcommands::Outdated => {
+            let outdated = outdated_templates(&home, &config);
+
+            if outdated.is_empty() {
+                println!("All templates are up to date");
+            } else {
+                println!("Templates with upstream updates:");
+
+                for template in outdated {
+                    println!("- {}", template);
+                }
+            }
+        }
+
+        Subcommands::Search {
+            query,
+            tag,
+            sort,
+            github: true,
+        } => match search_templates(
+            query.as_deref().unwrap_or_default(),
+            &tag,
+            sort,
+            &config,
+            http_fetcher(&config).as_ref(),
+        )
+        .await
+        {
+            Ok(repositories) if repositories.is_empty() => {
+                println!(
+                    "No templates found on GitHub for '{}'",
+                    search_description(&query, &tag)
+                );
+            }
+            Ok(repositories) => {
+                for repository in repositories {
+                    let updated_at = repository
+                        .updated_at
+                        .as_deref()
+                        .and_then(|updated_at| updated_at.get(..10))
+                        .map(|updated_at| format!(", updated {}", updated_at))
+                        .unwrap_or_default();
+
+                    println!(
+                        "- pi git {} (\u{2605} {}{}): {}",
+                        repository.full_name,
+                        repository.stargazers_count,
+                        updated_at,
+                        repository.description.unwrap_or_default()
+                    );
+                }
+            }
+            Err(error) => {
+                error!("{}", error);
+
+                std::process::exit(1);
+            }
+        },
+
+        Subcommands::Search {
+            query,
+            tag,
+            sort,
+            github: false,
+        } => {
+            let fetcher = http_fetcher(&config);
+
+            let mut entries = TemplateRepository::search(
+                &config.all_templates_repositories(),
+                query.as_deref().unwrap_or_default(),
+                &tag,
+                fetcher.as_ref(),
+                TEMPLATES_REPOSITORY_TIMEOUT,
+            )
+            .await;
+
+            if let Some(sort) = sort {
+                sort_entries(&mut entries, sort);
+            }
+
+            if entries.is_empty() {
+                println!(
+                    "No templates found for '{}'",
+                    search_description(&query, &tag)
+                );
+            } else {
+                for entry in entries {
+                    println!("- pi git {}", entry);
+                }
+            }
+        }
+
+        Subcommands::Info { template, rev } => {
+            let fetched;
+
+            let project = match Project::find(&home, &template) {
+                Some(path) => Project::from_path(&home, path),
+                None => {
+                    let source = match parse_remote(&template, rev, &config) {
+                        Ok(source) => source,
+                        Err(_) => {
+                            error!("No template named {} found", template);
+
+                            std::process::exit(1);
+                        }
+                    };
+
+                    fetched = match fetch_template(&source, &config, http_fetcher(&config).as_ref())
+                        .await
+                    {
+                        Ok(fetched) => fetched,
+                        Err(error) => {
+                            error!("{}", error);
+
+                            std::process::exit(1);
+                        }
+                    };
+
+                    fetched.project(&source)
+                }
+            };
+
+            print!(
+                "{}",
+                rendered_or_exit(TemplateInfo::from_project(&config, &project))
+            );
+        }
+
+        Subcommands::Publish {
+            directory,
+            repository,
+            token,
+        } => {
+            let project = Project::from_path(&home, &directory);
 
+            let repository = repository.as_deref().map(TemplateRepository::from);
+
+            let repository = match repository.as_ref().or(config.templates_repository.as_ref()) {
+                Some(repository) => repository,
                 None => {
-                    println!("No templates repository found in config")
+                    error!("No templates repository to publish to, use --repository or set templates_repository in your configuration");
+
+                    std::process::exit(1);
+                }
+            };
+
+            let token = token.or_else(|| std::env::var("PI_PUBLISH_TOKEN").ok());
+
+            let entry = match publish::prepare_entry(&home, &project, &config) {
+                Ok(entry) => entry,
+                Err(error) => {
+                    error!("{}", error);
+
+                    std::process::exit(1);
                 }
+            };
+
+            if let Err(error) = publish::publish(
+                entry,
+                repository,
+                token.as_deref(),
+                http_fetcher(&config).as_ref(),
+            )
+            .await
+            {
+                error!("{}", error);
+
+                std::process::exit(1);
+            }
+
+            println!("Published template to {}", repository);
+
+            // authors sign this checksum to release signed versions
+            match template_checksum(&project.path) {
+                Ok(checksum) => println!("Template checksum: {}", checksum),
+                Err(error) => warn!("Couldn't compute the template's checksum: {}", error),
             }
         }
 
         Subcommands::Init { force, no_prompt } => {
-            let global_config_path = home.join(GLOBAL_CONFIG_FILENAME);
-
             if !force && global_config_path.is_file() && global_config_path.exists() {
                 println!(
                     "Configuration file already exists in {}",
@@ -161,34 +859,29 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 std::process::exit(0);
             }
 
-            let mut config = Config::default();
-
-            let author = if no_prompt {
+            let author = if no_prompt || !is_interactive() {
                 Author::default()
             } else {
                 Author::from_input()
             };
 
-            config.author = Some(author);
-
-            let mut global_config_file = match File::create(&global_config_path) {
-                Ok(global_config_file) => global_config_file,
-                Err(_error) => {
-                    error!(
-                        "Couldn't create file {}",
-                        global_config_path.to_string_lossy()
-                    );
-
-                    std::process::exit(1);
-                }
+            let config = Config {
+                author: Some(author),
+                ..Config::default()
             };
 
-            let config_bytes = toml::to_string(&config).unwrap();
+            // the configuration is replaced as a whole, `pi config set` edits it
+            let written = toml::to_string(&config)
+                .map_err(|error| error.to_string())
+                .and_then(|content| {
+                    std::fs::write(&global_config_path, content).map_err(|error| error.to_string())
+                });
 
-            if global_config_file.write(config_bytes.as_bytes()).is_err() {
+            if let Err(error) = written {
                 error!(
-                    "Couldn't write in file {}",
-                    global_config_path.to_string_lossy()
+                    "Couldn't create file {}: {}",
+                    global_config_path.to_string_lossy(),
+                    error
                 );
 
                 std::process::exit(1);
@@ -199,7 +892,1031 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 global_config_path.to_string_lossy()
             );
         }
+
+        Subcommands::Cache {
+            command: CacheCommand::List,
+        } => {
+            let repositories = cached_repositories();
+
+            if repositories.is_empty() {
+                println!("No cached repositories");
+            }
+
+            for repository in &repositories {
+                println!(
+                    "- {} ({}{})",
+                    repository.name.to_string_lossy(),
+                    human_size(repository.size),
+                    repository
+                        .fetched_at
+                        .as_ref()
+                        .map(|date| format!(", fetched {}", date))
+                        .unwrap_or_default()
+                );
+            }
+
+            if let Some(cache) = cache_directory() {
+                println!("Total: {}", human_size(directory_size(cache)));
+            }
+        }
+
+        Subcommands::Cache {
+            command: CacheCommand::Clean,
+        } => match clean_cache() {
+            Ok(size) => println!("Removed {} of cache", human_size(size)),
+            Err(error) => {
+                error!("Couldn't clean the cache: {}", error);
+
+                std::process::exit(1);
+            }
+        },
+
+        Subcommands::Cache {
+            command: CacheCommand::Path,
+        } => match cache_directory() {
+            Some(cache) => println!("{}", cache.to_string_lossy()),
+            None => {
+                error!("Couldn't determine the cache directory");
+
+                std::process::exit(1);
+            }
+        },
+
+        Subcommands::Config {
+            command: ConfigCommand::Get { key },
+        } => {
+            let global_config_file = match ConfigFile::open(&global_config_path) {
+                Ok(global_config_file) => global_config_file,
+                Err(error) => {
+                    error!(
+                        "Couldn't read file {}: {}",
+                        global_config_path.to_string_lossy(),
+                        error
+                    );
+
+                    std::process::exit(1);
+                }
+            };
+
+            match global_config_file.get(&key) {
+                Some(item) => println!("{}", format_item(&key, item)),
+                None => {
+                    error!(
+                        "Key {} isn't set in {}",
+                        key,
+                        global_config_path.to_string_lossy()
+                    );
+
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Subcommands::Config {
+            command: ConfigCommand::Set { key, value },
+        } => {
+            let mut global_config_file = match ConfigFile::open(&global_config_path) {
+                Ok(global_config_file) => global_config_file,
+                Err(error) => {
+                    error!(
+                        "Couldn't read file {}: {}",
+                        global_config_path.to_string_lossy(),
+                        error
+                    );
+
+                    std::process::exit(1);
+                }
+            };
+
+            if let Err(error) = global_config_file.set(&key, parse_value(&value)) {
+                error!("{}", error);
+
+                std::process::exit(1);
+            }
+
+            // only save configurations pi can read back
+            if let Err(diagnostic) = global_config_file.check() {
+                error!("Invalid value for {}: {}", key, value);
+
+                eprint!("{}", diagnostic);
+
+                std::process::exit(1);
+            }
+
+            if let Err(error) = global_config_file.save() {
+                error!(
+                    "Couldn't write in file {}: {}",
+                    global_config_path.to_string_lossy(),
+                    error
+                );
+
+                std::process::exit(1);
+            }
+        }
+
+        Subcommands::Config {
+            command: ConfigCommand::Edit,
+        } => {
+            let command = match editor_command(&config) {
+                Some(command) => command,
+                None => {
+                    error!(
+                        "No editor to open {} with, set `editor` in it or $EDITOR",
+                        global_config_path.to_string_lossy()
+                    );
+
+                    std::process::exit(1);
+                }
+            };
+
+            if let Err(error) = edit_file(&command, &global_config_path) {
+                error!(
+                    "Couldn't open {} with {}: {}",
+                    global_config_path.to_string_lossy(),
+                    command,
+                    error
+                );
+
+                std::process::exit(1);
+            }
+
+            // report mistakes while the file is fresh in mind
+            if let Ok(content) = std::fs::read_to_string(&global_config_path) {
+                if let Err(diagnostic) = Config::parse(&global_config_path, &content) {
+                    warn!(
+                        "File {} was not properly formatted",
+                        global_config_path.to_string_lossy()
+                    );
+
+                    eprint!("{}", diagnostic);
+
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Subcommands::Verify { directory } => match verify(&home, &directory, &config).await {
+            Ok(drifts) if drifts.is_empty() => {
+                println!("{} matches its template", directory.to_string_lossy());
+            }
+            Ok(drifts) => {
+                for drift in drifts {
+                    println!("{}", drift);
+                }
+
+                std::process::exit(1);
+            }
+            Err(error) => {
+                error!("{}", error);
+
+                std::process::exit(1);
+            }
+        },
+
+        Subcommands::Diff {
+            directory,
+            no_color,
+        } => match project_diff(&home, &directory, &config).await {
+            Ok(diff) if diff.is_empty() => {
+                println!(
+                    "{} is up to date with its template",
+                    directory.to_string_lossy()
+                );
+            }
+            Ok(diff) => {
+                let color =
+                    !no_color && env::var_os("NO_COLOR").is_none() && io::stdout().is_terminal();
+
+                if color {
+                    print!("{}", colorize(&diff));
+                } else {
+                    print!("{}", diff);
+                }
+            }
+            Err(error) => {
+                error!("{}", error);
+
+                std::process::exit(1);
+            }
+        },
+
+        Subcommands::Upgrade {
+            directory,
+            rev,
+            force,
+            dry_run,
+        } => {
+            let fetcher = http_fetcher(&config);
+
+            let upgrade =
+                match plan_upgrade(&home, &directory, rev, &config, fetcher.as_ref()).await {
+                    Ok(upgrade) => upgrade,
+                    Err(error) => {
+                        error!("{}", error);
+
+                        std::process::exit(1);
+                    }
+                };
+
+            if upgrade.changes.is_empty() {
+                println!(
+                    "{} is up to date with its template",
+                    directory.to_string_lossy()
+                );
+
+                return Ok(());
+            }
+
+            for change in &upgrade.changes {
+                println!("{}", change);
+            }
+
+            if dry_run {
+                return Ok(());
+            }
+
+            let overwrite = if force {
+                upgrade
+                    .conflicts()
+                    .into_iter()
+                    .map(Path::to_path_buf)
+                    .collect()
+            } else {
+                upgrade.resolve_conflicts(&directory)
+            };
+
+            let kept = upgrade
+                .conflicts()
+                .into_iter()
+                .filter(|path| !overwrite.iter().any(|overwritten| overwritten == path))
+                .map(Path::to_path_buf)
+                .collect::<Vec<_>>();
+
+            if let Err(error) = apply_upgrade(&directory, upgrade, &overwrite) {
+                error!("{}", error);
+
+                std::process::exit(1);
+            }
+
+            for path in &kept {
+                warn!(
+                    "Kept your version of {}, rerun with --force to take the template's",
+                    path.to_string_lossy()
+                );
+            }
+
+            println!("Upgraded {}", directory.to_string_lossy());
+        }
+
+        Subcommands::Clean { directory, force } => match clean(&directory, force) {
+            Ok(report) => {
+                println!("Removed {} generated files", report.removed.len());
+
+                for path in &report.modified {
+                    println!("kept modified: {}", path.to_string_lossy());
+                }
+
+                for path in &report.added {
+                    println!("kept added:    {}", path.to_string_lossy());
+                }
+
+                if report.removed_project {
+                    println!("Removed {}", directory.to_string_lossy());
+                }
+            }
+            Err(error) => {
+                error!("{}", error);
+
+                std::process::exit(1);
+            }
+        },
+
+        // turned into the `pi new` of the picked template above
+        #[cfg(feature = "tui")]
+        Subcommands::Tui => unreachable!(),
+
+        #[cfg(feature = "serve")]
+        Subcommands::Serve {
+            address,
+            output_dir,
+        } => {
+            set_interactive(false);
+
+            // broken templates are answered with their error, report them early
+            for path in Project::installed(&home).unwrap_or_default() {
+                if let Err(error) = Project::read(&home, &path)
+                    .and_then(|project| check_template(&config, &project))
+                {
+                    warn!("{}", error);
+                }
+            }
+
+            let server = Server {
+                fetcher: http_fetcher(&config),
+                home,
+                config,
+                output: output_dir,
+            };
+
+            if let Err(error) = serve(address, server).await {
+                error!("{}", error);
+
+                std::process::exit(1);
+            }
+        }
+
+        Subcommands::Watch {
+            directory,
+            sandbox,
+            answers,
+        } => {
+            let template = Project::locate(&home, &directory);
+
+            let absolute = |path: PathBuf| match path.canonicalize() {
+                Ok(path) => path,
+                Err(_) => env::current_dir().unwrap_or_default().join(path),
+            };
+
+            let watch = Watch {
+                template: absolute(template),
+                sandbox: absolute(sandbox),
+                answers: answers.map(absolute),
+            };
+
+            if let Err(error) = watch.check_sandbox() {
+                error!("{}", error);
+
+                std::process::exit(1);
+            }
+
+            println!(
+                "Watching {}, stop with Ctrl-C",
+                watch.template.to_string_lossy()
+            );
+
+            if let Err(error) = watch.run() {
+                error!("{}", error);
+
+                std::process::exit(1);
+            }
+        }
+
+        Subcommands::Pack {
+            directory,
+            output,
+            format,
+        } => {
+            let project = Project::from_path(&home, &directory);
+
+            match pack(&project.path, &project, &config, format, output) {
+                Ok((archive, manifest)) => println!(
+                    "Packed template {} in {} (checksum {})",
+                    manifest.name,
+                    archive.to_string_lossy(),
+                    manifest.checksum
+                ),
+                Err(error) => {
+                    error!("Couldn't pack the template: {}", error);
+
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Subcommands::Batch {
+            directory,
+            names_from,
+            matrix,
+            answers,
+            output_dir,
+            force,
+            save_answers,
+        } => {
+            defer_warnings(true);
+
+            // asking for each project would defeat the purpose, missing keys
+            // are reported instead
+            set_interactive(false);
+
+            let common = read_answers_or_exit(answers.as_deref());
+
+            let entries = match names_from
+                .map(read_names)
+                .or_else(|| matrix.map(read_matrix))
+            {
+                Some(Ok(entries)) => entries,
+                Some(Err(error)) => {
+                    error!("{}", error);
+
+                    std::process::exit(1);
+                }
+                None => Vec::new(),
+            };
+
+            let path = Project::locate(&home, &directory);
+
+            let project = Project::from_path(&home, &path);
+
+            // every name is checked before generating anything
+            for (index, entry) in entries.iter().enumerate() {
+                if let Err(invalid) = validate_name(&entry.name, &project.name_rules) {
+                    error!("Invalid project name {:?}: {}", entry.name, invalid);
+
+                    std::process::exit(1);
+                }
+
+                if entries[..index]
+                    .iter()
+                    .any(|other| other.name == entry.name)
+                {
+                    error!("Project {} is listed twice", entry.name);
+
+                    std::process::exit(1);
+                }
+
+                if !force && output_dir.join(&entry.name).exists() {
+                    error!(
+                        "Path '{}' already exists, rerun with -f or --force to overwrite",
+                        output_dir.join(&entry.name).to_string_lossy()
+                    );
+
+                    std::process::exit(1);
+                }
+            }
+
+            let source = TemplateSource::from_path(&path);
+
+            let mut options = InitOptions {
+                force,
+                dependencies: resolve_or_exit(
+                    &home,
+                    &config,
+                    &source,
+                    &project,
+                    http_fetcher(&config).as_ref(),
+                )
+                .await,
+                source: Some(source),
+                manifest: true,
+                prompt: true,
+                save_answers,
+                verbose: args.verbose,
+                ..Default::default()
+            };
+
+            let started = Instant::now();
+
+            for entry in &entries {
+                options.output = output_dir.join(&entry.name);
+
+                // the answers of the row take precedence over the common ones
+                options.keys = common.iter().chain(&entry.keys).cloned().collect();
+
+                match init_helper(&entry.name, &config, &project, &options) {
+                    Ok(plan) => println!(
+                        "Generated {}: {}",
+                        options.output.to_string_lossy(),
+                        plan.stats()
+                    ),
+                    Err(error) => {
+                        error!("Couldn't generate {}: {}", entry.name, error);
+
+                        std::process::exit(1);
+                    }
+                }
+            }
+
+            defer_warnings(false);
+
+            match entries.len() {
+                1 => println!(
+                    "Generated 1 project in {:.2}s",
+                    started.elapsed().as_secs_f64()
+                ),
+                count => println!(
+                    "Generated {} projects in {:.2}s",
+                    count,
+                    started.elapsed().as_secs_f64()
+                ),
+            }
+
+            eprint!("{}", summary(&take_warnings()));
+        }
+
+        Subcommands::Render {
+            directory,
+            file,
+            name,
+            answers,
+            annotate,
+        } => {
+            let project = Project::from_path(&home, &directory);
+
+            let overrides = read_answers_or_exit(answers.as_deref());
+
+            let (keys, sources) = rendered_or_exit(template_keys_with_sources(
+                &name, &config, &project, &overrides,
+            ));
+
+            let path = project.path.join(&file);
+
+            if !path.is_file() {
+                error!(
+                    "No file {} in template {}",
+                    file.to_string_lossy(),
+                    project.path.to_string_lossy()
+                );
+
+                std::process::exit(1);
+            }
+
+            let template = rendered_or_exit(checked_template(&path));
+
+            if annotate {
+                print!("{}", annotate_template(&template.source, &keys, &sources));
+            } else {
+                print!(
+                    "{}",
+                    String::from_utf8_lossy(&rendered_or_exit(render_file(
+                        &template.source,
+                        &keys
+                    )))
+                );
+            }
+        }
+
+        Subcommands::Test { directory } => {
+            if !fixtures::run_fixtures(&home, &directory) {
+                std::process::exit(1);
+            }
+        }
+
+        Subcommands::Validate { directory } => {
+            set_interactive(false);
+
+            let template = Project::find(&home, &directory).unwrap_or(directory);
+
+            match validate(&config, &template) {
+                Ok(problems) if problems.is_empty() => {
+                    println!("{} is valid", template.to_string_lossy());
+                }
+                Ok(problems) => {
+                    for problem in &problems {
+                        println!("{}", problem);
+                    }
+
+                    println!(
+                        "{} problem{} found in {}",
+                        problems.len(),
+                        if problems.len() == 1 { "" } else { "s" },
+                        template.to_string_lossy()
+                    );
+
+                    std::process::exit(1);
+                }
+                Err(error) => {
+                    eprint!("{}", error);
+
+                    if !error.ends_with('\n') {
+                        eprintln!();
+                    }
+
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Subcommands::Freeze {
+            directory,
+            project,
+            output,
+        } => {
+            let name = match project.or_else(|| {
+                directory
+                    .canonicalize()
+                    .ok()?
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+            }) {
+                Some(name) => name,
+                None => {
+                    error!(
+                        "Couldn't name the project in {}, use --project",
+                        directory.to_string_lossy()
+                    );
+
+                    std::process::exit(1);
+                }
+            };
+
+            if !directory.is_dir() {
+                error!("{} isn't a directory", directory.to_string_lossy());
+
+                std::process::exit(1);
+            }
+
+            let output = output.unwrap_or_else(|| home.join(GLOBAL_TEMPLATE_DIRECTORY).join(&name));
+
+            match freeze(&directory, &output, &name, &config) {
+                Ok(frozen) => println!(
+                    "Created template {} in {} with {} templates, {} scripts and {} assets",
+                    name,
+                    output.to_string_lossy(),
+                    frozen.templates.len(),
+                    frozen.scripts.len(),
+                    frozen.assets.len()
+                ),
+                Err(error) => {
+                    error!("Couldn't freeze {}: {}", directory.to_string_lossy(), error);
+
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Subcommands::Template {
+            command:
+                TemplateCommand::New {
+                    name,
+                    path,
+                    description,
+                },
+        } => {
+            let directory =
+                path.unwrap_or_else(|| home.join(GLOBAL_TEMPLATE_DIRECTORY).join(&name));
+
+            let description = description
+                .or_else(|| input("Description:").filter(|description| !description.is_empty()));
+
+            let scaffold = Scaffold {
+                variables: confirm("Add example variables?", true),
+                name,
+                description,
+            };
+
+            if let Err(error) = scaffold_template(&directory, &scaffold) {
+                error!("Couldn't create the template: {}", error);
+
+                std::process::exit(1);
+            }
+
+            println!(
+                "Created template {} in {}",
+                scaffold.name,
+                directory.to_string_lossy()
+            );
+        }
+
+        Subcommands::SelfUpdate { check, force } => {
+            let fetcher = http_fetcher(&config);
+
+            let release = match latest_release(&config, fetcher.as_ref()).await {
+                Ok(release) => release,
+                Err(error) => {
+                    error!("{}", error);
+
+                    std::process::exit(1);
+                }
+            };
+
+            let current = Version::parse(env!("CARGO_PKG_VERSION")).unwrap();
+
+            let newer = release.version().map_or(true, |version| version > current);
+
+            if !newer && !force {
+                println!("pi {} is up to date", current);
+            } else if check {
+                println!(
+                    "pi {} is available, currently {}",
+                    release.tag_name, current
+                );
+            } else {
+                match install_release(&release, fetcher.as_ref()).await {
+                    Ok(path) => println!(
+                        "Updated {} to pi {}",
+                        path.to_string_lossy(),
+                        release.tag_name
+                    ),
+                    Err(error) => {
+                        error!("{}", error);
+
+                        std::process::exit(1);
+                    }
+                }
+            }
+        }
+
+        Subcommands::External(args) => {
+            std::process::exit(plugins::run_external(&args, &home));
+        }
     }
 
     Ok(())
 }
+
+/// Lock the `template` fetched from `source`, exiting if it can't be read.
+fn lock_template(template: &FetchedTemplate, source: &TemplateSource) -> LockedTemplate {
+    match LockedTemplate::from_fetched(template, source) {
+        Ok(lock) => lock,
+        Err(error) => {
+            error!("Couldn't compute the template's checksum: {}", error);
+
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Read the answers file at `path`, if any, exiting if it's invalid.
+fn read_answers_or_exit(path: Option<&Path>) -> Vec<(String, String)> {
+    match path.map(read_answers) {
+        Some(Ok(answers)) => answers,
+        Some(Err(error)) => {
+            error!("{}", error);
+
+            std::process::exit(1);
+        }
+        None => Vec::new(),
+    }
+}
+
+/// The value of a rendering, exiting with its report if it failed.
+fn rendered_or_exit<T>(result: Result<T, String>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(error) => {
+            error!("{}", error);
+
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The license `name` given with `--license`, exiting if it is unknown.
+fn license_or_exit(name: &str) -> License {
+    match requested_license(name) {
+        Ok(license) => license,
+        Err(error) => {
+            error!("{}", error);
+
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The HTTP fetcher following the TLS options of `config`, exiting if they
+/// are invalid.
+fn http_fetcher(config: &Config) -> Box<dyn HttpFetcher> {
+    match configured_fetcher(config) {
+        Ok(fetcher) => fetcher,
+        Err(error) => {
+            error!("{}", error);
+
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Fetch the dependencies of `project`, exiting if they can't be resolved.
+async fn resolve_or_exit(
+    home: &Path,
+    config: &Config,
+    source: &TemplateSource,
+    project: &Project,
+    fetcher: &dyn HttpFetcher,
+) -> Vec<Dependency> {
+    match resolve_dependencies(home, config, source, project, fetcher).await {
+        Ok(dependencies) => dependencies,
+        Err(error) => {
+            error!("{}", error);
+
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Describes a search query and its tag filters, for `pi search`'s messages.
+fn search_description(query: &Option<String>, tags: &[String]) -> String {
+    let mut description = query.clone().unwrap_or_default();
+
+    for tag in tags {
+        description.push_str(&format!(" [{}]", tag));
+    }
+
+    description.trim_start().to_string()
+}
+
+/// Report the generation of the project in `output` from `plan` in
+/// `elapsed`, along with the warnings deferred meanwhile.
+fn finished(output: &Path, plan: &Plan, elapsed: Duration) {
+    defer_warnings(false);
+
+    let name = output.to_string_lossy();
+
+    let warnings = take_warnings();
+
+    match warnings.len() {
+        0 => println!("Finished initializing project in {}", name),
+        1 => println!("Finished initializing project in {} with 1 warning", name),
+        count => println!(
+            "Finished initializing project in {} with {} warnings",
+            name, count
+        ),
+    }
+
+    println!("Created {} in {:.2}s", plan.stats(), elapsed.as_secs_f64());
+
+    print!("{}", plan.tree(&name));
+
+    eprint!("{}", summary(&warnings));
+
+    eprint!("{}", hints(&missing(&plan.requirements)));
+}
+
+/// Redirect stdout to stderr, for `--print-path`.
+fn reserve_stdout_or_exit() -> ReservedStdout {
+    match reserve_stdout() {
+        Ok(stdout) => stdout,
+        Err(error) => {
+            error!("Couldn't redirect stdout: {}", error);
+
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Print the path of the project in `output` on the reserved `stdout`.
+fn print_project_path(stdout: ReservedStdout, output: &Path) {
+    if let Err(error) = stdout.print_path(output) {
+        error!(
+            "Couldn't print the path of {}: {}",
+            output.to_string_lossy(),
+            error
+        );
+
+        std::process::exit(1);
+    }
+}
+
+/// Open the project in `output` generated from `plan` with `editor`.
+fn open_in_editor(editor: Option<String>, output: &Path, plan: &Plan) {
+    let name = output.to_string_lossy();
+
+    let editor = match editor {
+        Some(editor) => editor,
+        None => {
+            warn!(
+                "No editor to open {} with, set `editor` in $HOME/.pi.toml or $EDITOR",
+                name
+            );
+
+            return;
+        }
+    };
+
+    if let Err(error) = open_project(&editor, output, plan.entry.as_deref()) {
+        warn!("Couldn't open {} with {}: {}", name, editor, error);
+    }
+}
+
+/// Returns `name` if it can name a project of the template `project`, else
+/// the suggested name if the user accepts it, exiting otherwise.
+fn checked_name(name: String, project: &Project) -> String {
+    match validate_name(&name, &project.name_rules) {
+        Ok(()) => name,
+        Err(invalid) => {
+            if let Some(ref suggestion) = invalid.suggestion {
+                let question = format!("{}. Use {:?} instead?", invalid.reason, suggestion);
+
+                if is_interactive() && confirm(&question, true) {
+                    return suggestion.clone();
+                }
+            }
+
+            error!("Invalid project name: {}", invalid);
+
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Templates installed or listed in the templates repositories, exiting when
+/// there are none.
+async fn available_templates(
+    home: &Path,
+    config: &Config,
+    fetcher: &dyn HttpFetcher,
+) -> Vec<Candidate> {
+    let candidates = candidates(home, config, fetcher, TEMPLATES_REPOSITORY_TIMEOUT).await;
+
+    if candidates.is_empty() {
+        error!(
+            "No template to pick from, install one in {} or add a templates repository",
+            home.join(GLOBAL_TEMPLATE_DIRECTORY).to_string_lossy()
+        );
+
+        std::process::exit(1);
+    }
+
+    candidates
+}
+
+/// Let the user browse the templates and pick one, returning the `pi new`
+/// generating the project from it, exiting if none is picked.
+#[cfg(feature = "tui")]
+async fn browse_templates(home: &Path, config: &Config) -> Subcommands {
+    let fetcher = http_fetcher(config);
+
+    let candidates = available_templates(home, config, fetcher.as_ref()).await;
+
+    let selection = match browse(candidates, config) {
+        Ok(Some(selection)) => selection,
+        Ok(None) => std::process::exit(0),
+        Err(error) => {
+            error!("{}", error);
+
+            std::process::exit(1);
+        }
+    };
+
+    // templates of the registries are looked up by name
+    let directory = match selection.candidate.source {
+        TemplateSource::Path { path } if selection.candidate.repository.is_none() => path,
+        _ => PathBuf::from(&selection.candidate.name),
+    };
+
+    Subcommands::New {
+        directory,
+        name: Some(selection.name),
+        output: None,
+        force: false,
+        merge: false,
+        lock: false,
+        answers: None,
+        defines: Vec::new(),
+        save_answers: false,
+        license: None,
+        vcs: None,
+        no_vcs: false,
+        no_hooks: false,
+        trust_hooks: false,
+        open: false,
+        print_path: false,
+        dry_run: false,
+        locked: false,
+        fetch: true,
+    }
+}
+
+/// Let the user pick a template among the installed and registry ones,
+/// exiting when none is picked.
+async fn pick_template(home: &Path, config: &Config, fetcher: &dyn HttpFetcher) -> TemplateSource {
+    let candidates = available_templates(home, config, fetcher).await;
+
+    if !is_interactive() {
+        error!("No template given, and prompts are disabled");
+
+        std::process::exit(1);
+    }
+
+    match pick(candidates) {
+        Some(source) => source,
+        None => {
+            error!("No template picked");
+
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Source of the template `name` in the templates repositories, if it's
+/// listed there and the user agrees to fetch it, or `fetch` is set.
+async fn registry_source(
+    name: &Path,
+    config: &Config,
+    fetch: bool,
+    fetcher: &dyn HttpFetcher,
+) -> Option<TemplateSource> {
+    let name = name.to_str()?;
+
+    let entry = TemplateRepository::find_entry(
+        &config.all_templates_repositories(),
+        name,
+        fetcher,
+        TEMPLATES_REPOSITORY_TIMEOUT,
+    )
+    .await?;
+
+    if let (false, Some(min_pi_version)) = (entry.is_supported(), &entry.min_pi_version) {
+        warn!(
+            "Template {} requires pi {}, it may not render correctly",
+            name, min_pi_version
+        );
+    }
+
+    let question = format!(
+        "Template {} isn't installed, fetch it from {}?",
+        name, entry.repository
+    );
+
+    (fetch || confirm(&question, true)).then(|| entry.source())
+}
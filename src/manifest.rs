@@ -0,0 +1,249 @@
+//! Generation manifest, recorded in generated projects.
+//!
+//! The manifest remembers where a project's template came from, the keys it
+//! was rendered with, and a checksum of every file pi wrote, so that the
+//! project can later be compared against its template.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use crate::answers::is_secret;
+use crate::constants::{MANIFEST_DIRECTORY, MANIFEST_FILENAME, PACKAGE_MANIFEST_FILENAME};
+use crate::piignore::template_files;
+use crate::plan::Plan;
+
+/// Where a template was generated from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TemplateSource {
+    /// A template directory on the local filesystem
+    Path { path: PathBuf },
+    /// A git repository, at `rev` or at its default branch, holding a single
+    /// template or, when `template` is set, several ones in a `templates/`
+    /// directory
+    Git {
+        url: Url,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rev: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        template: Option<String>,
+    },
+    /// A GitHub gist
+    Gist { id: String },
+    /// An archive downloaded over HTTP
+    Archive { url: Url },
+}
+
+impl TemplateSource {
+    /// Source pointing to a local template directory, made absolute when
+    /// possible so that it can be found from anywhere.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        let path = path
+            .as_ref()
+            .canonicalize()
+            .unwrap_or_else(|_| path.as_ref().to_path_buf());
+
+        Self::Path { path }
+    }
+}
+
+impl std::fmt::Display for TemplateSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Path { path } => write!(f, "{}", path.to_string_lossy()),
+            Self::Git { url, rev, template } => {
+                write!(f, "{}", url)?;
+
+                if let Some(template) = template {
+                    write!(f, ":{}", template)?;
+                }
+
+                match rev {
+                    Some(rev) => write!(f, "#{}", rev),
+                    None => Ok(()),
+                }
+            }
+            Self::Gist { id } => write!(f, "gist:{}", id),
+            Self::Archive { url } => write!(f, "{}", url),
+        }
+    }
+}
+
+/// Returns `true` when `path` is made only of normal components, so that it
+/// can't point outside of the directory it is relative to.
+fn is_relative_inside(path: &Path) -> bool {
+    path.components().next().is_some()
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// A file written by pi, relative to the project root.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub path: PathBuf,
+    /// Hex encoded sha256 of the file's content when it was generated
+    pub checksum: String,
+}
+
+/// Record of a project generation.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Version of pi that generated the project
+    pub pi_version: String,
+    /// Name of the generated project
+    pub name: String,
+    /// Directories created by pi
+    #[serde(default)]
+    pub directories: Vec<PathBuf>,
+    pub source: TemplateSource,
+    /// Keys the templates were rendered with, secrets excluded
+    #[serde(default)]
+    pub answers: BTreeMap<String, String>,
+    /// Files written by pi, last since toml can't write an empty array of
+    /// tables after tables
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<ManifestFile>,
+}
+
+impl Manifest {
+    /// Build the manifest of a project generated from `plan`, leaving out
+    /// the answers to `secrets`, see [`is_secret`].
+    pub fn new(name: &str, source: TemplateSource, plan: &Plan, secrets: &[String]) -> Self {
+        Self {
+            pi_version: env!("CARGO_PKG_VERSION").to_string(),
+            name: name.to_string(),
+            directories: plan.directories.clone(),
+            source,
+            answers: plan
+                .keys
+                .iter()
+                .filter(|(key, _)| !is_secret(key, secrets))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+            files: plan
+                .files
+                .iter()
+                .map(|file| ManifestFile {
+                    path: file.path.clone(),
+                    checksum: match &file.source {
+                        Some(source) => file_checksum(source).unwrap_or_default(),
+                        None => checksum(&file.contents),
+                    },
+                })
+                .collect(),
+        }
+    }
+
+    /// Path of the manifest inside the project located in `project_dir`.
+    pub fn path<P: AsRef<Path>>(project_dir: P) -> PathBuf {
+        project_dir
+            .as_ref()
+            .join(MANIFEST_DIRECTORY)
+            .join(MANIFEST_FILENAME)
+    }
+
+    /// Read the manifest of the project located in `project_dir`.
+    pub fn from_project<P: AsRef<Path>>(
+        project_dir: P,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = Self::path(&project_dir);
+
+        let content = fs::read_to_string(&path).map_err(|error| {
+            format!(
+                "Couldn't read manifest {}: {}",
+                path.to_string_lossy(),
+                error
+            )
+        })?;
+
+        let manifest: Self = toml::from_str(&content)?;
+
+        // the manifest's paths are removed and overwritten, they must stay
+        // inside the project
+        if let Some(outside) = manifest
+            .directories
+            .iter()
+            .chain(manifest.files.iter().map(|file| &file.path))
+            .find(|path| !is_relative_inside(path))
+        {
+            return Err(format!(
+                "Invalid manifest {}: {} isn't a path inside the project",
+                path.to_string_lossy(),
+                outside.to_string_lossy()
+            )
+            .into());
+        }
+
+        Ok(manifest)
+    }
+
+    /// Write the manifest inside the project located in `project_dir`.
+    pub fn write<P: AsRef<Path>>(&self, project_dir: P) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::path(&project_dir);
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, toml::to_string(self)?)?;
+
+        Ok(())
+    }
+
+    /// Returns the answers as overriding keys, suitable for re-rendering the
+    /// template exactly as it was generated.
+    pub fn overrides(&self) -> Vec<(String, String)> {
+        self.answers
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+}
+
+/// Hex encoded sha256 of `contents`.
+pub fn checksum(contents: &[u8]) -> String {
+    hex::encode(Sha256::digest(contents))
+}
+
+/// Hex encoded sha256 of the file at `path`, read a chunk at a time.
+pub fn file_checksum<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Hex encoded sha256 identifying the contents of the template in `root`.
+///
+/// It covers the path and checksum of every file of the template, version
+/// control directories and the manifest added by `pi pack` excluded, so it
+/// doesn't depend on how the template was fetched.
+pub fn template_checksum<P: AsRef<Path>>(root: P) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+
+    for path in template_files(root.as_ref()) {
+        if path == Path::new(PACKAGE_MANIFEST_FILENAME) {
+            continue;
+        }
+
+        let contents = fs::read(root.as_ref().join(&path))?;
+
+        let path = path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        hasher.update(format!("{}  {}\n", checksum(&contents), path));
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
@@ -0,0 +1,179 @@
+//! Validation of project names.
+//!
+//! A project name must be usable as a directory name and, when the template
+//! declares `name_rules`, follow the naming rules of its ecosystem.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Characters that can't appear in a file name on some platform.
+const FORBIDDEN_CHARACTERS: &[char] = &['/', '\\', '<', '>', ':', '"', '|', '?', '*'];
+
+/// Naming rules of an ecosystem a template can require.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NameRule {
+    /// Rust crate names
+    Crate,
+    /// npm package names, without scope
+    Npm,
+    /// Python module names
+    Python,
+}
+
+/// Why a project name was rejected, with a name that would be accepted if
+/// one can be derived.
+#[derive(Debug)]
+pub struct InvalidName {
+    pub reason: String,
+    pub suggestion: Option<String>,
+}
+
+impl fmt::Display for InvalidName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.reason)?;
+
+        match self.suggestion {
+            Some(ref suggestion) => write!(f, ", try {:?}", suggestion),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Check that `name` can name the directory of a project and follows every
+/// rule of `rules`.
+pub fn validate_name(name: &str, rules: &[NameRule]) -> Result<(), InvalidName> {
+    let suggestion = |rule: Option<NameRule>| {
+        let suggestion = suggest(name, rule);
+
+        (!suggestion.is_empty() && suggestion != name).then_some(suggestion)
+    };
+
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(InvalidName {
+            reason: format!("{:?} can't name a directory", name),
+            suggestion: None,
+        });
+    }
+
+    if name
+        .chars()
+        .any(|c| c.is_control() || FORBIDDEN_CHARACTERS.contains(&c))
+        || name.ends_with(['.', ' '])
+    {
+        return Err(InvalidName {
+            reason: format!(
+                "{:?} can't name a directory on every platform, it can't hold path separators, control characters or any of <>:\"|?* nor end with a dot or space",
+                name
+            ),
+            suggestion: suggestion(None),
+        });
+    }
+
+    for rule in rules {
+        if let Some(reason) = rule.check(name) {
+            return Err(InvalidName {
+                reason,
+                suggestion: suggestion(Some(*rule)),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+impl NameRule {
+    /// Returns why `name` breaks the rule, if it does.
+    fn check(self, name: &str) -> Option<String> {
+        let starts_with_letter = name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+
+        match self {
+            Self::Crate if !starts_with_letter => Some(format!(
+                "crate name {:?} must start with a letter or an underscore",
+                name
+            )),
+            Self::Crate
+                if !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') =>
+            {
+                Some(format!(
+                    "crate name {:?} can only hold ASCII letters, digits, - and _",
+                    name
+                ))
+            }
+            Self::Npm if name.len() > 214 => Some(format!(
+                "npm package name {:?} is longer than 214 characters",
+                name
+            )),
+            Self::Npm if name.starts_with(['.', '_']) => Some(format!(
+                "npm package name {:?} can't start with . or _",
+                name
+            )),
+            Self::Npm
+                if !name.chars().all(|c| {
+                    c.is_ascii_lowercase() || c.is_ascii_digit() || "-._~".contains(c)
+                }) =>
+            {
+                Some(format!(
+                    "npm package name {:?} can only hold lowercase letters, digits and -._~",
+                    name
+                ))
+            }
+            Self::Python
+                if !starts_with_letter
+                    || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') =>
+            {
+                Some(format!(
+                    "python module name {:?} must be an identifier, made of letters, digits and _",
+                    name
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Derive from `name` a name following `rule`, or any directory name.
+fn suggest(name: &str, rule: Option<NameRule>) -> String {
+    let separator = match rule {
+        Some(NameRule::Npm) => '-',
+        Some(_) => '_',
+        None => '-',
+    };
+
+    let mut suggestion = name
+        .trim()
+        .chars()
+        .map(|c| match rule {
+            None if c.is_control() || FORBIDDEN_CHARACTERS.contains(&c) => separator,
+            None => c,
+            Some(NameRule::Npm) if c.is_ascii_alphanumeric() || "-._~".contains(c) => {
+                c.to_ascii_lowercase()
+            }
+            Some(NameRule::Crate) if c.is_ascii_alphanumeric() || c == '_' => c,
+            Some(NameRule::Python) if c.is_ascii_alphanumeric() || c == '_' => {
+                c.to_ascii_lowercase()
+            }
+            Some(_) => separator,
+        })
+        .collect::<String>()
+        .trim_end_matches(['.', ' '])
+        .to_string();
+
+    match rule {
+        Some(NameRule::Crate) | Some(NameRule::Python)
+            if suggestion.starts_with(|c: char| c.is_ascii_digit()) =>
+        {
+            suggestion.insert(0, '_')
+        }
+        Some(NameRule::Npm) => suggestion = suggestion.trim_start_matches(['.', '_']).to_string(),
+        _ => {}
+    }
+
+    suggestion
+}
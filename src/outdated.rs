@@ -0,0 +1,131 @@
+//! Detection of templates with upstream updates.
+//!
+//! Templates kept as git clones, either in the cache by `pi git` or
+//! installed in `$HOME/.pi_templates/`, remember the commit they were last
+//! used at. `pi outdated` compares it with the commit the remote's default
+//! branch currently points to.
+
+use std::fmt::Display;
+use std::path::Path;
+
+use git2::{Cred, Direction, RemoteCallbacks, Repository};
+use tracing::warn;
+use url::Url;
+
+use crate::cache::{cache_directory, clones};
+use crate::constants::{GLOBAL_TEMPLATE_DIRECTORY, REPOSITORIES_CACHE_DIRECTORY};
+use crate::tls::git_certificate_check;
+use crate::types::Config;
+
+/// A template clone whose remote has moved on.
+#[derive(Debug)]
+pub struct OutdatedTemplate {
+    /// How the template is referred to, `pi new NAME` or the repository url
+    pub name: String,
+    pub local_commit: String,
+    pub remote_commit: String,
+}
+
+impl Display for OutdatedTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} -> {}",
+            self.name,
+            short(&self.local_commit),
+            short(&self.remote_commit)
+        )
+    }
+}
+
+fn short(commit: &str) -> &str {
+    commit.get(..7).unwrap_or(commit)
+}
+
+/// Check every template clone for upstream updates, returning the outdated
+/// ones. Templates whose remote can't be reached are reported and skipped.
+pub fn outdated_templates<H: AsRef<Path>>(home: H, config: &Config) -> Vec<OutdatedTemplate> {
+    let installed = home.as_ref().join(GLOBAL_TEMPLATE_DIRECTORY);
+
+    let cached = cache_directory().map(|cache| cache.join(REPOSITORIES_CACHE_DIRECTORY));
+
+    let mut outdated = Vec::new();
+
+    for repository_path in clones(&installed)
+        .into_iter()
+        .chain(cached.iter().flat_map(|cached| clones(cached)))
+    {
+        match check_repository(&repository_path, config) {
+            Ok(Some(mut template)) => {
+                if let Ok(name) = repository_path.strip_prefix(&installed) {
+                    template.name = format!("pi new {}", name.to_string_lossy());
+                }
+
+                outdated.push(template);
+            }
+            Ok(None) => {}
+            Err(error) => warn!(
+                "Couldn't check {} for updates: {}",
+                repository_path.to_string_lossy(),
+                error
+            ),
+        }
+    }
+
+    outdated
+}
+
+/// Compare the checked out commit of the repository at `path` with the
+/// default branch of its `origin` remote.
+fn check_repository(
+    path: &Path,
+    config: &Config,
+) -> Result<Option<OutdatedTemplate>, Box<dyn std::error::Error>> {
+    let repository = Repository::open(path)?;
+
+    let local_commit = repository.head()?.peel_to_commit()?.id();
+
+    let mut remote = repository.find_remote("origin")?;
+
+    let url = remote.url().unwrap_or_default().to_string();
+
+    let token = url
+        .parse::<Url>()
+        .ok()
+        .and_then(|url| config.github_token_for(&url));
+
+    let mut callbacks = RemoteCallbacks::new();
+
+    if let Some(token) = token {
+        callbacks.credentials(move |_url, _username, _allowed| {
+            Cred::userpass_plaintext("x-access-token", &token)
+        });
+    }
+
+    git_certificate_check(&mut callbacks);
+
+    // like `git ls-remote`, nothing is downloaded
+    let connection = remote.connect_auth(Direction::Fetch, Some(callbacks), None)?;
+
+    let remote_commit = connection
+        .list()?
+        .iter()
+        .find(|head| head.name() == "HEAD")
+        .map(|head| head.oid())
+        .ok_or("the remote has no default branch")?;
+
+    // a remote commit unknown locally was never fetched, so it is newer
+    let up_to_date = remote_commit == local_commit
+        || (repository.find_commit(remote_commit).is_ok()
+            && repository.graph_descendant_of(local_commit, remote_commit)?);
+
+    if up_to_date {
+        return Ok(None);
+    }
+
+    Ok(Some(OutdatedTemplate {
+        name: url,
+        local_commit: local_commit.to_string(),
+        remote_commit: remote_commit.to_string(),
+    }))
+}
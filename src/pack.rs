@@ -0,0 +1,230 @@
+//! `pi pack`, bundling a template directory into an archive that `pi new`
+//! and `pi add` accept.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, DateTime as ZipDateTime, ZipWriter};
+
+use crate::constants::PACKAGE_MANIFEST_FILENAME;
+use crate::manifest::template_checksum;
+use crate::piignore::template_files;
+use crate::types::{Config, Project};
+use crate::util::{check_template, source_date_epoch};
+
+/// Archive format produced by `pi pack`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackFormat {
+    TarGz,
+    Zip,
+}
+
+impl PackFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::TarGz => "tar.gz",
+            Self::Zip => "zip",
+        }
+    }
+}
+
+impl FromStr for PackFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "tar.gz" | "tgz" => Ok(Self::TarGz),
+            "zip" => Ok(Self::Zip),
+            _ => Err(format!(
+                "Unknown archive format {}, expected tar.gz or zip",
+                value
+            )),
+        }
+    }
+}
+
+/// Description of a packed template, stored at the root of the archive.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PackageManifest {
+    pub name: String,
+    pub version: Option<String>,
+    /// Checksum of the template, see [`template_checksum`]
+    pub checksum: String,
+    /// Version of pi that packed the template
+    pub pi_version: String,
+}
+
+impl PackageManifest {
+    /// Read the manifest of the packed template extracted in `directory`, if
+    /// it has one.
+    pub fn from_directory<P: AsRef<Path>>(directory: P) -> Option<Result<Self, toml::de::Error>> {
+        let content =
+            fs::read_to_string(directory.as_ref().join(PACKAGE_MANIFEST_FILENAME)).ok()?;
+
+        Some(toml::from_str(&content))
+    }
+}
+
+/// Check the template in `directory` and bundle it, with a
+/// [`PackageManifest`], in an archive written to `output`, or next to the
+/// template as `<name>-<version>.<extension>`.
+///
+/// Returns the path of the archive along with its manifest.
+pub fn pack<P: AsRef<Path>>(
+    directory: P,
+    project: &Project,
+    config: &Config,
+    format: PackFormat,
+    output: Option<PathBuf>,
+) -> Result<(PathBuf, PackageManifest), Box<dyn std::error::Error>> {
+    let directory = directory.as_ref().canonicalize()?;
+
+    let directory = directory.as_path();
+
+    check_template(config, project)?;
+
+    let name = project
+        .template_name()
+        .ok_or("Couldn't infer the template's name, set template.name")?;
+
+    let version = project
+        .template
+        .as_ref()
+        .and_then(|metadata| metadata.version.clone());
+
+    let manifest = PackageManifest {
+        name: name.clone(),
+        version: version.clone(),
+        checksum: template_checksum(directory)?,
+        pi_version: env!("CARGO_PKG_VERSION").to_string(),
+    };
+
+    let output = output.unwrap_or_else(|| {
+        let stem = match &version {
+            Some(version) => format!("{}-{}", name, version),
+            None => name.clone(),
+        };
+
+        directory
+            .parent()
+            .unwrap_or(directory)
+            .join(format!("{}.{}", stem, format.extension()))
+    });
+
+    // the template is stored in a directory named after it
+    let mut entries = vec![(
+        PathBuf::from(&name).join(PACKAGE_MANIFEST_FILENAME),
+        toml::to_string(&manifest)?.into_bytes(),
+        0o644,
+    )];
+
+    for path in template_files(directory) {
+        if path == Path::new(PACKAGE_MANIFEST_FILENAME) {
+            continue;
+        }
+
+        let full_path = directory.join(&path);
+
+        entries.push((
+            PathBuf::from(&name).join(&path),
+            fs::read(&full_path)?,
+            file_mode(&full_path),
+        ));
+    }
+
+    // entries are dated from $SOURCE_DATE_EPOCH if set, and left undated otherwise
+    let epoch = source_date_epoch()?;
+
+    let file = File::create(&output)?;
+
+    match format {
+        PackFormat::TarGz => {
+            let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+
+            for (path, contents, mode) in &entries {
+                let mut header = tar::Header::new_gnu();
+
+                header.set_size(contents.len() as u64);
+                header.set_mode(*mode);
+
+                if let Some(ref time) = epoch {
+                    header.set_mtime(time.timestamp().max(0) as u64);
+                }
+
+                header.set_cksum();
+
+                builder.append_data(&mut header, path, contents.as_slice())?;
+            }
+
+            builder.into_inner()?.finish()?;
+        }
+        PackFormat::Zip => {
+            let mut writer = ZipWriter::new(file);
+
+            for (path, contents, mode) in &entries {
+                let mut options = FileOptions::default()
+                    .compression_method(CompressionMethod::Deflated)
+                    .unix_permissions(*mode);
+
+                // zip times start in 1980
+                if let Some(time) = epoch.as_ref().and_then(zip_time) {
+                    options = options.last_modified_time(time);
+                }
+
+                // zip paths always use forward slashes
+                let path = path
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+
+                writer.start_file(path, options)?;
+
+                writer.write_all(contents)?;
+            }
+
+            writer.finish()?;
+        }
+    }
+
+    Ok((output, manifest))
+}
+
+/// Permissions of the file at `path`, keeping scripts executable.
+fn file_mode(path: &Path) -> u32 {
+    #[cfg(not(target_os = "windows"))]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        fs::metadata(path)
+            .map(|metadata| metadata.permissions().mode() & 0o777)
+            .unwrap_or(0o644)
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        let _ = path;
+
+        0o644
+    }
+}
+
+/// Returns `time` as the date of a zip entry, if zip can represent it.
+fn zip_time(time: &DateTime<Utc>) -> Option<ZipDateTime> {
+    ZipDateTime::from_date_and_time(
+        u16::try_from(time.year()).ok()?,
+        time.month() as u8,
+        time.day() as u8,
+        time.hour() as u8,
+        time.minute() as u8,
+        time.second() as u8,
+    )
+    .ok()
+}
@@ -0,0 +1,97 @@
+//! Interactive choice of a template, when `pi new` is only given the name of
+//! the project.
+
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::http::HttpFetcher;
+use crate::manifest::TemplateSource;
+use crate::prompt::fuzzy_select;
+use crate::types::{Config, Project, TemplateMetadata, TemplateRepository};
+
+/// A template the user can pick.
+#[derive(Debug)]
+pub struct Candidate {
+    pub name: String,
+    pub description: Option<String>,
+    /// Templates repository listing the template, `None` if it is installed
+    pub repository: Option<String>,
+    pub source: TemplateSource,
+}
+
+impl fmt::Display for Candidate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+
+        if let Some(description) = self.description.as_deref().filter(|d| !d.is_empty()) {
+            write!(f, " - {}", description)?;
+        }
+
+        match &self.repository {
+            Some(repository) => write!(f, " ({})", repository),
+            None => write!(f, " (installed)"),
+        }
+    }
+}
+
+/// Returns the templates installed in `$HOME/.pi_templates/`, followed by the
+/// ones of the templates repositories that aren't installed.
+pub async fn candidates<H: AsRef<Path>>(
+    home: H,
+    config: &Config,
+    fetcher: &dyn HttpFetcher,
+    timeout: Duration,
+) -> Vec<Candidate> {
+    let mut candidates = Project::installed(&home)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|path| {
+            let metadata = TemplateMetadata::from_directory(&path).unwrap_or_default();
+
+            Candidate {
+                name: path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                description: metadata.description,
+                repository: None,
+                source: TemplateSource::from_path(path),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let repositories = config.all_templates_repositories();
+
+    for (repository, entries) in TemplateRepository::read_all(&repositories, fetcher, timeout).await
+    {
+        for entry in entries {
+            if candidates
+                .iter()
+                .any(|candidate| candidate.name == entry.name)
+            {
+                continue;
+            }
+
+            candidates.push(Candidate {
+                source: entry.source(),
+                name: entry.name,
+                description: Some(entry.description),
+                repository: Some(repository.to_string()),
+            });
+        }
+    }
+
+    candidates
+}
+
+/// Ask the user to pick one of `candidates`, returning where to fetch it
+/// from.
+pub fn pick(candidates: Vec<Candidate>) -> Option<TemplateSource> {
+    let index = fuzzy_select("Template", &candidates)?;
+
+    candidates
+        .into_iter()
+        .nth(index)
+        .map(|candidate| candidate.source)
+}
@@ -0,0 +1,54 @@
+//! `.piignore`, files of a template directory left out when it is walked.
+//!
+//! Templates listing their files in `template.toml` only generate those, but
+//! gists generate every file they hold, and `pi pack` and `pi watch` look at
+//! the whole template directory. A `.piignore` at the template root, in
+//! gitignore syntax, keeps build artifacts and editor files out of them.
+
+use std::path::{Path, PathBuf};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use tracing::warn;
+
+use crate::constants::PIIGNORE_FILENAME;
+use crate::verify::project_files;
+
+/// Returns the patterns of the `.piignore` of the template in `root`, if it
+/// has one. Invalid patterns are reported and skipped.
+pub fn piignore(root: &Path) -> Option<Gitignore> {
+    let path = root.join(PIIGNORE_FILENAME);
+
+    if !path.is_file() {
+        return None;
+    }
+
+    let mut builder = GitignoreBuilder::new(root);
+
+    if let Some(error) = builder.add(&path) {
+        warn!("Invalid pattern in {}: {}", path.to_string_lossy(), error);
+    }
+
+    match builder.build() {
+        Ok(ignore) => Some(ignore),
+        Err(error) => {
+            warn!("Ignoring {}: {}", path.to_string_lossy(), error);
+
+            None
+        }
+    }
+}
+
+/// List the files of the template in `root` like [`project_files`], leaving
+/// out the ones its `.piignore` matches, or that are in a directory it
+/// matches.
+pub fn template_files(root: &Path) -> Vec<PathBuf> {
+    let files = project_files(root);
+
+    match piignore(root) {
+        Some(ignore) => files
+            .into_iter()
+            .filter(|path| !ignore.matched_path_or_any_parents(path, false).is_ignore())
+            .collect(),
+        None => files,
+    }
+}
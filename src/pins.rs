@@ -0,0 +1,78 @@
+//! Checksum pinning of remote templates.
+//!
+//! `pi git --pin` records the checksum of the template it fetched in the
+//! global configuration, later fetches of the same repository and rev are
+//! refused if the template's contents changed.
+
+use std::path::Path;
+
+use toml_edit::{value, Table};
+
+use crate::config_file::ConfigFile;
+use crate::fetch::same_repository;
+use crate::manifest::TemplateSource;
+use crate::types::Config;
+
+/// Returns the checksum pinned in the configuration for `source`, if any.
+pub fn pinned_checksum<'a>(config: &'a Config, source: &TemplateSource) -> Option<&'a str> {
+    let (url, rev, template) = match source {
+        TemplateSource::Git { url, rev, template } => (url, rev, template),
+        _ => return None,
+    };
+
+    config
+        .pins
+        .iter()
+        .flatten()
+        .find(|pin| {
+            same_repository(&pin.repository, url) && &pin.rev == rev && &pin.template == template
+        })
+        .map(|pin| pin.checksum.as_str())
+}
+
+/// Make sure the `checksum` of the template fetched from `source` matches the
+/// one pinned for it, if any.
+pub fn check_pin(config: &Config, source: &TemplateSource, checksum: &str) -> Result<(), String> {
+    match pinned_checksum(config, source) {
+        Some(pinned) if pinned != checksum => Err(format!(
+            "Template {} doesn't match its pinned checksum, expected {} but got {}",
+            source, pinned, checksum
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Record `checksum` as the pinned checksum of `source` in the configuration
+/// file at `config_path`.
+pub fn record_pin<P: AsRef<Path>>(
+    config_path: P,
+    source: &TemplateSource,
+    checksum: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (url, rev, template) = match source {
+        TemplateSource::Git { url, rev, template } => (url, rev, template),
+        _ => return Err(format!("Only git templates can be pinned, not {}", source).into()),
+    };
+
+    let mut pin = Table::new();
+
+    pin.insert("repository", value(url.as_str()));
+
+    if let Some(rev) = rev {
+        pin.insert("rev", value(rev.as_str()));
+    }
+
+    if let Some(template) = template {
+        pin.insert("template", value(template.as_str()));
+    }
+
+    pin.insert("checksum", value(checksum));
+
+    let mut config_file = ConfigFile::open(config_path)?;
+
+    config_file.push_table("pins", pin)?;
+
+    config_file.save()?;
+
+    Ok(())
+}
@@ -0,0 +1,551 @@
+//! In-memory representation of a project about to be generated.
+//!
+//! Templates are first rendered into a [`Plan`], which is then written to
+//! disk. Keeping the rendering separate from the writing lets pi compare a
+//! template against an existing project without touching it.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Cursor, Write};
+#[cfg(not(target_os = "windows"))]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+#[cfg(not(target_os = "windows"))]
+use lazy_static::lazy_static;
+use rustache::{Data, HashBuilder};
+use zip::result::ZipResult;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::constants::TEMPLATE_FILENAME;
+use crate::hooks::Hook;
+use crate::requirements::Requirement;
+
+/// Where a planned file comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    /// Empty file listed under `files`
+    Blank,
+    /// Rendered license
+    License,
+    /// Rendered builtin README
+    Readme,
+    /// Rendered file listed under `templates`
+    Template,
+    /// Rendered file listed under `scripts`, made executable
+    Script,
+    /// File listed under `assets`, copied as is
+    Asset,
+}
+
+/// A file to write, with its path relative to the project root.
+#[derive(Debug, Clone)]
+pub struct PlannedFile {
+    pub path: PathBuf,
+    pub contents: Vec<u8>,
+    /// File of the template copied instead of writing `contents`, for assets
+    pub source: Option<PathBuf>,
+    pub kind: FileKind,
+}
+
+impl PlannedFile {
+    /// Contents of the file, read from the template for assets.
+    pub fn bytes(&self) -> io::Result<Cow<'_, [u8]>> {
+        match &self.source {
+            Some(source) => fs::read(source).map(Cow::Owned),
+            None => Ok(Cow::Borrowed(&self.contents)),
+        }
+    }
+}
+
+/// Everything a template produces, rendered but not written yet.
+#[derive(Debug, Default)]
+pub struct Plan {
+    /// Directories to create, relative to the project root
+    pub directories: Vec<PathBuf>,
+    /// Files to write, in order
+    pub files: Vec<PlannedFile>,
+    /// String representation of the keys the templates were rendered with
+    pub keys: BTreeMap<String, String>,
+    /// Tools the generated project needs
+    pub requirements: Vec<Requirement>,
+    /// File to open in the editor, relative to the project root
+    pub entry: Option<PathBuf>,
+    /// Commands to run in the project once written, dependencies' first
+    pub hooks: Vec<Hook>,
+    /// Permissions of the written files
+    pub permissions: Permissions,
+}
+
+/// What a [`Plan`] generates, see [`Plan::stats`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    pub directories: usize,
+    /// Every file, templates, scripts and assets included
+    pub files: usize,
+    pub templates: usize,
+    pub scripts: usize,
+    pub assets: usize,
+    /// Size of the files
+    pub bytes: u64,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let count = |count: usize, one: &str, many: &str| match count {
+            1 => format!("1 {}", one),
+            count => format!("{} {}", count, many),
+        };
+
+        write!(
+            f,
+            "{} and {}",
+            count(self.directories, "directory", "directories"),
+            count(self.files, "file", "files")
+        )?;
+
+        let kinds = [
+            (self.templates, "template", "templates"),
+            (self.scripts, "script", "scripts"),
+            (self.assets, "asset", "assets"),
+        ]
+        .iter()
+        .filter(|(number, _, _)| *number > 0)
+        .map(|(number, one, many)| count(*number, one, many))
+        .collect::<Vec<_>>();
+
+        if !kinds.is_empty() {
+            write!(f, " ({})", kinds.join(", "))?;
+        }
+
+        write!(f, ", {}", human_size(self.bytes))
+    }
+}
+
+/// `bytes` in the largest unit keeping the size above 1.
+pub fn human_size(bytes: u64) -> String {
+    let units = ["KiB", "MiB", "GiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit + 1 < units.len() {
+        size /= 1024.0;
+
+        unit += 1;
+    }
+
+    format!("{:.1} {}", size, units[unit])
+}
+
+/// Mode of private files, readable by their owner only.
+const PRIVATE_MODE: u32 = 0o600;
+
+/// Unix permissions of the planned files, before the umask applies.
+#[derive(Debug, Default, Clone)]
+pub struct Permissions {
+    /// Mode of files, 0o666 if unset
+    pub file_mode: Option<u32>,
+    /// Mode of scripts, 0o777 if unset
+    pub script_mode: Option<u32>,
+    /// Files, relative to the project root, with mode 0o600
+    pub private: Vec<PathBuf>,
+}
+
+impl Permissions {
+    /// Mode of `file`, `None` for assets keeping the mode of the file they
+    /// are copied from.
+    pub fn mode(&self, file: &PlannedFile) -> Option<u32> {
+        if self.private.contains(&file.path) {
+            return Some(PRIVATE_MODE);
+        }
+
+        match file.kind {
+            FileKind::Asset => None,
+            FileKind::Script => Some(self.script_mode.unwrap_or(0o777)),
+            _ => Some(self.file_mode.unwrap_or(0o666)),
+        }
+    }
+}
+
+impl Plan {
+    /// Record the keys of `hash` that can be represented as strings.
+    pub fn record_keys(&mut self, hash: &HashBuilder) {
+        self.keys = hash
+            .data
+            .iter()
+            .filter_map(|(key, value)| {
+                let value = match value {
+                    Data::String(value) => value.clone(),
+                    Data::Bool(value) => value.to_string(),
+                    Data::Integer(value) => value.to_string(),
+                    Data::Float(value) => value.to_string(),
+                    _ => return None,
+                };
+
+                Some((key.clone(), value))
+            })
+            .collect();
+    }
+
+    /// Returns the planned file at `path`, relative to the project root.
+    pub fn file<P: AsRef<Path>>(&self, path: P) -> Option<&PlannedFile> {
+        self.files.iter().find(|file| file.path == path.as_ref())
+    }
+
+    /// Paths of the planned files that already exist in `output` with other
+    /// contents.
+    pub fn conflicts<P: AsRef<Path>>(&self, output: P) -> Vec<PathBuf> {
+        self.files
+            .iter()
+            .filter(|file| {
+                fs::read(output.as_ref().join(&file.path))
+                    .is_ok_and(|contents| file.bytes().map_or(true, |planned| contents != *planned))
+            })
+            .map(|file| file.path.clone())
+            .collect()
+    }
+
+    /// Paths of the planned files that already exist in `output`, whatever
+    /// their contents.
+    pub fn existing<P: AsRef<Path>>(&self, output: P) -> Vec<PathBuf> {
+        self.files
+            .iter()
+            .filter(|file| output.as_ref().join(&file.path).exists())
+            .map(|file| file.path.clone())
+            .collect()
+    }
+
+    /// Remove the file at `path` from the plan.
+    pub fn skip<P: AsRef<Path>>(&mut self, path: P) {
+        self.files.retain(|file| file.path != path.as_ref());
+    }
+
+    /// Remove the directory or file at `path` from the plan, along with
+    /// everything under it.
+    pub fn exclude<P: AsRef<Path>>(&mut self, path: P) {
+        let path = path.as_ref();
+
+        self.directories
+            .retain(|directory| !directory.starts_with(path));
+        self.files.retain(|file| !file.path.starts_with(path));
+    }
+
+    /// Add the directories and files of `other` to this plan, files of
+    /// `other` replacing the ones at the same path.
+    pub fn merge(&mut self, other: Plan) {
+        for directory in other.directories {
+            if !self.directories.contains(&directory) {
+                self.directories.push(directory);
+            }
+        }
+
+        for file in other.files {
+            match self
+                .files
+                .iter_mut()
+                .find(|planned| planned.path == file.path)
+            {
+                Some(planned) => *planned = file,
+                None => self.files.push(file),
+            }
+        }
+
+        self.keys.extend(other.keys);
+
+        for requirement in other.requirements {
+            if !self
+                .requirements
+                .iter()
+                .any(|required| required.tool == requirement.tool)
+            {
+                self.requirements.push(requirement);
+            }
+        }
+
+        if other.entry.is_some() {
+            self.entry = other.entry;
+        }
+
+        self.hooks.extend(other.hooks);
+
+        self.permissions.file_mode = other.permissions.file_mode.or(self.permissions.file_mode);
+        self.permissions.script_mode = other
+            .permissions
+            .script_mode
+            .or(self.permissions.script_mode);
+        self.permissions.private.extend(other.permissions.private);
+    }
+
+    /// Count the planned directories and files, and the bytes they hold.
+    pub fn stats(&self) -> Stats {
+        let mut stats = Stats {
+            directories: self.directories.len(),
+            files: self.files.len(),
+            ..Stats::default()
+        };
+
+        for file in &self.files {
+            match file.kind {
+                FileKind::Template => stats.templates += 1,
+                FileKind::Script => stats.scripts += 1,
+                FileKind::Asset => stats.assets += 1,
+                _ => {}
+            }
+
+            stats.bytes += match file.source {
+                Some(ref source) => fs::metadata(source).map_or(0, |metadata| metadata.len()),
+                None => file.contents.len() as u64,
+            };
+        }
+
+        stats
+    }
+
+    /// Draw the planned directories and files as a tree rooted at `root`.
+    pub fn tree(&self, root: &str) -> String {
+        let mut tree = TreeNode::default();
+
+        for directory in &self.directories {
+            tree.insert(directory, true);
+        }
+
+        for file in &self.files {
+            tree.insert(&file.path, false);
+        }
+
+        let mut drawing = format!("{}/\n", root);
+
+        tree.draw("", &mut drawing);
+
+        drawing
+    }
+
+    /// Bundle the planned directories and files in a zip archive, under a
+    /// `root` directory.
+    pub fn zip(&self, root: &str) -> ZipResult<Vec<u8>> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+
+        // zip paths always use forward slashes
+        let zip_path = |path: &Path| {
+            path.components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .fold(root.to_string(), |path, component| {
+                    format!("{}/{}", path, component)
+                })
+        };
+
+        writer.add_directory(root, FileOptions::default())?;
+
+        for directory in &self.directories {
+            writer.add_directory(zip_path(directory), FileOptions::default())?;
+        }
+
+        for file in &self.files {
+            // the umask of whoever extracts the archive is unknown, apply the
+            // usual one
+            let mode = self.permissions.mode(file).unwrap_or(0o666) & !0o022;
+
+            let options = FileOptions::default()
+                .compression_method(CompressionMethod::Deflated)
+                .unix_permissions(mode);
+
+            writer.start_file(zip_path(&file.path), options)?;
+
+            writer.write_all(&file.bytes()?)?;
+        }
+
+        Ok(writer.finish()?.into_inner())
+    }
+
+    /// Create the planned directories and files in `output`, stopping at the
+    /// first file that can't be written.
+    pub fn write<P: AsRef<Path>>(&self, output: P) -> Result<(), String> {
+        let output = output.as_ref();
+
+        // create directories
+        let _ = fs::create_dir_all(output);
+
+        for directory in &self.directories {
+            let _ = fs::create_dir(output.join(directory));
+        }
+
+        for file in &self.files {
+            write_file(output, file, self.permissions.mode(file))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+lazy_static! {
+    /// Permissions removed from the mode of created files by the process.
+    static ref UMASK: u32 = proc_umask().unwrap_or_else(|| {
+        // SAFETY: umask can only be read by setting it, it is restored at
+        // once, see `read_umask` for files created meanwhile
+        let umask = unsafe {
+            let umask = libc::umask(0o022);
+
+            libc::umask(umask);
+
+            umask
+        };
+
+        umask as u32
+    });
+}
+
+/// Read the umask of the process once and for all, before any file is
+/// created concurrently.
+///
+/// Where `/proc` doesn't report it, the umask can only be read by changing
+/// it for a moment, during which other threads would create files with the
+/// wrong permissions.
+pub fn read_umask() {
+    #[cfg(not(target_os = "windows"))]
+    lazy_static::initialize(&UMASK);
+}
+
+/// The umask as reported by `/proc/self/status`, on Linux since 4.7.
+#[cfg(not(target_os = "windows"))]
+fn proc_umask() -> Option<u32> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+
+    let umask = status
+        .lines()
+        .find_map(|line| line.strip_prefix("Umask:"))?;
+
+    u32::from_str_radix(umask.trim(), 8).ok()
+}
+
+/// Directory of a [`Plan::tree`], entries are sorted by name.
+#[derive(Debug, Default)]
+struct TreeNode {
+    children: BTreeMap<String, (bool, TreeNode)>,
+}
+
+impl TreeNode {
+    fn insert(&mut self, path: &Path, is_directory: bool) {
+        let components = path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+
+        let mut node = self;
+
+        for (index, component) in components.iter().enumerate() {
+            let last = index + 1 == components.len();
+
+            let entry = node
+                .children
+                .entry(component.clone())
+                .or_insert_with(|| (!last || is_directory, TreeNode::default()));
+
+            // a file may be listed before the directory holding it
+            entry.0 |= !last || is_directory;
+
+            node = &mut entry.1;
+        }
+    }
+
+    fn draw(&self, prefix: &str, drawing: &mut String) {
+        for (index, (name, (is_directory, child))) in self.children.iter().enumerate() {
+            let last = index + 1 == self.children.len();
+
+            let (branch, indent) = if last {
+                ("\u{2514}\u{2500}\u{2500} ", "    ")
+            } else {
+                ("\u{251c}\u{2500}\u{2500} ", "\u{2502}   ")
+            };
+
+            drawing.push_str(prefix);
+            drawing.push_str(branch);
+            drawing.push_str(name);
+
+            if *is_directory {
+                drawing.push('/');
+            }
+
+            drawing.push('\n');
+
+            child.draw(&format!("{}{}", prefix, indent), drawing);
+        }
+    }
+}
+
+/// Write a single planned file in `output` with `mode`, less the umask.
+///
+/// Private files fail to be written rather than be left readable by others.
+fn write_file(output: &Path, planned_file: &PlannedFile, mode: Option<u32>) -> Result<(), String> {
+    let path = output.join(&planned_file.path);
+
+    let mut options = OpenOptions::new();
+
+    options.write(true).create(true).truncate(true);
+
+    // new files are created with their mode, so that secrets are never
+    // readable, even for a moment
+    #[cfg(not(target_os = "windows"))]
+    if let Some(mode) = mode {
+        options.mode(mode);
+    }
+
+    let file = options.open(&path).map_err(|error| {
+        format!(
+            "Failed to create file: {:?}: {}, check that the directory is included in your {}",
+            path, error, TEMPLATE_FILENAME
+        )
+    })?;
+
+    // existing files keep theirs, it is changed through the open file rather
+    // than by looking its path up again, and before writing
+    #[cfg(not(target_os = "windows"))]
+    if let Some(mode) = mode {
+        match file.set_permissions(fs::Permissions::from_mode(mode & !*UMASK)) {
+            Err(error) if mode == PRIVATE_MODE => {
+                return Err(format!("Failed to make {:?} private: {}", path, error))
+            }
+            _ => {}
+        }
+    }
+
+    let mut writer = BufWriter::new(file);
+
+    // assets are copied file to file, which lets the system copy them in the
+    // kernel where it can, without going through pi's memory
+    if let Some(ref source) = planned_file.source {
+        let copied = File::open(source).and_then(|mut source_file| {
+            let file = writer.get_mut();
+
+            io::copy(&mut source_file, file)?;
+
+            // assets without a mode of their own keep the one of their source
+            if mode.is_none() {
+                file.set_permissions(source_file.metadata()?.permissions())?;
+            }
+
+            Ok(())
+        });
+
+        return copied.map_err(|error| {
+            format!(
+                "Failed to copy {:?} to {:?}: {}, check that the directory is included in your {}",
+                source, path, error, TEMPLATE_FILENAME
+            )
+        });
+    }
+
+    writer
+        .write_all(&planned_file.contents)
+        .and_then(|()| writer.flush())
+        .map_err(|error| format!("Failed to write file {:?}: {}", path, error))
+}
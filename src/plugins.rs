@@ -0,0 +1,60 @@
+//! Support for external subcommands.
+//!
+//! Like cargo and git, an unknown subcommand `pi foo` resolves to an
+//! executable named `pi-foo` on the `PATH`, receiving the remaining
+//! arguments and some context through environment variables.
+
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::Command;
+
+use tracing::error;
+
+use crate::constants::{GLOBAL_CONFIG_FILENAME, GLOBAL_TEMPLATE_DIRECTORY};
+
+/// Prefix of the executables resolved as external subcommands.
+pub const PLUGIN_PREFIX: &str = "pi-";
+
+/// Run the external subcommand described by `args` (the subcommand name
+/// followed by its arguments) and return its exit code.
+pub fn run_external(args: &[String], home: &Path) -> i32 {
+    let (subcommand, arguments) = match args.split_first() {
+        Some(split) => split,
+        None => {
+            error!("No subcommand provided");
+
+            return 1;
+        }
+    };
+
+    let program = format!("{}{}", PLUGIN_PREFIX, subcommand);
+
+    let mut command = Command::new(&program);
+
+    command
+        .args(arguments)
+        .env("PI_VERSION", env!("CARGO_PKG_VERSION"))
+        .env("PI_CONFIG_PATH", home.join(GLOBAL_CONFIG_FILENAME))
+        .env("PI_TEMPLATES_DIR", home.join(GLOBAL_TEMPLATE_DIRECTORY));
+
+    if let Ok(current_exe) = std::env::current_exe() {
+        command.env("PI_BIN", current_exe);
+    }
+
+    match command.status() {
+        Ok(status) => status.code().unwrap_or(1),
+        Err(error) if error.kind() == ErrorKind::NotFound => {
+            error!(
+                "No such subcommand '{}', and no '{}' executable found in your PATH",
+                subcommand, program
+            );
+
+            1
+        }
+        Err(error) => {
+            error!("Failed to run '{}': {}", program, error);
+
+            1
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! Questions asked to the user on the terminal.
+//!
+//! Prompts can be disabled for the whole process with [`set_interactive`],
+//! for scripts and CI pipelines, in which case every question gets its
+//! default answer.
+
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use dialoguer::{FuzzySelect, MultiSelect};
+
+/// Environment variable set by most CI services.
+const CI_VARIABLE: &str = "CI";
+
+static INTERACTIVE: AtomicBool = AtomicBool::new(true);
+
+/// Enable or disable prompts.
+pub fn set_interactive(interactive: bool) {
+    INTERACTIVE.store(interactive, Ordering::Relaxed);
+}
+
+/// Returns `false` when prompts are disabled.
+pub fn is_interactive() -> bool {
+    INTERACTIVE.load(Ordering::Relaxed)
+}
+
+/// Returns `true` when running in a CI pipeline.
+pub fn ci_detected() -> bool {
+    std::env::var(CI_VARIABLE)
+        .map(|value| !matches!(value.to_lowercase().as_str(), "" | "0" | "false"))
+        .unwrap_or(false)
+}
+
+/// Ask a yes/no `question`, returning `default` when the answer is empty or
+/// can't be read, or prompts are disabled.
+pub fn confirm(question: &str, default: bool) -> bool {
+    if !is_interactive() {
+        return default;
+    }
+
+    let choices = if default { "[Y/n]" } else { "[y/N]" };
+
+    print!("{} {} ", question, choices);
+
+    let _ = io::stdout().flush();
+
+    let mut answer = String::new();
+
+    match io::stdin().lock().read_line(&mut answer) {
+        Ok(read) if read > 0 => match answer.trim().to_lowercase().as_str() {
+            "y" | "yes" => true,
+            "n" | "no" => false,
+            _ => default,
+        },
+        _ => default,
+    }
+}
+
+/// Ask for a value, returning `None` when no answer can be read or prompts
+/// are disabled.
+pub fn input(question: &str) -> Option<String> {
+    if !is_interactive() {
+        return None;
+    }
+
+    print!("{} ", question);
+
+    let _ = io::stdout().flush();
+
+    let mut answer = String::new();
+
+    match io::stdin().lock().read_line(&mut answer) {
+        Ok(read) if read > 0 => Some(answer.trim_end_matches(['\r', '\n']).to_string()),
+        _ => None,
+    }
+}
+
+/// Ask `question` until one of the `choices`, given as a key and its
+/// meaning, is picked by its key. Returns `None` when no answer can be read
+/// or prompts are disabled.
+pub fn choose(question: &str, choices: &[(char, &str)]) -> Option<char> {
+    if !is_interactive() {
+        return None;
+    }
+
+    let keys = choices.iter().map(|(key, _)| *key).collect::<String>();
+
+    let help = choices
+        .iter()
+        .map(|(key, meaning)| format!("{} - {}", key, meaning))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    loop {
+        print!("{} [{}] ", question, keys);
+
+        let _ = io::stdout().flush();
+
+        let mut answer = String::new();
+
+        match io::stdin().lock().read_line(&mut answer) {
+            Ok(read) if read > 0 => {}
+            _ => return None,
+        }
+
+        let answer = answer.trim().to_lowercase();
+
+        match choices
+            .iter()
+            .find(|(key, _)| answer.chars().eq(std::iter::once(*key)))
+        {
+            Some((key, _)) => return Some(*key),
+            None => println!("{}", help),
+        }
+    }
+}
+
+/// Let the user pick one of `items` by typing part of it, returning its
+/// index. Returns `None` when nothing is picked, the terminal doesn't allow
+/// it, or prompts are disabled.
+pub fn fuzzy_select<T: ToString>(question: &str, items: &[T]) -> Option<usize> {
+    if !is_interactive() || items.is_empty() {
+        return None;
+    }
+
+    FuzzySelect::new()
+        .with_prompt(question)
+        .items(items)
+        .default(0)
+        .interact_opt()
+        .ok()
+        .flatten()
+}
+
+/// Let the user toggle any of `items`, initially toggled as in `defaults`,
+/// returning the indices of the toggled ones. Returns `None` when the
+/// terminal doesn't allow it or prompts are disabled.
+pub fn multi_select<T: ToString>(
+    question: &str,
+    items: &[T],
+    defaults: &[bool],
+) -> Option<Vec<usize>> {
+    if !is_interactive() || items.is_empty() {
+        return None;
+    }
+
+    MultiSelect::new()
+        .with_prompt(question)
+        .items(items)
+        .defaults(defaults)
+        .interact_opt()
+        .ok()
+        .flatten()
+}
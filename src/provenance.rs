@@ -0,0 +1,89 @@
+//! Provenance of a generated project, `.pi/source.toml`.
+//!
+//! A short record of which template a project came from, at which commit,
+//! when and with which version of pi, for audits and later updates. Unlike
+//! the [`Manifest`](crate::manifest::Manifest), it says nothing about the
+//! generated files.
+//!
+//! Templates installed by `pi add` record theirs as well, for `pi update` to
+//! fetch them again from the same source.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::SecondsFormat;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{MANIFEST_DIRECTORY, PROVENANCE_FILENAME};
+use crate::manifest::TemplateSource;
+use crate::util::now;
+
+/// Where and when a project was generated from.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Name of the template, as set in its `[template]` table or else the
+    /// name of its directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// Commit the template was fetched at, for git templates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    /// Version of pi that generated the project
+    pub pi_version: String,
+    /// When the project was generated, in RFC 3339 format
+    pub generated_at: String,
+    pub source: TemplateSource,
+}
+
+impl Provenance {
+    /// Provenance of a project generated now from the template `template`,
+    /// fetched from `source` at `commit`.
+    pub fn new(template: Option<String>, source: TemplateSource, commit: Option<String>) -> Self {
+        Self {
+            template,
+            commit,
+            pi_version: env!("CARGO_PKG_VERSION").to_string(),
+            generated_at: now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            source,
+        }
+    }
+
+    /// Path of the provenance record inside the project located in
+    /// `project_dir`.
+    pub fn path<P: AsRef<Path>>(project_dir: P) -> PathBuf {
+        project_dir
+            .as_ref()
+            .join(MANIFEST_DIRECTORY)
+            .join(PROVENANCE_FILENAME)
+    }
+
+    /// Read the provenance of the project located in `project_dir`.
+    pub fn from_project<P: AsRef<Path>>(
+        project_dir: P,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = Self::path(&project_dir);
+
+        let content = fs::read_to_string(&path).map_err(|error| {
+            format!(
+                "Couldn't read the provenance of the project at {}: {}",
+                path.to_string_lossy(),
+                error
+            )
+        })?;
+
+        Ok(toml::from_str(&content)?)
+    }
+
+    /// Write the provenance in the project located in `project_dir`.
+    pub fn write<P: AsRef<Path>>(&self, project_dir: P) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::path(&project_dir);
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, toml::to_string(self)?)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+//! `pi publish`, adding or updating a template's entry in a templates
+//! repository.
+
+use std::fs;
+use std::path::Path;
+
+use serde_json::{json, Value};
+use url::Url;
+
+use crate::fixtures::{run_fixtures, FIXTURES_DIRECTORY};
+use crate::http::HttpFetcher;
+use crate::types::{Config, Project, TemplateRepository};
+use crate::util::check_template;
+
+/// Check that the template can be rendered, and that its fixtures pass if it
+/// has any, then return its entry for a templates repository.
+pub fn prepare_entry<H: AsRef<Path>>(
+    home: H,
+    project: &Project,
+    config: &Config,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    let metadata = project.template.as_ref().ok_or(
+        "The template has no [template] table in its template.toml, \
+         describe it there before publishing it",
+    )?;
+
+    let name = project
+        .template_name()
+        .ok_or("Couldn't infer the template's name, set template.name")?;
+
+    let description = metadata
+        .description
+        .clone()
+        .ok_or("The template has no description, set template.description")?;
+
+    let repository = match &metadata.repository {
+        Some(repository) => repository.clone(),
+        None => origin_url(&project.path)
+            .ok_or("Couldn't find the template's git remote, set template.repository")?,
+    };
+
+    check_template(config, project)?;
+
+    if project.path.join(FIXTURES_DIRECTORY).is_dir() && !run_fixtures(home, &project.path) {
+        return Err("The template's fixtures failed".into());
+    }
+
+    let mut entry = json!({
+        "name": name,
+        "repository": repository,
+        "description": description,
+    });
+
+    if !metadata.tags.is_empty() {
+        entry["tags"] = json!(metadata.tags);
+    }
+
+    if let Some(ref language) = metadata.language {
+        entry["language"] = json!(language);
+    }
+
+    Ok(entry)
+}
+
+/// Publish `entry` to `repository`.
+///
+/// Local repositories are updated in place, keeping their format and any
+/// other field of an existing entry, such as its released versions. The entry
+/// is sent with a POST request to remote ones, authenticated with `token`.
+pub async fn publish(
+    entry: Value,
+    repository: &TemplateRepository,
+    token: Option<&str>,
+    fetcher: &dyn HttpFetcher,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match repository {
+        TemplateRepository::Path(path) => {
+            let mut index = match fs::read(path) {
+                Ok(bytes) => serde_json::from_slice(&bytes)?,
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => json!([]),
+                Err(error) => return Err(error.into()),
+            };
+
+            let entries = match &mut index {
+                Value::Array(entries) => entries,
+                Value::Object(index) => index
+                    .entry("templates")
+                    .or_insert_with(|| json!([]))
+                    .as_array_mut()
+                    .ok_or("The templates repository's templates isn't a list")?,
+                _ => return Err("The templates repository's format is invalid".into()),
+            };
+
+            upsert(entries, entry);
+
+            fs::write(path, serde_json::to_string_pretty(&index)? + "\n")?;
+        }
+        TemplateRepository::Url(url) => {
+            fetcher
+                .post_json(url, serde_json::to_vec(&entry)?, token)
+                .await
+                .map_err(|error| format!("Couldn't publish to {}: {}", url, error))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Replace the fields of the entry named like `entry` in `entries`, or add
+/// it if there is none.
+fn upsert(entries: &mut Vec<Value>, entry: Value) {
+    let position = entries
+        .iter()
+        .position(|existing| existing.get("name") == entry.get("name"));
+
+    match (position, entry) {
+        (Some(position), Value::Object(fields)) => {
+            if let Some(existing) = entries[position].as_object_mut() {
+                existing.extend(fields);
+            }
+        }
+        (_, entry) => entries.push(entry),
+    }
+}
+
+/// Url of the `origin` remote of the git repository containing `path`.
+fn origin_url(path: &Path) -> Option<Url> {
+    let repository = git2::Repository::discover(path).ok()?;
+
+    let remote = repository.find_remote("origin").ok()?;
+
+    let url = remote.url()?;
+
+    // scp-like urls, as used for ssh remotes
+    match url.strip_prefix("git@") {
+        Some(rest) => {
+            let (host, path) = rest.split_once(':')?;
+
+            format!("https://{}/{}", host, path.trim_end_matches(".git"))
+                .parse()
+                .ok()
+        }
+        None => url.trim_end_matches(".git").parse().ok(),
+    }
+}
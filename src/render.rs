@@ -1,327 +1,573 @@
 //! Module containing functions for rendering templates
 
+use std::collections::HashMap;
+use std::fmt;
 use std::fs;
-use std::fs::File;
-use std::io::prelude::*;
 use std::io::Cursor;
-#[cfg(not(target_os = "windows"))]
-use std::os::unix::fs::PermissionsExt;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 
-use os_str_bytes::OsStrBytes;
+use lazy_static::lazy_static;
 use rustache::*;
-use tracing::error;
 
-use crate::constants::TEMPLATE_FILENAME;
+use crate::diagnostics::template_diagnostic;
+use crate::plan::{FileKind, PlannedFile};
+use crate::types::Project;
+
+/// Why a rendered path can't be created in the project.
+#[derive(Debug, PartialEq, Eq)]
+pub enum InvalidPath {
+    /// The path, or what it rendered to, isn't valid UTF-8
+    NotUtf8,
+    /// The path failed to render
+    Template(String),
+    /// The path rendered empty
+    Empty,
+    /// The rendered path is absolute, or goes up with `..`
+    OutsideProject(String),
+}
 
-/// Trait allowing us to create dirs/templates/files.
-trait Create {
-    fn create_dirs<P: AsRef<Path>>(&self, name: P);
+impl fmt::Display for InvalidPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidPath::NotUtf8 => write!(f, "it isn't valid UTF-8"),
+            InvalidPath::Template(error) => write!(f, "{}", error),
+            InvalidPath::Empty => write!(f, "it renders to an empty path"),
+            InvalidPath::OutsideProject(rendered) => {
+                write!(f, "{:?} points outside of the project", rendered)
+            }
+        }
+    }
 }
 
-/// Create directories given a `Vec<AsRes<Path>>` of directory names
-impl<T: AsRef<Path>> Create for Vec<T> {
-    fn create_dirs<P: AsRef<Path>>(&self, name: P) {
-        self.iter().for_each(|dir| {
-            let subdir = name.as_ref().join(dir);
+/// Substitute the keys of `hash` in a path, relative to the project.
+///
+/// Both `/` and the platform's separators separate the components of the
+/// rendered path, which is rebuilt with the platform's.
+pub fn try_render_path<P: AsRef<Path>>(
+    path: P,
+    hash: &HashBuilder,
+) -> std::result::Result<PathBuf, InvalidPath> {
+    let template = path.as_ref().to_str().ok_or(InvalidPath::NotUtf8)?;
+
+    let mut output = Vec::with_capacity(template.len());
+
+    hash.render(template, &mut output)
+        .map_err(|error| InvalidPath::Template(error.to_string()))?;
+
+    let rendered = String::from_utf8(output).map_err(|_| InvalidPath::NotUtf8)?;
 
-            let _ = fs::create_dir(subdir);
-        });
+    let mut relative = PathBuf::new();
+
+    for component in Path::new(&rendered).components() {
+        match component {
+            Component::Normal(name) => relative.push(name),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(InvalidPath::OutsideProject(rendered));
+            }
+        }
+    }
+
+    if relative.as_os_str().is_empty() {
+        return Err(InvalidPath::Empty);
     }
+
+    Ok(relative)
 }
 
-/// Render a list of directories, substituting in templates
-pub fn render_dirs<D: AsRef<Path>, N: AsRef<Path>>(
-    directories: Vec<D>,
+/// Substitute the keys of `hash` in a path, see [`try_render_path`].
+///
+/// Fails with an error naming `path` if it doesn't render to a path in the
+/// project.
+pub fn render_path<P: AsRef<Path>>(
+    path: P,
     hash: &HashBuilder,
-    name: N,
-) {
-    // substitute into directory names using templates
-    let directories: Vec<String> = directories
-        .into_iter()
-        .map(|file| {
-            let mut output = Cursor::new(Vec::new());
+) -> std::result::Result<PathBuf, String> {
+    try_render_path(&path, hash).map_err(|error| {
+        format!(
+            "Invalid path {} in the template: {}",
+            path.as_ref().to_string_lossy(),
+            error
+        )
+    })
+}
 
-            hash.render(&file.as_ref().to_string_lossy(), &mut output)
-                .unwrap();
+/// Render a list of directories, substituting in templates
+pub fn render_dirs<D: AsRef<Path>>(
+    directories: &[D],
+    hash: &HashBuilder,
+) -> std::result::Result<Vec<PathBuf>, String> {
+    directories
+        .iter()
+        .map(|directory| render_path(directory, hash))
+        .collect()
+}
 
-            String::from_utf8(output.into_inner()).unwrap()
+/// Render the names of a list of blank files, returning the files to create
+pub fn render_files<D: AsRef<Path>>(
+    files: &[D],
+    hash: &HashBuilder,
+) -> std::result::Result<Vec<PlannedFile>, String> {
+    files
+        .iter()
+        .map(|file| {
+            Ok(PlannedFile {
+                path: render_path(file, hash)?,
+                contents: Vec::new(),
+                source: None,
+                kind: FileKind::Blank,
+            })
         })
-        .collect();
-
-    directories.create_dirs(name);
+        .collect()
 }
 
-/// Create all the files, and return a list of files that have been created
-/// suitable for insertion
-/// into a `HashBuilder`
-pub fn render_files<'a, D: AsRef<Path>, N: AsRef<Path>>(
-    files: Vec<D>,
+/// List the assets located in `project_path`, only substituting in their
+/// names: their content is copied from the template when written.
+pub fn render_assets<P: AsRef<Path>, A: AsRef<Path>>(
+    project_path: P,
     hash: &HashBuilder,
-    name: N,
-) -> VecBuilder<'a> {
-    // render filenames
-    let substitutions = files
-        .into_iter()
+    assets: &[A],
+) -> std::result::Result<Vec<PlannedFile>, String> {
+    assets
+        .iter()
         .map(|file| {
-            let mut output = Cursor::new(Vec::new());
+            let source = project_path.as_ref().join(file);
 
-            hash.render(&file.as_ref().to_string_lossy(), &mut output)
-                .unwrap();
+            if !source.is_file() {
+                return Err(format!("Failed to read asset {}", source.to_string_lossy()));
+            }
 
-            Path::from_raw_bytes(output.into_inner())
-                .unwrap()
-                .as_ref()
-                .to_path_buf()
+            Ok(PlannedFile {
+                path: render_path(file, hash)?,
+                contents: Vec::new(),
+                source: Some(source),
+                kind: FileKind::Asset,
+            })
         })
-        .collect::<Vec<PathBuf>>();
+        .collect()
+}
 
-    // create files
-    substitutions.iter().for_each(|path| {
-        File::create(name.as_ref().join(path)).unwrap();
-    });
+/// Returns `true` when a key holding `data` renders a mustache section: it
+/// is set, and neither false, empty nor the string "false".
+pub fn is_truthy(data: Option<&Data>) -> bool {
+    match data {
+        None => false,
+        Some(Data::Bool(value)) => *value,
+        Some(Data::String(value)) => !value.is_empty() && value != "false",
+        Some(Data::Vector(values)) => !values.is_empty(),
+        Some(_) => true,
+    }
+}
 
-    // collect filenames
-    let data: Vec<Data> = substitutions
-        .into_iter()
-        .map(|substitution| Data::from(substitution.to_string_lossy().into_owned()))
+/// Returns the list of rendered file names suitable for insertion into a
+/// `HashBuilder`
+pub fn file_names<'a>(files: &[PlannedFile]) -> VecBuilder<'a> {
+    let data: Vec<Data> = files
+        .iter()
+        .map(|file| Data::from(file.path.to_string_lossy().into_owned()))
         .collect();
 
-    // return a `VecBuilder` object.
     VecBuilder { data }
 }
 
-/// render a `<Vec<String>>` of templates, doing nothing if it's empty.
-#[cfg(target_os = "windows")]
-pub fn render_templates<P: AsRef<Path>, T: AsRef<Path>, N: AsRef<Path>>(
+/// Render a list of templates located in `project_path`, substituting in
+/// both their names and their content.
+///
+/// Scripts are rendered the same way, only their kind differs so that they
+/// are made executable when written. On Windows, their variant for Windows
+/// is rendered instead if the template has one, see [`windows_variant`].
+pub fn render_templates<P: AsRef<Path>, T: AsRef<Path>>(
     project_path: P,
-    name: N,
     hash: &HashBuilder,
-    templates: Option<Vec<T>>,
-    executable: bool,
-) {
-    if let Some(original_templates) = templates {
-        // create Vec<T> of paths to templates
-        let templates = original_templates
-            .iter()
-            .map(|file| {
-                let mut path = project_path.as_ref().join(file);
-
-                if executable {
-                    path = path.join(".bat");
+    templates: &[T],
+    kind: FileKind,
+) -> std::result::Result<Vec<PlannedFile>, String> {
+    templates
+        .iter()
+        .map(|file| {
+            let file = match kind {
+                FileKind::Script if cfg!(target_os = "windows") => {
+                    windows_variant(project_path.as_ref(), file.as_ref())
                 }
-
-                path
+                _ => file.as_ref().to_path_buf(),
+            };
+
+            let path = project_path.as_ref().join(&file);
+
+            let template = checked_template(&path)?;
+
+            // render straight into the file's contents, which are usually
+            // about the size of the template
+            let mut contents = Vec::with_capacity(template.source.len());
+
+            hash.render(&template.source, &mut contents)
+                .map_err(|error| {
+                    format!(
+                        "Failed to render template {}: {}",
+                        path.to_string_lossy(),
+                        error
+                    )
+                })?;
+
+            Ok(PlannedFile {
+                path: render_path(&file, hash)?,
+                contents,
+                source: None,
+                kind,
             })
-            .collect::<Vec<PathBuf>>();
+        })
+        .collect()
+}
 
-        // read all the template files
-        let template_files = templates
+/// Extensions of the scripts Windows runs, by order of preference.
+pub const WINDOWS_SCRIPT_EXTENSIONS: &[&str] = &["ps1", "cmd", "bat"];
+
+/// Returns the variant of the `script` of the template in `project_path` to
+/// use on Windows: the script with the first of [`WINDOWS_SCRIPT_EXTENSIONS`]
+/// the template has, replacing its own extension, else the script itself.
+///
+/// A template listing `setup.sh` can ship `setup.ps1` alongside it, which is
+/// generated instead on Windows.
+pub fn windows_variant(project_path: &Path, script: &Path) -> PathBuf {
+    let is_windows_script = script.extension().is_some_and(|extension| {
+        WINDOWS_SCRIPT_EXTENSIONS
             .iter()
-            .map(|path| {
-                let mut template_file = match File::open(&path) {
-                    Ok(template_file) => template_file,
-                    Err(_) => {
-                        error!("Failed to open file: {:?}", path);
+            .any(|windows| extension == *windows)
+    });
 
-                        std::process::exit(0x0f00);
-                    }
-                };
+    if is_windows_script {
+        return script.to_path_buf();
+    }
 
-                let mut template = String::new();
+    WINDOWS_SCRIPT_EXTENSIONS
+        .iter()
+        .map(|extension| script.with_extension(extension))
+        .find(|variant| project_path.join(variant).is_file())
+        .unwrap_or_else(|| script.to_path_buf())
+}
 
-                template_file
-                    .read_to_string(&mut template)
-                    // ok to panic because we already errored.
-                    .expect("File read failed");
+/// A template file read, checked and scanned for placeholders once, then
+/// reused by every rendering of it in the process, as when `pi serve`
+/// generates the same template again.
+///
+/// Rendering still parses `source` every time, rustache doesn't expose the
+/// templates it parses.
+#[derive(Debug)]
+pub struct CheckedTemplate {
+    pub source: String,
+    /// Variables used outside of any section, see [`placeholders`]
+    pub placeholders: Vec<String>,
+    /// Modification time and length of the file when it was read
+    stamp: (Option<SystemTime>, u64),
+}
 
-                template
-            })
-            .collect::<Vec<String>>();
+lazy_static! {
+    static ref CHECKED_TEMPLATES: Mutex<HashMap<PathBuf, Arc<CheckedTemplate>>> =
+        Mutex::new(HashMap::new());
+}
 
-        // create Vec<T> of paths to rendered templates
-        let templates_new = original_templates
-            .iter()
-            .map(|file| name.as_ref().join(file))
-            .collect::<Vec<PathBuf>>();
+/// Returns the template at `path`, reading and checking it unless it was
+/// already and the file is unchanged since.
+///
+/// Fails with a report if the file can't be read or isn't a valid template.
+pub fn checked_template(path: &Path) -> std::result::Result<Arc<CheckedTemplate>, String> {
+    let stamp = fs::metadata(path)
+        .map(|metadata| (metadata.modified().ok(), metadata.len()))
+        .unwrap_or_default();
 
-        // subtitute into template names
-        let templates_named = templates_new
-            .iter()
-            .map(|name| {
-                let mut output = Cursor::new(Vec::new());
+    let mut checked = CHECKED_TEMPLATES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
 
-                hash.render(&name.to_string_lossy(), &mut output).unwrap();
+    match checked.get(path) {
+        Some(template) if template.stamp == stamp => Ok(template.clone()),
+        _ => {
+            let template = Arc::new(read_template(path, stamp)?);
 
-                Path::from_raw_bytes(output.into_inner())
-                    .unwrap()
-                    .as_ref()
-                    .to_path_buf()
-            })
-            .collect::<Vec<PathBuf>>();
+            checked.insert(path.to_path_buf(), template.clone());
 
-        // render all the template files
-        let substitutions = template_files
-            .iter()
-            .map(|file| {
-                let mut output = Cursor::new(Vec::new());
+            Ok(template)
+        }
+    }
+}
 
-                hash.render(file, &mut output).unwrap();
+fn read_template(
+    path: &Path,
+    stamp: (Option<SystemTime>, u64),
+) -> std::result::Result<CheckedTemplate, String> {
+    let bytes = fs::read(path).map_err(|error| {
+        format!(
+            "Failed to read template {}: {}",
+            path.to_string_lossy(),
+            error
+        )
+    })?;
+
+    let source = match String::from_utf8(bytes) {
+        Ok(source) => source,
+        Err(error) => {
+            let offset = error.utf8_error().valid_up_to();
+
+            let source = String::from_utf8_lossy(error.as_bytes());
+
+            return Err(template_failure(
+                path,
+                &source,
+                offset,
+                "template is not valid UTF-8",
+            ));
+        }
+    };
+
+    if let Err(error) = check_syntax(&source) {
+        return Err(template_failure(
+            path,
+            &source,
+            error.offset,
+            &error.message,
+        ));
+    }
 
-                output.into_inner()
-                // Path::from_raw_bytes(output.into_inner()).unwrap().as_ref()
-            })
-            .collect::<Vec<Vec<u8>>>();
+    Ok(CheckedTemplate {
+        placeholders: placeholders(&source),
+        source,
+        stamp,
+    })
+}
 
-        // write the rendered templates
-        let files_to_write = templates_named.iter().zip(substitutions.iter());
+/// Report of the error `message` at `offset` of the template at `path`.
+fn template_failure(path: &Path, source: &str, offset: usize, message: &str) -> String {
+    format!(
+        "Failed to render template {}\n{}",
+        path.to_string_lossy(),
+        template_diagnostic(path, source, offset, message).trim_end()
+    )
+}
 
-        files_to_write
-            .into_iter()
-            .for_each(|(path, contents)| match File::create(&path) {
-                Ok(mut file) => {
-                    let _ = file.write(contents);
-                }
-                Err(_error) => {
-                    error!("Failed to create file: {:?}, check that the directory is included in your {}", path, TEMPLATE_FILENAME);
+/// A mistake in the mustache syntax of a template, see [`check_syntax`].
+#[derive(Debug)]
+pub struct SyntaxError {
+    /// Byte offset of the faulty tag in the template
+    pub offset: usize,
+    pub message: String,
+}
 
-                    std::process::exit(0x0f01);
+/// Check that every tag of `template` is closed and that its sections are
+/// properly nested.
+///
+/// Templates changing their delimiters are only checked up to the change.
+pub fn check_syntax(template: &str) -> std::result::Result<(), SyntaxError> {
+    // name and offset of the opened sections
+    let mut sections: Vec<(&str, usize)> = Vec::new();
+
+    let mut offset = 0;
+
+    while let Some(start) = template[offset..].find("{{") {
+        let start = offset + start;
+
+        let rest = &template[start + 2..];
+
+        let (tag, end) = match rest.strip_prefix('{') {
+            Some(triple) => (triple.find("}}}").map(|end| &triple[..end]), 3 + 3),
+            None => (rest.find("}}").map(|end| &rest[..end]), 2 + 2),
+        };
+
+        let tag = match tag {
+            Some(tag) => tag,
+            None => {
+                return Err(SyntaxError {
+                    offset: start,
+                    message: "unclosed tag, expected `}}`".to_string(),
+                })
+            }
+        };
+
+        offset = start + tag.len() + end;
+
+        let tag = tag.trim();
+
+        let name = |sigil: &str| tag[sigil.len()..].trim();
+
+        match tag.chars().next() {
+            None => {
+                return Err(SyntaxError {
+                    offset: start,
+                    message: "empty tag".to_string(),
+                })
+            }
+            Some('=') => return Ok(()),
+            Some('#') | Some('^') => sections.push((name("#"), start)),
+            Some('/') => match sections.pop() {
+                Some((opened, _)) if opened == name("/") => {}
+                Some((opened, _)) => {
+                    return Err(SyntaxError {
+                        offset: start,
+                        message: format!(
+                            "`{{{{/{}}}}}` doesn't match the open section `{}`",
+                            name("/"),
+                            opened
+                        ),
+                    })
                 }
-            });
+                None => {
+                    return Err(SyntaxError {
+                        offset: start,
+                        message: format!(
+                            "`{{{{/{}}}}}` closes a section that was never opened",
+                            name("/")
+                        ),
+                    })
+                }
+            },
+            Some(_) => {}
+        }
+    }
+
+    match sections.pop() {
+        Some((name, offset)) => Err(SyntaxError {
+            offset,
+            message: format!("section `{}` is never closed", name),
+        }),
+        None => Ok(()),
     }
 }
 
-/// render a `<Vec<String>>` of templates, doing nothing if it's empty.
-#[cfg(not(target_os = "windows"))]
-pub fn render_templates<P: AsRef<Path>, T: AsRef<Path>, N: AsRef<Path>>(
-    project_path: P,
-    name: N,
+/// Render a static string
+pub fn render_file(
+    static_template: &str,
     hash: &HashBuilder,
-    templates: Option<Vec<T>>,
-    executable: bool,
-) {
-    if let Some(original_templates) = templates {
-        // create Vec<T> of paths to templates
-        let templates = original_templates
-            .iter()
-            .map(|file| project_path.as_ref().join(file))
-            .collect::<Vec<PathBuf>>();
-
-        // read all the template files
-        let template_files = templates
-            .iter()
-            .map(|path| {
-                let mut template_file = match File::open(&path) {
-                    Ok(template_file) => template_file,
-                    Err(_) => {
-                        error!("Failed to open file: {:?}", path);
-
-                        std::process::exit(0x0f00);
-                    }
-                };
-
-                let mut template = String::new();
-
-                template_file
-                    .read_to_string(&mut template)
-                    // ok to panic because we already errored.
-                    .expect("File read failed");
+) -> std::result::Result<Vec<u8>, String> {
+    let mut output = Cursor::new(Vec::new());
 
-                template
-            })
-            .collect::<Vec<String>>();
+    hash.render(static_template, &mut output)
+        .map_err(|error| error.to_string())?;
 
-        // create Vec<T> of paths to rendered templates
-        let templates_new = original_templates
-            .iter()
-            .map(|file| name.as_ref().join(file))
-            .collect::<Vec<PathBuf>>();
+    Ok(output.into_inner())
+}
 
-        // subtitute into template names
-        let templates_named = templates_new
-            .iter()
-            .map(|name| {
-                let mut output = Cursor::new(Vec::new());
+/// Returns the variables used outside of any section in `template` that have
+/// no value in `hash`.
+///
+/// Sections are not checked, as a missing section key is a legitimate way to
+/// skip it, and their content may refer to the fields of the current item.
+pub fn unresolved_placeholders(template: &str, hash: &HashBuilder) -> Vec<String> {
+    unresolved_in(placeholders(template), hash)
+}
 
-                hash.render(&name.to_string_lossy(), &mut output).unwrap();
+/// Returns the `placeholders` without a value in `hash`.
+fn unresolved_in(placeholders: Vec<String>, hash: &HashBuilder) -> Vec<String> {
+    placeholders
+        .into_iter()
+        .filter(|name| {
+            let root = name.split('.').next().unwrap_or_default();
 
-                Path::from_raw_bytes(output.into_inner())
-                    .unwrap()
-                    .as_ref()
-                    .to_path_buf()
-            })
-            .collect::<Vec<PathBuf>>();
+            !hash.data.contains_key(root)
+        })
+        .collect()
+}
 
-        // render all the template files
-        let substitutions = template_files
-            .iter()
-            .map(|file| {
-                let mut output = Cursor::new(Vec::new());
+/// Returns the variables used outside of any section in `template`, once
+/// each.
+pub fn placeholders(template: &str) -> Vec<String> {
+    let mut placeholders = Vec::new();
 
-                hash.render(file, &mut output).unwrap();
+    let mut depth = 0usize;
 
-                output.into_inner()
-                // Path::from_raw_bytes(output.into_inner()).unwrap().as_ref()
-            })
-            .collect::<Vec<Vec<u8>>>();
+    let mut rest = template;
 
-        // write the rendered templates
-        let files_to_write = templates_named.iter().zip(substitutions.iter());
+    while let Some(start) = rest.find("{{") {
+        rest = &rest[start + 2..];
 
-        files_to_write
-            .into_iter()
-            .for_each(|(path, contents)| match File::create(&path) {
-                Ok(mut file) => {
-                    let _ = file.write(contents);
+        let (tag, end) = match rest.strip_prefix('{') {
+            Some(triple) => match triple.find("}}}") {
+                Some(end) => (&triple[..end], end + 4),
+                None => break,
+            },
+            None => match rest.find("}}") {
+                Some(end) => (&rest[..end], end + 2),
+                None => break,
+            },
+        };
 
-                    if executable {
-                        let mut permissions = fs::metadata(path)
-                            .expect("failed to read file metadata")
-                            .permissions();
+        rest = &rest[end..];
 
-                        permissions.set_mode(0o755);
+        let tag = tag.trim();
 
-                        let _ = fs::set_permissions(path, permissions);
-                    };
-                }
-                Err(_error) => {
-                    error!("Failed to create file: {:?}, check that the directory is included in your {}", path, TEMPLATE_FILENAME);
+        match tag.chars().next() {
+            Some('#') | Some('^') => depth += 1,
+            Some('/') => depth = depth.saturating_sub(1),
+            Some('!') | Some('>') | Some('=') | None => {}
+            Some(sigil) => {
+                let name = if sigil == '&' { tag[1..].trim() } else { tag };
 
-                    std::process::exit(0x0f01);
+                if depth == 0 && name != "." && !placeholders.iter().any(|key| key == name) {
+                    placeholders.push(name.to_string());
                 }
-            });
+            }
+        }
     }
-}
-
-/// Render a static string and write it to file
-pub fn render_file<N: AsRef<Path>>(
-    static_template: &str,
-    name: N,
-    filename: &str,
-    hash: &HashBuilder,
-) {
-    // render the template
-    let mut output = Cursor::new(Vec::new());
 
-    hash.render(static_template, &mut output).unwrap();
-
-    let contents = String::from_utf8(output.into_inner()).unwrap();
+    placeholders
+}
 
-    // write the file
-    let path = name.as_ref().join(filename);
+/// A placeholder without a value, see [`unresolved_keys`].
+#[derive(Debug)]
+pub struct UnresolvedKey {
+    pub key: String,
+    /// Template or file name the placeholder appears in
+    pub path: PathBuf,
+    /// Whether the placeholder is in the file name rather than its content
+    pub in_path: bool,
+}
 
-    // write the rendered template
-    match File::create(&path) {
-        Ok(mut file) => {
-            let _ = file.write(contents.as_bytes());
+/// Returns the placeholders without a value in `hash` used by the file names
+/// and templates of `project`.
+pub fn unresolved_keys(project: &Project, hash: &HashBuilder) -> Vec<UnresolvedKey> {
+    let mut unresolved = Vec::new();
+
+    let os_scripts = Some(project.files.current_os_scripts());
+
+    let names = [
+        &project.files.files,
+        &project.files.directories,
+        &project.files.templates,
+        &project.files.scripts,
+        &project.files.assets,
+        &os_scripts,
+    ];
+
+    for path in names.into_iter().flatten().flatten() {
+        for key in unresolved_placeholders(&path.to_string_lossy(), hash) {
+            unresolved.push(UnresolvedKey {
+                key,
+                path: path.clone(),
+                in_path: true,
+            });
         }
-        Err(_) => {
-            error!(
-                "Failed to create file: {:?}. Check that the directory is included in your {}",
-                path, TEMPLATE_FILENAME
-            );
+    }
 
-            std::process::exit(0x0f01);
+    let templates = [
+        &project.files.templates,
+        &project.files.scripts,
+        &os_scripts,
+    ];
+
+    for path in templates.into_iter().flatten().flatten() {
+        let path_in_template = project.path.join(path);
+
+        // missing and broken templates are reported when generating
+        if let Ok(template) = checked_template(&path_in_template) {
+            for key in unresolved_in(template.placeholders.clone(), hash) {
+                unresolved.push(UnresolvedKey {
+                    key,
+                    path: path.clone(),
+                    in_path: false,
+                });
+            }
         }
     }
+
+    unresolved
 }
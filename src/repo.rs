@@ -1,86 +1,38 @@
+use std::path::Path;
 use std::process::Command;
 
-use tracing::error;
-
-pub fn git_init(name: &str) {
-    if git2::Repository::init(name).is_err() {
-        error!("Git failed to initialize, is it in your path?");
-
-        std::process::exit(0x0f01);
-    }
+pub fn git_init(path: &Path) -> Result<(), String> {
+    git2::Repository::init(path)
+        .map(|_| ())
+        .map_err(|_| "Git failed to initialize, is it in your path?".to_string())
 }
 
 // FIXME: This function doesn't work on Windows
-pub fn pijul_init(name: &str) {
-    let mut cmd = "cd ".to_string();
-
-    cmd.push_str(name);
-    cmd.push_str("&&");
-    cmd.push_str("pijul init && pijul add **");
-
-    match Command::new("sh")
-        .arg("-c")
-        .arg(cmd)
-        .stdout(std::process::Stdio::null())
-        .spawn()
-    {
-        Ok(child) => {
-            child.wait_with_output().expect("failed to wait on child");
-        }
-        Err(_error) => {
-            error!("Pijul failed to initialize, is it in your path?");
-
-            std::process::exit(0x0f01);
-        }
-    }
+pub fn pijul_init(path: &Path) -> Result<(), String> {
+    run_init("pijul init && pijul add **", path)
+        .map_err(|_| "Pijul failed to initialize, is it in your path?".to_string())
 }
 
 // FIXME: This function doesn't work on Windows
-pub fn darcs_init(name: &str) {
-    let mut cmd = "cd ".to_string();
-
-    cmd.push_str(name);
-    cmd.push_str("&&");
-    cmd.push_str("darcs init && darcs add **");
-
-    match Command::new("sh")
-        .arg("-c")
-        .arg(cmd)
-        .stdout(std::process::Stdio::null())
-        .spawn()
-    {
-        Ok(child) => {
-            child.wait_with_output().expect("failed to wait on child");
-        }
-        Err(_error) => {
-            error!("Darcs failed to initialize, is it in your path?");
-
-            std::process::exit(0x0f01);
-        }
-    }
+pub fn darcs_init(path: &Path) -> Result<(), String> {
+    run_init("darcs init && darcs add **", path)
+        .map_err(|_| "Darcs failed to initialize, is it in your path?".to_string())
 }
 
 // FIXME: This function doesn't work on Windows
-pub fn hg_init(name: &str) {
-    let mut cmd = "cd ".to_string();
-
-    cmd.push_str(name);
-    cmd.push_str("&&");
-    cmd.push_str("hg init && hg add *");
+pub fn hg_init(path: &Path) -> Result<(), String> {
+    run_init("hg init && hg add *", path)
+        .map_err(|_| "Mercurial failed to initialize, is it in your path?".to_string())
+}
 
-    match Command::new("sh")
+/// Run the shell `command` initializing version control in `path`.
+fn run_init(command: &str, path: &Path) -> std::io::Result<()> {
+    Command::new("sh")
         .arg("-c")
-        .arg(cmd)
+        .arg(command)
+        .current_dir(path)
         .stdout(std::process::Stdio::null())
-        .spawn()
-    {
-        Ok(child) => {
-            child.wait_with_output().expect("failed to wait on child");
-        }
-        Err(_error) => {
-            error!("Mercurial failed to initialize, is it in your path?");
-
-            std::process::exit(0x0f01);
-        }
-    }
+        .spawn()?
+        .wait_with_output()
+        .map(|_| ())
 }
@@ -0,0 +1,116 @@
+//! Tools a generated project needs, checked once it is generated.
+//!
+//! Templates list them in `requires`, `pi` then points out the missing ones
+//! with a hint on how to install them, so that the first build of the project
+//! doesn't fail for a reason unrelated to the template.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Hints for the tools templates commonly require.
+const KNOWN_HINTS: &[(&str, &str)] = &[
+    ("cargo", "install rustup: https://rustup.rs"),
+    ("rustc", "install rustup: https://rustup.rs"),
+    ("node", "install Node.js: https://nodejs.org"),
+    ("npm", "install Node.js: https://nodejs.org"),
+    ("yarn", "install yarn: https://yarnpkg.com"),
+    (
+        "docker",
+        "install Docker: https://docs.docker.com/get-docker",
+    ),
+    (
+        "python3",
+        "install Python: https://www.python.org/downloads",
+    ),
+    ("pip", "install Python: https://www.python.org/downloads"),
+    ("go", "install Go: https://go.dev/doc/install"),
+    ("stack", "install stack: https://haskellstack.org"),
+    ("cabal", "install ghcup: https://www.haskell.org/ghcup"),
+    ("git", "install git: https://git-scm.com/downloads"),
+    ("make", "install make with your package manager"),
+];
+
+/// A tool the generated project needs, with how to install it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Requirement {
+    pub tool: String,
+    pub hint: Option<String>,
+}
+
+/// Requirements of the tools `requires`, hinted by `hints` or else by the
+/// known hints.
+pub fn requirements(requires: &[String], hints: &BTreeMap<String, String>) -> Vec<Requirement> {
+    requires
+        .iter()
+        .map(|tool| {
+            let hint = hints.get(tool).cloned().or_else(|| {
+                KNOWN_HINTS
+                    .iter()
+                    .find(|(known, _)| known == tool)
+                    .map(|(_, hint)| hint.to_string())
+            });
+
+            Requirement {
+                tool: tool.clone(),
+                hint,
+            }
+        })
+        .collect()
+}
+
+/// Whether `tool` is an executable in one of the `PATH` directories.
+pub fn is_installed(tool: &str) -> bool {
+    let paths = match env::var_os("PATH") {
+        Some(paths) => paths,
+        None => return false,
+    };
+
+    let file_name = format!("{}{}", tool, env::consts::EXE_SUFFIX);
+
+    env::split_paths(&paths).any(|directory| is_executable(&directory.join(&file_name)))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// The requirements whose tool isn't installed.
+pub fn missing(requirements: &[Requirement]) -> Vec<&Requirement> {
+    requirements
+        .iter()
+        .filter(|requirement| !is_installed(&requirement.tool))
+        .collect()
+}
+
+/// Lists the `missing` tools with their hints, empty when nothing is
+/// missing.
+pub fn hints(missing: &[&Requirement]) -> String {
+    let mut hints = String::new();
+
+    if missing.is_empty() {
+        return hints;
+    }
+
+    let _ = writeln!(hints, "Missing tools:");
+
+    for requirement in missing {
+        let _ = match requirement.hint {
+            Some(ref hint) => writeln!(hints, "  {}: {}", requirement.tool, hint),
+            None => writeln!(hints, "  {}", requirement.tool),
+        };
+    }
+
+    hints
+}
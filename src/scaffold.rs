@@ -0,0 +1,173 @@
+//! `pi template new`, creating the skeleton of a new template.
+
+use std::fs;
+use std::path::Path;
+
+#[cfg(not(target_os = "windows"))]
+use std::os::unix::fs::PermissionsExt;
+
+use crate::constants::TEMPLATE_FILENAME;
+
+/// What goes in a new template.
+#[derive(Debug, Default)]
+pub struct Scaffold {
+    pub name: String,
+    pub description: Option<String>,
+    /// Declare example variables in `template.toml`
+    pub variables: bool,
+}
+
+const README: &str = "# {{ Project }}
+
+{{ description }}
+
+{{ greeting }}
+
+Generated with pi on {{ date }}.
+";
+
+const KEYS_SCRIPT: &str =
+    "// Run before rendering: reads the resolved keys in `keys`, and the map it
+// returns adds keys, here {{ greeting }} used in README.md.
+#{ greeting: \"Welcome to \" + keys.project + \"!\" }
+";
+
+const GUIDE: &str = "# Writing this template
+
+Files listed in template.toml are generated in every project made from this
+template, other files, like this one, stay here.
+
+## Placeholders
+
+Files listed under `templates` and `scripts`, and the names of every listed
+file, are rendered with [mustache](https://mustache.github.io/mustache.5.html):
+
+- `{{ project }}` is the name of the project, also available as `{{ Project }}`,
+  `{{ ProjectCamelCase }}`, `{{ project_snake }}`, `{{ project-kebab }}`,
+  `{{ PROJECT_SHOUTY }}` and `{{ projectlower }}`
+- `{{ name }}`, `{{ email }}` and `{{ github_username }}` come from the author
+  in ~/.pi.toml
+- `{{ year }}`, `{{ date }}`, `{{ version }}` and `{{ license }}` are set by pi
+- `[custom_keys.toml]` in template.toml adds keys, `[variables]` asks for them
+- keys.rhai computes keys from the others
+
+`{{#key}}...{{/key}}` renders its content only when the key is set and not
+false, `{{^key}}...{{/key}}` only when it isn't. Placeholders without a value are
+asked for when generating a project.
+
+## Trying it
+
+    pi validate
+    pi new . my-project --dry-run
+";
+
+const SETUP_SCRIPT: &str = "#!/bin/sh
+# Made executable in generated projects, run it once after generating one.
+echo \"Setting up {{ project }}\"
+";
+
+/// Create the template described by `scaffold` in `directory`, which must
+/// not exist or be empty.
+pub fn scaffold_template<P: AsRef<Path>>(
+    directory: P,
+    scaffold: &Scaffold,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let directory = directory.as_ref();
+
+    if fs::read_dir(directory).is_ok_and(|mut entries| entries.next().is_some()) {
+        return Err(format!(
+            "{} already exists and isn't empty",
+            directory.to_string_lossy()
+        )
+        .into());
+    }
+
+    fs::create_dir_all(directory.join("scripts"))?;
+
+    fs::write(directory.join(TEMPLATE_FILENAME), template_toml(scaffold))?;
+    fs::write(directory.join("README.md"), README)?;
+    fs::write(directory.join("TEMPLATE.md"), GUIDE)?;
+    fs::write(directory.join("keys.rhai"), KEYS_SCRIPT)?;
+
+    let script = directory.join("scripts").join("setup.sh");
+
+    fs::write(&script, SETUP_SCRIPT)?;
+
+    #[cfg(not(target_os = "windows"))]
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755))?;
+
+    Ok(())
+}
+
+/// Commented `template.toml` of the template described by `scaffold`.
+fn template_toml(scaffold: &Scaffold) -> String {
+    let description = scaffold
+        .description
+        .clone()
+        .unwrap_or_else(|| format!("A {} project", scaffold.name));
+
+    let mut toml = format!(
+        r#"# Template read by pi, see `man pi` for every option.
+
+# license = "MIT"     # LICENSE file of generated projects
+# with_readme = false # generic README.md, this template has its own
+script = "keys.rhai"  # computes keys before rendering, TEMPLATE.md explains them
+
+[template]
+name = {name:?}
+description = {description:?}
+tags = []
+
+[files]
+# directories created empty
+directories = ["scripts"]
+# files created empty, their names can hold placeholders
+files = [".gitignore"]
+# files whose name and content are rendered with the keys
+templates = ["README.md"]
+# templates made executable
+scripts = ["scripts/setup.sh"]
+
+[config]
+version = "0.1.0"
+# version_control = "git"
+
+# keys available to every template, besides the builtin ones such as
+# project, year, date, name and email
+[custom_keys.toml]
+description = {description:?}
+"#,
+        name = scaffold.name,
+        description = description,
+    );
+
+    if scaffold.variables {
+        toml.push_str(
+            r#"
+# variables asked for when generating a project
+[variables.crate_name]
+prompt = "Name of the crate"
+help = "Lowercase letters, digits and underscores"
+pattern = "^[a-z][a-z0-9_]*$"
+order = 1
+
+[variables.homepage]
+prompt = "Homepage"
+default = "https://example.com"
+validator = "url"
+order = 2
+"#,
+        );
+    } else {
+        toml.push_str(
+            r#"
+# variables asked for when generating a project, for instance
+# [variables.crate_name]
+# prompt = "Name of the crate"
+# pattern = "^[a-z][a-z0-9_]*$"
+"#,
+        );
+    }
+
+    toml
+}
@@ -0,0 +1,100 @@
+//! Embedded scripting, for template logic that goes beyond placeholders.
+//!
+//! A template can reference a [rhai](https://rhai.rs) script in its
+//! `template.toml`, the script sees the already resolved keys in a `keys`
+//! map and must return a map, whose entries are merged into the keys used to
+//! render the template.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use rhai::{Array, Dynamic, Engine, Map, Scope};
+use rustache::{Data, HashBuilder};
+
+/// Run the rhai script located at `path` and merge the map it returns in
+/// `hash`.
+pub fn run_script<'a, P: AsRef<Path>>(
+    path: P,
+    hash: HashBuilder<'a>,
+) -> Result<HashBuilder<'a>, Box<dyn std::error::Error>> {
+    let engine = Engine::new();
+
+    let mut scope = Scope::new();
+
+    scope.push_constant("keys", keys_to_map(&hash.data));
+
+    let result: Dynamic = engine
+        .eval_file_with_scope(&mut scope, path.as_ref().to_path_buf())
+        .map_err(|error| error.to_string())?;
+
+    let map = result.try_cast::<Map>().ok_or_else(|| {
+        format!(
+            "Script {} must return a map of keys",
+            path.as_ref().to_string_lossy()
+        )
+    })?;
+
+    Ok(map.into_iter().fold(hash, |hash, (key, value)| {
+        hash.insert(key.to_string(), dynamic_to_data(value))
+    }))
+}
+
+/// Convert the keys already available to templates into a rhai map,
+/// lambdas can't be represented and are skipped.
+fn keys_to_map(data: &HashMap<String, Data>) -> Map {
+    data.iter()
+        .filter_map(|(key, value)| data_to_dynamic(value).map(|value| (key.into(), value)))
+        .collect()
+}
+
+fn data_to_dynamic(data: &Data) -> Option<Dynamic> {
+    match data {
+        Data::String(value) => Some(value.clone().into()),
+        Data::Bool(value) => Some((*value).into()),
+        Data::Integer(value) => Some((*value as i64).into()),
+        Data::Float(value) => Some((*value).into()),
+        Data::Vector(values) => Some(
+            values
+                .iter()
+                .filter_map(data_to_dynamic)
+                .collect::<Array>()
+                .into(),
+        ),
+        Data::Hash(values) => Some(keys_to_map(values).into()),
+        Data::Lambda(_) => None,
+    }
+}
+
+fn dynamic_to_data<'a>(value: Dynamic) -> Data<'a> {
+    if value.is_map() {
+        let map = value.cast::<Map>();
+
+        Data::Hash(
+            map.into_iter()
+                .map(|(key, value)| (key.to_string(), dynamic_to_data(value)))
+                .collect(),
+        )
+    } else if value.is_array() {
+        Data::Vector(
+            value
+                .cast::<Array>()
+                .into_iter()
+                .map(dynamic_to_data)
+                .collect(),
+        )
+    } else if let Ok(value) = value.as_bool() {
+        Data::Bool(value)
+    } else if let Ok(value) = value.as_int() {
+        // mustache data only has 32 bits integers, larger values are kept
+        // as strings
+        i32::try_from(value)
+            .map(Data::Integer)
+            .unwrap_or_else(|_| Data::String(value.to_string()))
+    } else if let Ok(value) = value.as_float() {
+        Data::Float(value)
+    } else if value.is_unit() {
+        Data::String(String::new())
+    } else {
+        Data::String(value.to_string())
+    }
+}
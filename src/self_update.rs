@@ -0,0 +1,199 @@
+//! Updating pi itself to its latest GitHub release.
+//!
+//! Releases carry a binary per platform, named after the target triple it
+//! was built for, along with its sha256 checksum, either in a `<BINARY>.sha256`
+//! file or in a `SHA256SUMS` file covering every binary.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use semver::Version;
+use serde::Deserialize;
+use tempdir::TempDir;
+use url::Url;
+use walkdir::WalkDir;
+
+use crate::archive::extract;
+use crate::constants::RELEASES_REPOSITORY;
+use crate::fetch::ARCHIVE_EXTENSIONS;
+use crate::http::HttpFetcher;
+use crate::manifest::checksum;
+use crate::types::Config;
+
+/// Target triple this pi was built for, as found in the names of release
+/// binaries.
+pub const TARGET: &str = env!("PI_TARGET");
+
+/// Name of the file listing the checksums of every binary of a release.
+const CHECKSUMS_FILENAME: &str = "SHA256SUMS";
+
+/// A release of pi, as returned by the GitHub releases API.
+#[derive(Debug, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    pub assets: Vec<ReleaseAsset>,
+}
+
+/// A file attached to a [`Release`].
+#[derive(Debug, Deserialize)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub browser_download_url: Url,
+}
+
+impl Release {
+    /// Version of pi the release is for, from its `vX.Y.Z` tag.
+    pub fn version(&self) -> Result<Version, semver::Error> {
+        Version::parse(self.tag_name.trim_start_matches('v'))
+    }
+
+    /// The binary built for this platform, if the release has one.
+    pub fn binary(&self) -> Option<&ReleaseAsset> {
+        self.assets
+            .iter()
+            .find(|asset| asset.name.contains(TARGET) && !asset.name.ends_with(".sha256"))
+    }
+
+    /// The file holding the checksum of `binary`.
+    fn checksum_file(&self, binary: &ReleaseAsset) -> Option<&ReleaseAsset> {
+        let name = format!("{}.sha256", binary.name);
+
+        self.assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .or_else(|| {
+                self.assets
+                    .iter()
+                    .find(|asset| asset.name == CHECKSUMS_FILENAME)
+            })
+    }
+}
+
+/// Fetch the description of the latest release of pi.
+pub async fn latest_release(
+    config: &Config,
+    fetcher: &dyn HttpFetcher,
+) -> Result<Release, Box<dyn std::error::Error>> {
+    let url = config
+        .github_api_url()
+        .join(&format!("repos/{}/releases/latest", RELEASES_REPOSITORY))?;
+
+    let body = fetcher
+        .get_with_token(&url, config.github_token().as_deref())
+        .await
+        .map_err(|error| format!("Couldn't fetch the latest release of pi: {}", error))?;
+
+    Ok(serde_json::from_slice(&body)
+        .map_err(|error| format!("Unexpected response from GitHub: {}", error))?)
+}
+
+/// Replace the running executable with the binary of `release` for this
+/// platform, once its checksum is verified.
+pub async fn install_release(
+    release: &Release,
+    fetcher: &dyn HttpFetcher,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let binary = release
+        .binary()
+        .ok_or_else(|| format!("Release {} has no binary for {}", release.tag_name, TARGET))?;
+
+    let checksum_file = release.checksum_file(binary).ok_or_else(|| {
+        format!(
+            "Release {} has no checksum for {}, refusing to install it",
+            release.tag_name, binary.name
+        )
+    })?;
+
+    let bytes = fetcher
+        .get(&binary.browser_download_url)
+        .await
+        .map_err(|error| format!("Couldn't download {}: {}", binary.name, error))?;
+
+    let checksums = fetcher
+        .get(&checksum_file.browser_download_url)
+        .await
+        .map_err(|error| format!("Couldn't download {}: {}", checksum_file.name, error))?;
+
+    let expected = expected_checksum(&String::from_utf8_lossy(&checksums), &binary.name)
+        .ok_or_else(|| format!("{} doesn't list {}", checksum_file.name, binary.name))?;
+
+    let actual = checksum(&bytes);
+
+    if !actual.eq_ignore_ascii_case(&expected) {
+        return Err(format!(
+            "Checksum mismatch for {}, expected {} but got {}",
+            binary.name, expected, actual
+        )
+        .into());
+    }
+
+    let executable = if ARCHIVE_EXTENSIONS
+        .iter()
+        .any(|extension| binary.name.ends_with(extension))
+    {
+        let directory = TempDir::new("pi-self-update")?;
+
+        extract(&bytes, directory.path())?;
+
+        fs::read(
+            executable_in(directory.path())
+                .ok_or_else(|| format!("{} doesn't contain a pi executable", binary.name))?,
+        )?
+    } else {
+        bytes
+    };
+
+    replace_current_executable(&executable)
+}
+
+/// Find the checksum of `name` in the contents of a checksum file, either a
+/// lone checksum or `<CHECKSUM>  <NAME>` lines.
+fn expected_checksum(checksums: &str, name: &str) -> Option<String> {
+    let mut lines = checksums.lines().map(str::split_whitespace);
+
+    match checksums.lines().count() {
+        1 => lines.next()?.next().map(str::to_string),
+        _ => lines.find_map(|mut fields| {
+            let checksum = fields.next()?;
+
+            (fields.next()?.trim_start_matches('*') == name).then(|| checksum.to_string())
+        }),
+    }
+}
+
+/// Path of the pi executable in an extracted release archive.
+fn executable_in(directory: &Path) -> Option<PathBuf> {
+    let name = format!("pi{}", std::env::consts::EXE_SUFFIX);
+
+    WalkDir::new(directory)
+        .into_iter()
+        .flatten()
+        .find(|entry| entry.file_type().is_file() && entry.file_name() == name.as_str())
+        .map(|entry| entry.into_path())
+}
+
+/// Write `executable` in place of the running executable, returning its
+/// path.
+fn replace_current_executable(executable: &[u8]) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let current = std::env::current_exe()?.canonicalize()?;
+
+    // written next to the executable, so that it can be renamed over it
+    let update = current.with_extension("update");
+
+    fs::write(&update, executable)?;
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        fs::set_permissions(&update, fs::Permissions::from_mode(0o755))?;
+    }
+
+    // a running executable can't be replaced on Windows, but it can be moved
+    #[cfg(target_os = "windows")]
+    fs::rename(&current, current.with_extension("old"))?;
+
+    fs::rename(&update, &current)?;
+
+    Ok(current)
+}
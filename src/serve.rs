@@ -0,0 +1,274 @@
+//! `pi serve`, generating projects over HTTP for developer portals and bots.
+//!
+//! The templates served are those installed in `$HOME/.pi_templates/`:
+//!
+//! - `GET /templates` lists them, as a JSON array of their name and
+//!   description.
+//! - `POST /generate` takes a JSON [`GenerateRequest`] and answers with the
+//!   generated project as a zip archive, or generates it in the server's
+//!   output directory and answers with its path.
+//!
+//! Errors are answered with a JSON object holding an `error` message.
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use hyper::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use tokio::runtime::Handle;
+use tracing::{info, warn};
+
+use crate::answers::json_answers;
+use crate::dependencies::resolve_dependencies;
+use crate::http::HttpFetcher;
+use crate::install::installed_template;
+use crate::manifest::TemplateSource;
+use crate::names::validate_name;
+use crate::types::{Config, Project, TemplateMetadata};
+use crate::util::{init_helper, plan_generation, InitOptions};
+
+/// What the server generates projects with.
+pub struct Server {
+    pub home: PathBuf,
+    pub config: Config,
+    /// Directory projects are generated into when asked for their path,
+    /// those requests are refused without it
+    pub output: Option<PathBuf>,
+    pub fetcher: Box<dyn HttpFetcher>,
+}
+
+/// Body of `POST /generate`.
+#[derive(Debug, Deserialize)]
+pub struct GenerateRequest {
+    /// Name of an installed template
+    pub template: String,
+    /// Name of the project
+    pub name: String,
+    /// Values of the template's keys, as in a JSON answers file
+    #[serde(default)]
+    pub answers: serde_json::Map<String, serde_json::Value>,
+    #[serde(default)]
+    pub output: GenerateOutput,
+}
+
+/// How `POST /generate` hands over the generated project.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GenerateOutput {
+    /// A zip archive of the project, in the response
+    #[default]
+    Zip,
+    /// The path of the project, generated in the server's output directory
+    Path,
+}
+
+/// Entry of `GET /templates`.
+#[derive(Debug, Serialize)]
+struct TemplateEntry {
+    name: String,
+    description: Option<String>,
+}
+
+/// A request the server refuses, with the reason.
+type Rejection = (StatusCode, String);
+
+/// Serve `server` on `address` until the process is stopped.
+pub async fn serve(address: SocketAddr, server: Server) -> Result<(), hyper::Error> {
+    let server = Arc::new(server);
+
+    let service = make_service_fn(move |_| {
+        let server = server.clone();
+
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let server = server.clone();
+
+                async move { Ok::<_, Infallible>(handle(server, request).await) }
+            }))
+        }
+    });
+
+    let bound = hyper::Server::try_bind(&address)?.serve(service);
+
+    info!("Serving templates on http://{}", bound.local_addr());
+
+    bound.await
+}
+
+async fn handle(server: Arc<Server>, request: Request<Body>) -> Response<Body> {
+    let result = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/templates") => templates(&server),
+        (&Method::POST, "/generate") => generate(server, request).await,
+        (_, "/templates") | (_, "/generate") => Err((
+            StatusCode::METHOD_NOT_ALLOWED,
+            format!("{} isn't allowed here", request.method()),
+        )),
+        (_, path) => Err((StatusCode::NOT_FOUND, format!("Nothing at {}", path))),
+    };
+
+    result.unwrap_or_else(|(status, message)| {
+        if status.is_server_error() {
+            warn!("{}", message);
+        }
+
+        json_response(status, &serde_json::json!({ "error": message }))
+    })
+}
+
+fn templates(server: &Server) -> Result<Response<Body>, Rejection> {
+    let templates = Project::installed(&server.home)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|path| {
+            let name = path.file_name()?.to_string_lossy().into_owned();
+
+            let description =
+                TemplateMetadata::from_directory(&path).and_then(|metadata| metadata.description);
+
+            Some(TemplateEntry { name, description })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(json_response(StatusCode::OK, &templates))
+}
+
+async fn generate(
+    server: Arc<Server>,
+    request: Request<Body>,
+) -> Result<Response<Body>, Rejection> {
+    let body = hyper::body::to_bytes(request.into_body())
+        .await
+        .map_err(|error| (StatusCode::BAD_REQUEST, error.to_string()))?;
+
+    let request: GenerateRequest = serde_json::from_slice(&body).map_err(|error| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid request: {}", error),
+        )
+    })?;
+
+    // fetching dependencies and rendering don't run on the server's threads
+    tokio::task::spawn_blocking(move || generate_project(&server, request))
+        .await
+        .map_err(|error| (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()))?
+}
+
+/// Generate the project `request` asks for, as a response.
+fn generate_project(
+    server: &Server,
+    request: GenerateRequest,
+) -> Result<Response<Body>, Rejection> {
+    let directory = installed_template(&server.home, &request.template).map_err(|_| {
+        (
+            StatusCode::NOT_FOUND,
+            format!("No template named {} installed", request.template),
+        )
+    })?;
+
+    let project = Project::read(&server.home, &directory)
+        .map_err(|error| (StatusCode::INTERNAL_SERVER_ERROR, error))?;
+
+    validate_name(&request.name, &project.name_rules).map_err(|invalid| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid project name: {}", invalid),
+        )
+    })?;
+
+    let source = TemplateSource::from_path(&directory);
+
+    let dependencies = Handle::current()
+        .block_on(resolve_dependencies(
+            &server.home,
+            &server.config,
+            &source,
+            &project,
+            server.fetcher.as_ref(),
+        ))
+        .map_err(|error| (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()))?;
+
+    let mut options = InitOptions {
+        output: PathBuf::from(&request.name),
+        keys: json_answers(request.answers),
+        dependencies,
+        source: Some(source),
+        manifest: true,
+        // placeholders without an answer are an error rather than a prompt
+        prompt: true,
+        ..Default::default()
+    };
+
+    let name = request.name;
+
+    match request.output {
+        GenerateOutput::Zip => {
+            let plan = plan_generation(
+                &name,
+                &server.config,
+                &project,
+                &options,
+                &mut options.keys.clone(),
+            )
+            .map_err(|error| (StatusCode::UNPROCESSABLE_ENTITY, error.to_string()))?;
+
+            let archive = plan
+                .zip(&name)
+                .map_err(|error| (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()))?;
+
+            info!("Generated {} from {}", name, request.template);
+
+            Ok(Response::builder()
+                .header(CONTENT_TYPE, "application/zip")
+                .header(
+                    CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}.zip\"", name),
+                )
+                .body(Body::from(archive))
+                .unwrap())
+        }
+        GenerateOutput::Path => {
+            let output = server.output.as_ref().ok_or_else(|| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    "pi serve was started without --output-dir, ask for a zip instead".to_string(),
+                )
+            })?;
+
+            options.output = output.join(&name);
+
+            if options.output.exists() {
+                return Err((
+                    StatusCode::CONFLICT,
+                    format!("A project named {} already exists", name),
+                ));
+            }
+
+            init_helper(&name, &server.config, &project, &options)
+                .map_err(|error| (StatusCode::UNPROCESSABLE_ENTITY, error.to_string()))?;
+
+            info!(
+                "Generated {} from {} in {}",
+                name,
+                request.template,
+                options.output.to_string_lossy()
+            );
+
+            Ok(json_response(
+                StatusCode::CREATED,
+                &serde_json::json!({ "path": options.output }),
+            ))
+        }
+    }
+}
+
+fn json_response<T: Serialize>(status: StatusCode, value: &T) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(serde_json::to_vec(value).unwrap_or_default()))
+        .unwrap()
+}
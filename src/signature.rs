@@ -0,0 +1,128 @@
+//! Verification of the signatures of remote templates.
+//!
+//! Templates repositories can list a minisign signature for each released
+//! version of a template. The signed message is the template's checksum, as
+//! computed by [`template_checksum`], so it doesn't depend on whether the
+//! template was cloned or downloaded.
+
+use std::path::Path;
+
+use minisign_verify::{PublicKey, Signature};
+use tracing::warn;
+use url::Url;
+
+use crate::constants::TEMPLATES_REPOSITORY_TIMEOUT;
+use crate::fetch::same_repository;
+use crate::http::HttpFetcher;
+use crate::manifest::{template_checksum, TemplateSource};
+use crate::types::{Config, TemplateRepository};
+
+/// Check the signature of the template fetched from `source` into
+/// `template_dir`, according to the `signatures` configuration.
+///
+/// Returns an error when the template must be refused, problems are only
+/// reported as warnings unless signatures are enforced.
+pub async fn check_signature(
+    config: &Config,
+    source: &TemplateSource,
+    template_dir: &Path,
+    fetcher: &dyn HttpFetcher,
+) -> Result<(), String> {
+    let settings = match &config.signatures {
+        Some(settings) => settings,
+        None => return Ok(()),
+    };
+
+    let refuse_or_warn = |message: String| {
+        if settings.enforce {
+            Err(message)
+        } else {
+            warn!("{}", message);
+
+            Ok(())
+        }
+    };
+
+    // only git templates are listed in templates repositories, local ones
+    // are trusted
+    let (url, rev) = match source {
+        TemplateSource::Git { url, rev, .. } => (url, rev.as_deref()),
+        TemplateSource::Path { .. } => return Ok(()),
+        _ => {
+            return refuse_or_warn(format!(
+                "Template {} is not signed, only git templates can be",
+                source
+            ))
+        }
+    };
+
+    let signature = match find_signature(config, url, rev, fetcher).await {
+        Some(signature) => signature,
+        None => return refuse_or_warn(format!("Template {} is not signed", url)),
+    };
+
+    let signature = Signature::decode(&signature)
+        .map_err(|error| format!("Invalid signature for template {}: {}", url, error))?;
+
+    let checksum = template_checksum(template_dir)
+        .map_err(|error| format!("Couldn't compute the template's checksum: {}", error))?;
+
+    let trusted = settings
+        .trusted_keys
+        .iter()
+        .any(|key| match PublicKey::from_base64(key) {
+            Ok(key) => key.verify(checksum.as_bytes(), &signature, false).is_ok(),
+            Err(error) => {
+                warn!("Invalid trusted key {}: {}", key, error);
+
+                false
+            }
+        });
+
+    if trusted {
+        Ok(())
+    } else {
+        refuse_or_warn(format!(
+            "Template {} isn't signed by any trusted key, it may have been tampered with",
+            url
+        ))
+    }
+}
+
+/// Look for the signature of the template at `url` in the templates
+/// repositories, downloading it if the entry only links to it.
+async fn find_signature(
+    config: &Config,
+    url: &Url,
+    rev: Option<&str>,
+    fetcher: &dyn HttpFetcher,
+) -> Option<String> {
+    let repositories = TemplateRepository::read_all(
+        &config.all_templates_repositories(),
+        fetcher,
+        TEMPLATES_REPOSITORY_TIMEOUT,
+    )
+    .await;
+
+    let signature = repositories
+        .into_iter()
+        .flat_map(|(_, entries)| entries)
+        .filter(|entry| same_repository(&entry.repository, url))
+        .flat_map(|entry| entry.versions)
+        .find(|version| rev == Some(version.rev.as_str()) || rev == Some(version.version.as_str()))
+        .and_then(|version| version.signature)?;
+
+    match signature.parse::<Url>() {
+        Ok(signature_url) if matches!(signature_url.scheme(), "http" | "https") => {
+            match fetcher.get(&signature_url).await {
+                Ok(bytes) => Some(String::from_utf8_lossy(&bytes).into_owned()),
+                Err(error) => {
+                    warn!("Couldn't download signature {}: {}", signature_url, error);
+
+                    None
+                }
+            }
+        }
+        _ => Some(signature),
+    }
+}
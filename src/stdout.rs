@@ -0,0 +1,73 @@
+//! Reserving stdout for the path of the generated project, for
+//! `--print-path`.
+//!
+//! Shell functions run `cd "$(pi new rust foo --print-path)"`, so nothing but
+//! the path may reach stdout: everything else written there, by pi or by the
+//! commands it runs, goes to stderr meanwhile.
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// The original stdout, while stdout is redirected to stderr.
+#[derive(Debug)]
+pub struct ReservedStdout {
+    #[cfg(unix)]
+    stdout: File,
+}
+
+/// Redirect stdout to stderr until the returned [`ReservedStdout`] prints the
+/// path.
+///
+/// Only Unix redirects the output of the commands pi runs, elsewhere the path
+/// is printed on stdout along with it.
+pub fn reserve_stdout() -> io::Result<ReservedStdout> {
+    io::stdout().flush()?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::io::FromRawFd;
+
+        // SAFETY: only duplicates the standard descriptors, the duplicate is
+        // owned by the returned file
+        unsafe {
+            let stdout = libc::dup(libc::STDOUT_FILENO);
+
+            if stdout < 0 || libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) < 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            Ok(ReservedStdout {
+                stdout: File::from_raw_fd(stdout),
+            })
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        Ok(ReservedStdout {})
+    }
+}
+
+impl ReservedStdout {
+    /// Print the absolute `path` on the original stdout.
+    pub fn print_path<P: AsRef<Path>>(self, path: P) -> io::Result<()> {
+        let path = path.as_ref().canonicalize()?;
+
+        io::stdout().flush()?;
+
+        #[cfg(unix)]
+        {
+            let mut stdout = self.stdout;
+
+            writeln!(stdout, "{}", path.to_string_lossy())
+        }
+
+        #[cfg(not(unix))]
+        {
+            println!("{}", path.to_string_lossy());
+
+            Ok(())
+        }
+    }
+}
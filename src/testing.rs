@@ -0,0 +1,172 @@
+//! Helpers to test templates from Rust.
+//!
+//! Organizations maintaining in-house templates can generate them in a
+//! temporary directory with canned answers, and assert on the produced
+//! project from their own integration tests:
+//!
+//! ```no_run
+//! use project_init::testing::generate;
+//!
+//! let project = generate("templates/rust-cli", "demo", &[("team", "core")]);
+//!
+//! project.assert_file("Cargo.toml");
+//! project.assert_contains("Cargo.toml", "name = \"demo\"");
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tempdir::TempDir;
+use walkdir::WalkDir;
+
+use crate::constants::TEMPLATE_FILENAME;
+use crate::types::{is_template, Config, Project};
+use crate::util::{init_helper, InitOptions};
+
+/// A project generated in a temporary directory, removed when dropped.
+#[derive(Debug)]
+pub struct GeneratedProject {
+    // kept alive so the directory isn't removed before the project
+    _directory: TempDir,
+    path: PathBuf,
+}
+
+/// Generate the template located in `template` as a project called `name`.
+///
+/// `answers` take precedence over every other key, the user's global
+/// configuration is ignored so that results don't depend on the machine
+/// running the tests.
+///
+/// # Panics
+///
+/// Panics if the template can't be found or if the generation fails.
+pub fn generate<T: AsRef<Path>>(
+    template: T,
+    name: &str,
+    answers: &[(&str, &str)],
+) -> GeneratedProject {
+    let template = template.as_ref();
+
+    assert!(
+        is_template(template),
+        "No {} found in {}",
+        TEMPLATE_FILENAME,
+        template.to_string_lossy()
+    );
+
+    let project = Project::read(template, template)
+        .unwrap_or_else(|error| panic!("Invalid template: {}", error));
+
+    let directory = TempDir::new("pi-testing").expect("failed to create temporary directory");
+
+    let options = InitOptions {
+        output: directory.path().join(name),
+        force: true,
+        keys: answers
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+        ..Default::default()
+    };
+
+    if let Err(error) = init_helper(name, &Config::default(), &project, &options) {
+        panic!(
+            "Failed to generate {}: {}",
+            template.to_string_lossy(),
+            error
+        );
+    }
+
+    GeneratedProject {
+        _directory: directory,
+        path: options.output,
+    }
+}
+
+impl GeneratedProject {
+    /// Root directory of the generated project.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Every file of the generated project, relative to its root and sorted.
+    pub fn files(&self) -> Vec<PathBuf> {
+        WalkDir::new(&self.path)
+            .sort_by_file_name()
+            .into_iter()
+            .flatten()
+            .filter(|entry| entry.file_type().is_file())
+            .filter_map(|entry| {
+                entry
+                    .path()
+                    .strip_prefix(&self.path)
+                    .ok()
+                    .map(Path::to_path_buf)
+            })
+            .collect()
+    }
+
+    /// Content of the file at `path`, relative to the project root.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the file can't be read.
+    pub fn read<P: AsRef<Path>>(&self, path: P) -> String {
+        let full_path = self.path.join(&path);
+
+        fs::read_to_string(&full_path).unwrap_or_else(|error| {
+            panic!("Couldn't read {}: {}", full_path.to_string_lossy(), error)
+        })
+    }
+
+    /// Assert that the file at `path`, relative to the project root, exists.
+    pub fn assert_file<P: AsRef<Path>>(&self, path: P) {
+        assert!(
+            self.path.join(&path).is_file(),
+            "Expected file {} was not generated",
+            path.as_ref().to_string_lossy()
+        );
+    }
+
+    /// Assert that the directory at `path`, relative to the project root,
+    /// exists.
+    pub fn assert_dir<P: AsRef<Path>>(&self, path: P) {
+        assert!(
+            self.path.join(&path).is_dir(),
+            "Expected directory {} was not generated",
+            path.as_ref().to_string_lossy()
+        );
+    }
+
+    /// Assert that nothing exists at `path`, relative to the project root.
+    pub fn assert_missing<P: AsRef<Path>>(&self, path: P) {
+        assert!(
+            !self.path.join(&path).exists(),
+            "Unexpected path {} was generated",
+            path.as_ref().to_string_lossy()
+        );
+    }
+
+    /// Assert that the file at `path` has exactly the `expected` content.
+    pub fn assert_contents<P: AsRef<Path>>(&self, path: P, expected: &str) {
+        assert_eq!(
+            self.read(&path),
+            expected,
+            "Unexpected content in {}",
+            path.as_ref().to_string_lossy()
+        );
+    }
+
+    /// Assert that the file at `path` contains `needle`.
+    pub fn assert_contains<P: AsRef<Path>>(&self, path: P, needle: &str) {
+        let content = self.read(&path);
+
+        assert!(
+            content.contains(needle),
+            "{} doesn't contain {:?}:\n{}",
+            path.as_ref().to_string_lossy(),
+            needle,
+            content
+        );
+    }
+}
@@ -0,0 +1,68 @@
+//! Custom root certificates and certificate verification.
+//!
+//! HTTP requests get the options through their client, see
+//! [`crate::http::configured_fetcher`]. libgit2 is given the extra
+//! certificates by [`configure_git_transport`], and keeps trusting the
+//! system's.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use git2::{CertificateCheckStatus, RemoteCallbacks};
+use tracing::warn;
+
+use crate::types::Config;
+
+const PEM_END_CERTIFICATE: &str = "-----END CERTIFICATE-----";
+
+/// Whether git transports skip certificate verification.
+static GIT_INSECURE: AtomicBool = AtomicBool::new(false);
+
+/// Split a PEM bundle into its certificates.
+pub fn pem_certificates(bundle: &[u8]) -> Vec<Vec<u8>> {
+    String::from_utf8_lossy(bundle)
+        .split_inclusive(PEM_END_CERTIFICATE)
+        .filter(|certificate| certificate.contains(PEM_END_CERTIFICATE))
+        .map(|certificate| certificate.trim().as_bytes().to_vec())
+        .collect()
+}
+
+/// Apply the TLS options of `config` to the git transports of this process.
+pub fn configure_git_transport(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let tls = match &config.tls {
+        Some(tls) => tls,
+        None => return Ok(()),
+    };
+
+    if tls.insecure {
+        warn!("TLS certificate verification is disabled");
+    }
+
+    GIT_INSECURE.store(tls.insecure, Ordering::Relaxed);
+
+    if let Some(ca_bundle) = &tls.ca_bundle {
+        if !ca_bundle.is_file() {
+            return Err(format!("CA bundle {} not found", ca_bundle.to_string_lossy()).into());
+        }
+
+        // SAFETY: libgit2's global options are only set here, at startup,
+        // before any git transport is used; the bundle is trusted on top of
+        // the system's certificates
+        unsafe { git2::opts::set_ssl_cert_file(ca_bundle) }.map_err(|error| {
+            format!(
+                "Couldn't trust CA bundle {}: {}",
+                ca_bundle.to_string_lossy(),
+                error
+            )
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Skip certificate verification in `callbacks` if it is disabled.
+pub fn git_certificate_check(callbacks: &mut RemoteCallbacks<'_>) {
+    if GIT_INSECURE.load(Ordering::Relaxed) {
+        callbacks
+            .certificate_check(|_certificate, _host| Ok(CertificateCheckStatus::CertificateOk));
+    }
+}
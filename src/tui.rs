@@ -0,0 +1,266 @@
+//! `pi tui`, a full-screen browser of the installed and registry templates.
+//!
+//! Templates are listed on the left, filtered as you type, with a preview of
+//! the selected one on the right: its metadata, variables and file tree for
+//! installed templates, where it is listed for the others. Picking one asks
+//! for the project's name, the generation then goes on as with `pi new`.
+
+use std::io::{self, Stdout};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{Frame, Terminal};
+
+use crate::info::TemplateInfo;
+use crate::manifest::TemplateSource;
+use crate::names::validate_name;
+use crate::picker::Candidate;
+use crate::types::{Config, Project};
+use crate::warnings::{defer_warnings, take_warnings};
+
+/// Template picked in the browser, and the name of the project to generate.
+#[derive(Debug)]
+pub struct Selection {
+    pub candidate: Candidate,
+    pub name: String,
+}
+
+/// What the keys currently do.
+enum Mode {
+    /// Moving through and filtering the templates
+    Browse,
+    /// Typing the name of the project
+    Name { name: String, error: Option<String> },
+}
+
+struct Browser {
+    candidates: Vec<Candidate>,
+    /// Preview of each candidate, by index
+    previews: Vec<String>,
+    filter: String,
+    /// Index of the selected candidate among the filtered ones
+    selected: usize,
+    mode: Mode,
+}
+
+/// Restores the terminal when dropped, even if drawing failed.
+struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen);
+    }
+}
+
+/// Let the user browse `candidates` and pick one, along with the name of the
+/// project. Returns `None` if the user quits instead.
+pub fn browse(candidates: Vec<Candidate>, config: &Config) -> io::Result<Option<Selection>> {
+    // rendering previews may warn or, for broken templates, exit, so it is
+    // done before taking over the terminal
+    defer_warnings(true);
+
+    let previews = candidates
+        .iter()
+        .map(|candidate| preview(candidate, config))
+        .collect();
+
+    take_warnings();
+
+    defer_warnings(false);
+
+    let mut browser = Browser {
+        candidates,
+        previews,
+        filter: String::new(),
+        selected: 0,
+        mode: Mode::Browse,
+    };
+
+    enable_raw_mode()?;
+
+    let guard = TerminalGuard;
+
+    execute!(io::stdout(), EnterAlternateScreen)?;
+
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+
+    let picked = loop {
+        terminal.draw(|frame| browser.draw(frame))?;
+
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match browser.handle(key) {
+                Some(Some(name)) => break Some(name),
+                Some(None) => break None,
+                None => {}
+            }
+        }
+    };
+
+    drop(guard);
+
+    Ok(picked.map(|name| {
+        let index = browser.filtered()[browser.selected];
+
+        Selection {
+            candidate: browser.candidates.swap_remove(index),
+            name,
+        }
+    }))
+}
+
+impl Browser {
+    /// Indices of the candidates matching the filter.
+    fn filtered(&self) -> Vec<usize> {
+        let filter = self.filter.to_lowercase();
+
+        self.candidates
+            .iter()
+            .enumerate()
+            .filter(|(_, candidate)| candidate.to_string().to_lowercase().contains(&filter))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Handle `key`, returning `Some` once the browser is done: with the
+    /// project's name if a template was picked, else `None`.
+    fn handle(&mut self, key: KeyEvent) -> Option<Option<String>> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Some(None);
+        }
+
+        let count = self.filtered().len();
+
+        match &mut self.mode {
+            Mode::Browse => match key.code {
+                KeyCode::Esc => return Some(None),
+                KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+                KeyCode::Down if self.selected + 1 < count => self.selected += 1,
+                KeyCode::Enter if count > 0 => {
+                    self.mode = Mode::Name {
+                        name: String::new(),
+                        error: None,
+                    }
+                }
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                    self.selected = 0;
+                }
+                KeyCode::Char(c) => {
+                    self.filter.push(c);
+                    self.selected = 0;
+                }
+                _ => {}
+            },
+            Mode::Name { name, error } => match key.code {
+                KeyCode::Esc => self.mode = Mode::Browse,
+                KeyCode::Enter => match validate_name(name, &[]) {
+                    Ok(()) => return Some(Some(name.clone())),
+                    Err(invalid) => *error = Some(invalid.to_string()),
+                },
+                KeyCode::Backspace => {
+                    name.pop();
+                }
+                KeyCode::Char(c) => name.push(c),
+                _ => {}
+            },
+        }
+
+        None
+    }
+
+    fn draw(&self, frame: &mut Frame<CrosstermBackend<Stdout>>) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(3),
+                Constraint::Length(1),
+            ])
+            .split(frame.size());
+
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(rows[1]);
+
+        let (prompt, title, help) = match &self.mode {
+            Mode::Browse => (
+                self.filter.clone(),
+                "Filter".to_string(),
+                "↑/↓ move · type to filter · enter pick · esc quit",
+            ),
+            Mode::Name { name, error } => (
+                name.clone(),
+                error.clone().unwrap_or_else(|| "Project name".to_string()),
+                "enter generate · esc back",
+            ),
+        };
+
+        frame.render_widget(
+            Paragraph::new(prompt).block(Block::default().borders(Borders::ALL).title(title)),
+            rows[0],
+        );
+
+        let filtered = self.filtered();
+
+        let items = filtered
+            .iter()
+            .map(|&index| ListItem::new(self.candidates[index].name.clone()))
+            .collect::<Vec<_>>();
+
+        let mut state = ListState::default();
+
+        state.select((!filtered.is_empty()).then_some(self.selected));
+
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(Block::default().borders(Borders::ALL).title("Templates"))
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+            columns[0],
+            &mut state,
+        );
+
+        let preview = filtered
+            .get(self.selected)
+            .map(|&index| self.previews[index].as_str())
+            .unwrap_or("No template matches the filter");
+
+        frame.render_widget(
+            Paragraph::new(preview)
+                .wrap(Wrap { trim: false })
+                .block(Block::default().borders(Borders::ALL).title("Preview")),
+            columns[1],
+        );
+
+        frame.render_widget(Paragraph::new(help), rows[2]);
+    }
+}
+
+/// Description of `candidate`: what `pi info` shows for installed templates,
+/// where the others are listed.
+fn preview(candidate: &Candidate, config: &Config) -> String {
+    match (&candidate.source, &candidate.repository) {
+        (TemplateSource::Path { path }, None) => Project::read(path, path)
+            .and_then(|project| TemplateInfo::from_project(config, &project))
+            .map_or_else(|error| error, |info| info.to_string()),
+        (source, repository) => format!(
+            "Name: {}\nDescription: {}\nListed in: {}\nSource: {}\n\nFetched once picked.\n",
+            candidate.name,
+            candidate.description.as_deref().unwrap_or("-"),
+            repository.as_deref().unwrap_or("-"),
+            source
+        ),
+    }
+}
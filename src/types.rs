@@ -1,24 +1,39 @@
 //! This module contains the structs for the configuration files.
 
 use std::{
+    collections::BTreeMap,
     fmt::Display,
     fs::File,
     io::{BufReader, Read},
     path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
 };
 
-use serde::{Deserialize, Deserializer};
-use serde_derive::Serialize;
+use futures::future::join_all;
+use semver::Version;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use text_io::read;
 use toml::value::Value;
 use tracing::{error, info, warn};
 use url::Url;
 
-use crate::constants::{GLOBAL_TEMPLATE_DIRECTORY, TEMPLATE_FILENAME};
+use crate::cache::{cached_index, cached_index_validators, store_index, touch_index};
+use crate::constants::{
+    GITHUB_API_URL, GITHUB_BASE_URL_VARIABLE, GITHUB_TOKEN_VARIABLE, GITHUB_URL,
+    GLOBAL_TEMPLATE_DIRECTORY, TEMPLATE_FILENAME, TEMPLATE_FILENAMES,
+};
+use crate::diagnostics::{data_diagnostic, toml_diagnostic};
+use crate::http::{Conditional, HttpFetcher, Validators};
+use crate::manifest::TemplateSource;
+use crate::names::NameRule;
+use crate::variables::Variable;
+use crate::workspace::Workspace;
 
 /// Struct for the author. This is read from the global
 /// configuration that resides at $HOME/.pi.toml
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Author {
     pub name: String,
     pub email: String,
@@ -44,7 +59,7 @@ impl Author {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum VersionControl {
     Git,
@@ -69,26 +84,279 @@ impl Display for VersionControl {
     }
 }
 
+impl FromStr for VersionControl {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "git" => Ok(Self::Git),
+            "hg" => Ok(Self::Hg),
+            "mercurial" => Ok(Self::Mercurial),
+            "pijul" => Ok(Self::Pijul),
+            "darcs" => Ok(Self::Darcs),
+            _ => Err(format!(
+                "Unknown version control {}, expected git, mercurial, pijul or darcs",
+                value
+            )),
+        }
+    }
+}
+
+/// Released version of a template listed in a templates repository.
+#[derive(Debug, Deserialize)]
+pub struct TemplateVersion {
+    pub version: String,
+    /// Git ref (tag, branch or commit) the version was released from
+    pub rev: String,
+    /// Sha256 checksum of the released template, if published
+    pub checksum: Option<String>,
+    /// Minisign signature of the checksum, or the url it can be downloaded from
+    pub signature: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct TemplateRepositoryEntry {
     pub name: String,
     pub repository: Url,
     pub description: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Main programming language of generated projects
+    pub language: Option<String>,
+    /// Released versions, from oldest to newest
+    #[serde(default)]
+    pub versions: Vec<TemplateVersion>,
+    /// Oldest version of pi able to render the template
+    pub min_pi_version: Option<String>,
+    /// Popularity of the template, as reported by the registry
+    pub stars: Option<u64>,
+    pub downloads: Option<u64>,
+    /// Date of the template's last update, as an ISO 8601 date
+    pub updated_at: Option<String>,
+}
+
+impl TemplateRepositoryEntry {
+    /// Most recent released version of the template, if any.
+    pub fn latest_version(&self) -> Option<&TemplateVersion> {
+        self.versions.last()
+    }
+
+    /// Returns `false` when the template requires a newer version of pi.
+    pub fn is_supported(&self) -> bool {
+        let current = Version::parse(env!("CARGO_PKG_VERSION")).unwrap();
+
+        self.min_pi_version
+            .as_deref()
+            .and_then(|version| Version::parse(version).ok())
+            .is_none_or(|min_pi_version| min_pi_version <= current)
+    }
+
+    /// Where the template can be fetched from, at its latest released version
+    /// if it has any.
+    pub fn source(&self) -> TemplateSource {
+        TemplateSource::Git {
+            url: self.repository.clone(),
+            rev: self.latest_version().map(|version| version.rev.clone()),
+            template: None,
+        }
+    }
+
+    /// Returns `true` when the template is tagged with every tag in `tags`.
+    pub fn has_tags(&self, tags: &[String]) -> bool {
+        has_tags(&self.tags, tags)
+    }
+
+    /// Returns `true` when the template is written in `language`, or when no
+    /// language is wanted.
+    pub fn has_language(&self, language: Option<&str>) -> bool {
+        has_language(self.language.as_deref(), language)
+    }
+
+    /// Returns `true` when `query` is part of the template's name or
+    /// description, or is one of its tags, ignoring case. An empty query
+    /// matches every template.
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+
+        query.is_empty()
+            || self.name.to_lowercase().contains(&query)
+            || self.description.to_lowercase().contains(&query)
+            || self.tags.iter().any(|tag| tag.to_lowercase() == query)
+    }
+}
+
+/// Returns the file describing the template in `directory`, the first of
+/// [`TEMPLATE_FILENAMES`] it holds.
+pub fn template_file<P: AsRef<Path>>(directory: P) -> Option<PathBuf> {
+    TEMPLATE_FILENAMES
+        .iter()
+        .map(|name| directory.as_ref().join(name))
+        .find(|path| path.is_file())
+}
+
+/// Returns `true` if `directory` holds a template.
+pub fn is_template<P: AsRef<Path>>(directory: P) -> bool {
+    template_file(directory).is_some()
+}
+
+/// Parse the template description `content`, read from `path`, as YAML or
+/// JSON depending on its extension and as TOML otherwise. Errors are rendered
+/// as diagnostics pointing at the problem.
+pub fn parse_template_file<T: DeserializeOwned>(path: &Path, content: &str) -> Result<T, String> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("yaml") | Some("yml") => serde_yaml::from_str(content).map_err(|error| {
+            let position = error
+                .location()
+                .map(|location| (location.line() - 1, location.column() - 1));
+
+            data_diagnostic(path, content, &error.to_string(), position)
+        }),
+        Some("json") => serde_json::from_str(content).map_err(|error| {
+            let position =
+                (error.line() > 0).then(|| (error.line() - 1, error.column().saturating_sub(1)));
+
+            data_diagnostic(path, content, &error.to_string(), position)
+        }),
+        _ => toml::from_str(content).map_err(|error| toml_diagnostic(path, content, &error)),
+    }
+}
+
+/// Returns `true` when `language` is `wanted`, ignoring case, or when no
+/// language is wanted.
+pub fn has_language(language: Option<&str>, wanted: Option<&str>) -> bool {
+    wanted
+        .is_none_or(|wanted| language.is_some_and(|language| language.eq_ignore_ascii_case(wanted)))
+}
+
+/// Returns `true` when every tag in `wanted` is in `tags`, ignoring case.
+pub fn has_tags(tags: &[String], wanted: &[String]) -> bool {
+    wanted
+        .iter()
+        .all(|wanted| tags.iter().any(|tag| tag.eq_ignore_ascii_case(wanted)))
 }
 
 impl Display for TemplateRepositoryEntry {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{} -- {}: {}",
+            "{} -- {}",
             self.repository.path().get(1..).unwrap(),
             self.name,
-            self.description
-        )
+        )?;
+
+        if let Some(version) = self.latest_version() {
+            write!(f, " {}", version.version)?;
+        }
+
+        write!(f, ": {}", self.description)?;
+
+        if !self.tags.is_empty() {
+            write!(f, " [{}]", self.tags.join(", "))?;
+        }
+
+        let mut statistics = Vec::new();
+
+        if let Some(stars) = self.stars {
+            statistics.push(format!("\u{2605} {}", stars));
+        }
+
+        if let Some(downloads) = self.downloads {
+            statistics.push(format!("{} downloads", downloads));
+        }
+
+        if let Some(updated_at) = &self.updated_at {
+            statistics.push(format!(
+                "updated {}",
+                updated_at.get(..10).unwrap_or(updated_at)
+            ));
+        }
+
+        if !statistics.is_empty() {
+            write!(f, " ({})", statistics.join(", "))?;
+        }
+
+        match &self.min_pi_version {
+            Some(min_pi_version) if !self.is_supported() => {
+                write!(f, " (requires pi {})", min_pi_version)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Order in which `pi list` and `pi search` show templates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    /// Most starred, then most downloaded, first
+    Popularity,
+    /// Most recently updated first
+    Updated,
+    /// Alphabetically by name
+    Name,
+}
+
+impl FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "popularity" => Ok(Self::Popularity),
+            "updated" => Ok(Self::Updated),
+            "name" => Ok(Self::Name),
+            _ => Err(format!(
+                "Unknown sort order {}, expected popularity, updated or name",
+                value
+            )),
+        }
     }
 }
 
-#[derive(Debug, Serialize)]
+/// Sort `entries` by `order`, entries missing the sorted field come last.
+pub fn sort_entries(entries: &mut [TemplateRepositoryEntry], order: SortOrder) {
+    match order {
+        SortOrder::Popularity => entries.sort_by(|left, right| {
+            (right.stars, right.downloads).cmp(&(left.stars, left.downloads))
+        }),
+        SortOrder::Updated => entries.sort_by(|left, right| right.updated_at.cmp(&left.updated_at)),
+        SortOrder::Name => {
+            entries.sort_by_key(|entry| entry.name.to_lowercase());
+        }
+    }
+}
+
+/// Contents of a templates repository file.
+///
+/// The first format is a bare list of entries, the second one wraps them in
+/// an object carrying the format's version, so it can evolve further.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum TemplateRepositoryIndex {
+    V1(Vec<TemplateRepositoryEntry>),
+    V2 {
+        version: u32,
+        templates: Vec<TemplateRepositoryEntry>,
+    },
+}
+
+impl TemplateRepositoryIndex {
+    fn into_entries(self) -> Vec<TemplateRepositoryEntry> {
+        match self {
+            Self::V1(entries) => entries,
+            Self::V2 { version, templates } => {
+                if version > 2 {
+                    warn!(
+                        "Templates repository format version {} is newer than this pi supports",
+                        version
+                    );
+                }
+
+                templates
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum TemplateRepository {
     Url(Url),
     Path(PathBuf),
@@ -103,25 +371,103 @@ impl Display for TemplateRepository {
     }
 }
 
-impl TemplateRepository {
-    fn deserialize<'de, D>(deserializer: D) -> Result<Option<Self>, D::Error>
+impl From<&str> for TemplateRepository {
+    fn from(value: &str) -> Self {
+        if let Ok(url) = value.parse::<Url>() {
+            Self::Url(url)
+        } else {
+            Self::Path(Path::new(value).to_path_buf())
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for TemplateRepository {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        let value = match Option::<&str>::deserialize(deserializer) {
-            Ok(Some(value)) => value,
-            Ok(None) => return Ok(None),
-            Err(error) => return Err(error),
-        };
+        String::deserialize(deserializer).map(|value| Self::from(value.as_str()))
+    }
+}
 
-        if let Ok(url) = value.parse::<Url>() {
-            Ok(Some(Self::Url(url)))
-        } else {
-            Ok(Some(Self::Path(Path::new(value).to_path_buf())))
-        }
+impl Serialize for TemplateRepository {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl TemplateRepository {
+    /// Read all the given templates repositories concurrently.
+    ///
+    /// Each repository gets at most `timeout` to answer, repositories that
+    /// are unreachable or too slow are reported and yield no entries.
+    pub async fn read_all<'a>(
+        repositories: &[&'a Self],
+        fetcher: &dyn HttpFetcher,
+        timeout: Duration,
+    ) -> Vec<(&'a Self, Vec<TemplateRepositoryEntry>)> {
+        join_all(repositories.iter().map(|repository| async move {
+            match tokio::time::timeout(timeout, repository.read(fetcher)).await {
+                Ok(entries) => (*repository, entries),
+                Err(_elapsed) => match repository.read_cached() {
+                    Some((entries, date)) => {
+                        warn!(
+                            "Template repository {} timed out, using cached version from {}",
+                            repository, date
+                        );
+
+                        (*repository, entries)
+                    }
+                    None => {
+                        warn!("Template repository {} timed out", repository);
+
+                        (*repository, Vec::new())
+                    }
+                },
+            }
+        }))
+        .await
+    }
+
+    /// Look the template `name` up in the given templates repositories,
+    /// returning the first entry with that name.
+    pub async fn find_entry(
+        repositories: &[&Self],
+        name: &str,
+        fetcher: &dyn HttpFetcher,
+        timeout: Duration,
+    ) -> Option<TemplateRepositoryEntry> {
+        Self::read_all(repositories, fetcher, timeout)
+            .await
+            .into_iter()
+            .flat_map(|(_, entries)| entries)
+            .find(|entry| entry.name == name)
     }
 
-    pub async fn read(&self) -> Vec<TemplateRepositoryEntry> {
+    /// Search the given templates repositories for the templates matching
+    /// `query` and tagged with every tag in `tags`, see
+    /// [`TemplateRepositoryEntry::matches`].
+    pub async fn search(
+        repositories: &[&Self],
+        query: &str,
+        tags: &[String],
+        fetcher: &dyn HttpFetcher,
+        timeout: Duration,
+    ) -> Vec<TemplateRepositoryEntry> {
+        Self::read_all(repositories, fetcher, timeout)
+            .await
+            .into_iter()
+            .flat_map(|(_, entries)| entries)
+            .filter(|entry| entry.has_tags(tags) && entry.matches(query))
+            .collect()
+    }
+
+    /// Read the entries of the templates repository, using `fetcher` to
+    /// access remote repositories.
+    pub async fn read(&self, fetcher: &dyn HttpFetcher) -> Vec<TemplateRepositoryEntry> {
         match self {
             Self::Path(path) => {
                 let file = match File::open(path) {
@@ -135,8 +481,8 @@ impl TemplateRepository {
 
                 let reader = BufReader::new(file);
 
-                match serde_json::from_reader(reader) {
-                    Ok(entries) => entries,
+                match serde_json::from_reader::<_, TemplateRepositoryIndex>(reader) {
+                    Ok(index) => index.into_entries(),
                     Err(error) => {
                         warn!("Template repository's format is invalid: {}", error);
 
@@ -145,17 +491,59 @@ impl TemplateRepository {
                 }
             }
             Self::Url(url) => {
-                let response = match reqwest::get(url.as_str()).await {
-                    Ok(response) => response,
-                    Err(_) => {
-                        warn!("Couldn't access remote template repository {}", url);
+                let validators = cached_index_validators(url);
+
+                let (bytes, validators) = match fetcher.get_conditional(url, &validators).await {
+                    Ok(Conditional::NotModified) => match self.read_cached() {
+                        Some((entries, _date)) => {
+                            if let Err(error) = touch_index(url) {
+                                warn!(
+                                    "Couldn't refresh cached template repository {}: {}",
+                                    url, error
+                                );
+                            }
+
+                            return entries;
+                        }
+                        // the cached copy can't be read anymore, download it again
+                        None => match fetcher.get(url).await {
+                            Ok(bytes) => (bytes, Validators::default()),
+                            Err(_) => {
+                                warn!("Couldn't access remote template repository {}", url);
+
+                                return Vec::new();
+                            }
+                        },
+                    },
+                    Ok(Conditional::Modified { body, validators }) => (body, validators),
+                    Err(error) => {
+                        if let Some((entries, date)) = self.read_cached() {
+                            warn!(
+                                "Couldn't access remote template repository {}: {}, using cached version from {}",
+                                url, error, date
+                            );
+
+                            return entries;
+                        }
+
+                        warn!(
+                            "Couldn't access remote template repository {}: {}",
+                            url, error
+                        );
 
                         return Vec::new();
                     }
                 };
 
-                match response.json().await {
-                    Ok(entries) => entries,
+                match serde_json::from_slice::<TemplateRepositoryIndex>(&bytes) {
+                    Ok(index) => {
+                        // remembered for when the repository can't be reached
+                        if let Err(error) = store_index(url, &bytes, &validators) {
+                            warn!("Couldn't cache template repository {}: {}", url, error);
+                        }
+
+                        index.into_entries()
+                    }
                     Err(error) => {
                         warn!("Template repository's format is invalid: {}", error);
 
@@ -165,23 +553,150 @@ impl TemplateRepository {
             }
         }
     }
+
+    /// Read the last copy of a remote templates repository kept in the
+    /// cache, along with the date it was cached.
+    pub fn read_cached(&self) -> Option<(Vec<TemplateRepositoryEntry>, String)> {
+        let url = match self {
+            Self::Url(url) => url,
+            Self::Path(_) => return None,
+        };
+
+        let (bytes, date) = cached_index(url)?;
+
+        let index = serde_json::from_slice::<TemplateRepositoryIndex>(&bytes).ok()?;
+
+        Some((index.into_entries(), date))
+    }
 }
 
 /// Struct for the global configuration at $HOME/.pi.toml
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub version_control: Option<VersionControl>,
     pub author: Option<Author>,
     pub license: Option<License>,
+    /// chrono format string of the `date` key
+    pub date_format: Option<String>,
+    /// Holder of the copyright of generated projects, such as a company,
+    /// defaults to the author's name
+    pub copyright_holder: Option<String>,
+    /// Command of the editor `--open` opens projects with
+    pub editor: Option<String>,
+    /// Unix permissions of generated files, 0o666 by default
+    pub file_mode: Option<u32>,
+    /// Unix permissions of generated scripts, 0o777 by default
+    pub script_mode: Option<u32>,
     /// Set of custom keys the user can set in their global configuration file
     pub custom_keys: Option<CustomKeys>,
     /// A path or url that points to a templates repository file,
     /// that is a json file listing all the available templates
-    #[serde(default, deserialize_with = "TemplateRepository::deserialize")]
     pub templates_repository: Option<TemplateRepository>,
+    /// Additional templates repositories, read alongside `templates_repository`
+    pub templates_repositories: Option<Vec<TemplateRepository>>,
+    /// Verification of the signatures of remote templates
+    pub signatures: Option<SignatureConfig>,
+    /// Checksums remote templates must match
+    pub pins: Option<Vec<Pin>>,
+    /// Url of the GitHub instance `pi git USER/REPO` fetches from, to use a
+    /// GitHub Enterprise Server
+    pub github_base_url: Option<Url>,
+    /// Token authenticating requests to GitHub, for higher rate limits and
+    /// access to private templates
+    pub github_token: Option<String>,
+    /// Certificates and verification of TLS connections
+    pub tls: Option<TlsConfig>,
+}
+
+/// Checksum the template of a repository, at a given rev, must match.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pin {
+    pub repository: Url,
+    pub rev: Option<String>,
+    /// Template of a repository holding several ones
+    pub template: Option<String>,
+    pub checksum: String,
+}
+
+/// Keys trusted to sign templates, and what to do with templates they didn't
+/// sign.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SignatureConfig {
+    /// Minisign public keys, base64 encoded
+    #[serde(default)]
+    pub trusted_keys: Vec<String>,
+    /// Refuse templates that aren't signed by a trusted key, instead of warning
+    #[serde(default)]
+    pub enforce: bool,
+}
+
+/// TLS options applied to HTTP requests and git transports, for networks
+/// intercepting TLS connections.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct TlsConfig {
+    /// PEM file with root certificates trusted in addition to the system's
+    pub ca_bundle: Option<PathBuf>,
+    /// Accept any certificate, for lab environments only
+    #[serde(default)]
+    pub insecure: bool,
 }
 
 impl Config {
+    /// Url of the GitHub instance templates are fetched from, the
+    /// `PI_GITHUB_BASE_URL` environment variable taking precedence over the
+    /// configuration.
+    pub fn github_url(&self) -> Url {
+        std::env::var(GITHUB_BASE_URL_VARIABLE)
+            .ok()
+            .and_then(|url| url.parse().ok())
+            .or_else(|| self.github_base_url.clone())
+            .unwrap_or_else(|| GITHUB_URL.clone())
+    }
+
+    /// Token authenticating requests to GitHub, the `GITHUB_TOKEN`
+    /// environment variable taking precedence over the configuration.
+    pub fn github_token(&self) -> Option<String> {
+        std::env::var(GITHUB_TOKEN_VARIABLE)
+            .ok()
+            .filter(|token| !token.is_empty())
+            .or_else(|| self.github_token.clone())
+    }
+
+    /// Returns the GitHub token if `url` points to the GitHub instance, so
+    /// that it is never sent anywhere else.
+    pub fn github_token_for(&self, url: &Url) -> Option<String> {
+        let github_hosts = [self.github_url(), self.github_api_url()];
+
+        if github_hosts
+            .iter()
+            .any(|github_url| github_url.host_str() == url.host_str())
+        {
+            self.github_token()
+        } else {
+            None
+        }
+    }
+
+    /// Url of the API of the GitHub instance templates are fetched from,
+    /// served under `/api/v3/` by GitHub Enterprise Server.
+    pub fn github_api_url(&self) -> Url {
+        let github_url = self.github_url();
+
+        if github_url.host_str() == GITHUB_URL.host_str() {
+            return GITHUB_API_URL.clone();
+        }
+
+        github_url.join("api/v3/").unwrap_or(github_url)
+    }
+
+    /// Returns every templates repository declared in the configuration.
+    pub fn all_templates_repositories(&self) -> Vec<&TemplateRepository> {
+        self.templates_repository
+            .iter()
+            .chain(self.templates_repositories.iter().flatten())
+            .collect()
+    }
+
     /// Given a `Path`, read the .toml file there as a configuration file.
     pub fn from_path<P: AsRef<Path>>(config_path: P) -> Self {
         let mut config_file = match File::open(&config_path) {
@@ -207,19 +722,26 @@ impl Config {
             std::process::exit(1);
         };
 
-        match toml::from_str(&toml_str) {
+        match Self::parse(config_path.as_ref(), &toml_str) {
             Ok(config) => config,
-            Err(error) => {
+            Err(diagnostic) => {
                 warn!(
-                    "File {} was not properly formatted: {}",
-                    config_path.as_ref().to_string_lossy(),
-                    error
+                    "File {} was not properly formatted",
+                    config_path.as_ref().to_string_lossy()
                 );
 
+                eprint!("{}", diagnostic);
+
                 std::process::exit(1);
             }
         }
     }
+
+    /// Parse the configuration `content`, read from `path`. Errors are
+    /// rendered as diagnostics pointing at the problem.
+    pub fn parse(path: &Path, content: &str) -> Result<Self, String> {
+        toml::from_str(content).map_err(|error| toml_diagnostic(path, content, &error))
+    }
 }
 
 /// Struct for directories, files, templates, and scripts to be created.
@@ -229,6 +751,31 @@ pub struct Directory {
     pub directories: Option<Vec<PathBuf>>,
     pub templates: Option<Vec<PathBuf>>,
     pub scripts: Option<Vec<PathBuf>>,
+    /// Files copied as they are, without rendering their content: images,
+    /// fixtures, archives and the like
+    pub assets: Option<Vec<PathBuf>>,
+    /// Paths, among the ones above, only readable and writable by their
+    /// owner, like files holding secrets
+    pub private: Option<Vec<PathBuf>>,
+    /// Scripts only generated on the operating system, as named by Rust
+    /// (`linux`, `macos`, `windows`...), or the family of operating systems
+    /// (`unix`, `windows`) they are listed under
+    pub os_scripts: Option<BTreeMap<String, Vec<PathBuf>>>,
+    /// Paths, among the ones above, only generated when the key they are
+    /// listed under is set and neither false nor empty
+    pub conditional: Option<BTreeMap<String, Vec<PathBuf>>>,
+}
+
+impl Directory {
+    /// Scripts listed under `os_scripts` for the operating system pi runs on.
+    pub fn current_os_scripts(&self) -> Vec<PathBuf> {
+        self.os_scripts
+            .iter()
+            .flatten()
+            .filter(|(os, _)| *os == std::env::consts::OS || *os == std::env::consts::FAMILY)
+            .flat_map(|(_, scripts)| scripts.iter().cloned())
+            .collect()
+    }
 }
 
 /// Struct for project-specific configuration options
@@ -238,16 +785,46 @@ pub struct ProjectConfig {
     pub version: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+/// License of a generated project, the builtin ones or one whose text is in
+/// `$HOME/.pi_templates/licenses/`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
 pub enum License {
     Bsd3,
     Bsd,
     Gpl3,
     Mit,
     AllRightsReserved,
-    #[serde(other)]
-    Unknown,
+    /// Any other license, by the name of its file in the licenses directory
+    Custom(String),
+}
+
+impl From<String> for License {
+    fn from(name: String) -> Self {
+        match name.as_str() {
+            "BSD3" => License::Bsd3,
+            "BSD" => License::Bsd,
+            "GPL3" => License::Gpl3,
+            "MIT" => License::Mit,
+            "ALL_RIGHTS_RESERVED" => License::AllRightsReserved,
+            _ => License::Custom(name),
+        }
+    }
+}
+
+impl From<License> for String {
+    /// Name of the license in configuration files, which is also the name of
+    /// its file in the licenses directory.
+    fn from(license: License) -> Self {
+        match license {
+            License::Bsd3 => "BSD3".to_string(),
+            License::Bsd => "BSD".to_string(),
+            License::Gpl3 => "GPL3".to_string(),
+            License::Mit => "MIT".to_string(),
+            License::AllRightsReserved => "ALL_RIGHTS_RESERVED".to_string(),
+            License::Custom(name) => name,
+        }
+    }
 }
 
 impl Display for License {
@@ -258,83 +835,228 @@ impl Display for License {
             License::Gpl3 => write!(f, "GPL3"),
             License::Mit => write!(f, "MIT"),
             License::AllRightsReserved => write!(f, "All Rights Reserved"),
-            License::Unknown => write!(f, "Unknown License"),
+            License::Custom(name) => write!(f, "{}", name),
         }
     }
 }
 
+/// Description of a template, used by `pi publish`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TemplateMetadata {
+    /// Name of the template, defaults to the name of its directory
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// Where the template can be fetched from, defaults to the url of the
+    /// template's `origin` git remote
+    pub repository: Option<Url>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Main programming language of generated projects
+    pub language: Option<String>,
+    /// Version of the template itself, recorded by `pi pack`
+    pub version: Option<String>,
+}
+
+impl TemplateMetadata {
+    /// Reads the `[template]` table of the template in `directory`, see
+    /// [`TemplateMetadata::from_path`].
+    pub fn from_directory<P: AsRef<Path>>(directory: P) -> Option<Self> {
+        Self::from_path(template_file(directory)?)
+    }
+
+    /// Reads the `[template]` table of the template description file at
+    /// `path`, without validating the rest of the template.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let content = std::fs::read_to_string(&path).ok()?;
+
+        parse_template_file::<Value>(path.as_ref(), &content)
+            .ok()?
+            .get("template")?
+            .clone()
+            .try_into()
+            .ok()
+    }
+}
+
 /// Struct for a project
 #[derive(Debug, Deserialize)]
 pub struct Project {
     pub license: Option<License>,
     #[serde(default)]
     pub with_readme: bool,
+    /// chrono format string of the `date` key, overriding the configuration's
+    pub date_format: Option<String>,
+    /// Holder of the copyright, overriding the configuration's
+    pub copyright_holder: Option<String>,
+    /// First year of the copyright, when the project started before the year
+    /// it is generated
+    pub copyright_start_year: Option<i32>,
     // TODO: Rename to directories, or rename `Directory` to `File`?
     pub files: Directory,
     pub config: Option<ProjectConfig>,
     pub custom_keys: Option<CustomKeys>,
+    /// Path to a rhai script, relative to the template directory, whose
+    /// returned map is merged into the keys before rendering
+    pub script: Option<PathBuf>,
+    /// Information describing the template in templates repositories
+    pub template: Option<TemplateMetadata>,
+    /// Templates rendered into the project before this one, see
+    /// [`crate::dependencies`]
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    /// Keys never written in answers files
+    #[serde(default)]
+    pub secrets: Vec<String>,
+    /// Naming rules the project name must follow
+    #[serde(default)]
+    pub name_rules: Vec<NameRule>,
+    /// Variables asked for when generating a project, see
+    /// [`crate::variables`]
+    #[serde(default)]
+    pub variables: BTreeMap<String, Variable>,
+    /// Tools the generated project needs, see [`crate::requirements`]
+    #[serde(default)]
+    pub requires: Vec<String>,
+    /// How to install required tools, by tool
+    #[serde(default)]
+    pub hints: BTreeMap<String, String>,
+    /// File `--open` opens in the editor, relative to the project
+    pub entry: Option<String>,
+    /// Commands run in the project once generated, see [`crate::hooks`]
+    #[serde(default)]
+    pub hooks: Vec<String>,
+    /// Unix permissions of generated files, overriding the configuration's
+    pub file_mode: Option<u32>,
+    /// Unix permissions of generated scripts, overriding the configuration's
+    pub script_mode: Option<u32>,
+    /// Subprojects generated along with the project, see
+    /// [`crate::workspace`]
+    pub workspace: Option<Workspace>,
     // Set manually
     #[serde(skip)]
     pub path: PathBuf,
 }
 
 impl Project {
-    /// Given a filepath, read the .toml file there as containing the
-    /// directories/templates.
-    /// If no such file is found, read from global template directory in
-    /// `$HOME/.pi_templates/`.
-    pub fn from_path<D: AsRef<Path>, H: AsRef<Path>>(home: H, directory: D) -> Self {
-        let template_path = directory.as_ref().join(TEMPLATE_FILENAME);
+    /// Name of the template, as set in its `[template]` table or else the
+    /// name of its directory.
+    pub fn template_name(&self) -> Option<String> {
+        if let Some(name) = self
+            .template
+            .as_ref()
+            .and_then(|metadata| metadata.name.clone())
+        {
+            return Some(name);
+        }
 
-        let (mut template_file, path) = match File::open(&template_path) {
-            Ok(file) => (file, directory.as_ref().to_path_buf()),
-            Err(_) => {
-                let global_directory = home
-                    .as_ref()
-                    .join(GLOBAL_TEMPLATE_DIRECTORY)
-                    .join(&directory);
-
-                let global_template_path = global_directory.join(TEMPLATE_FILENAME);
-
-                match File::open(&global_template_path) {
-                    Ok(file) => (file, global_directory),
-                    Err(_) => {
-                        error!(
-                            "File {:?} could not be opened, does it exist?",
-                            global_template_path
-                        );
+        self.path
+            .canonicalize()
+            .ok()?
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+    }
 
-                        std::process::exit(0x0f00);
-                    }
-                }
+    /// Returns the directory containing the template `directory`, either
+    /// relative to the current directory or in the global template directory
+    /// in `$HOME/.pi_templates/`.
+    pub fn locate<D: AsRef<Path>, H: AsRef<Path>>(home: H, directory: D) -> PathBuf {
+        match Self::find(&home, &directory) {
+            Some(path) => path,
+            None => {
+                error!(
+                    "File {:?} could not be opened, does it exist?",
+                    home.as_ref()
+                        .join(GLOBAL_TEMPLATE_DIRECTORY)
+                        .join(&directory)
+                        .join(TEMPLATE_FILENAME)
+                );
+
+                std::process::exit(0x0f00);
             }
-        };
+        }
+    }
 
-        let mut template = String::new();
+    /// Like [`Project::locate`], but returns `None` when there is no such
+    /// template.
+    pub fn find<D: AsRef<Path>, H: AsRef<Path>>(home: H, directory: D) -> Option<PathBuf> {
+        if is_template(&directory) {
+            return Some(directory.as_ref().to_path_buf());
+        }
 
-        if template_file.read_to_string(&mut template).is_err() {
-            error!("Couldn't read content of file {:?}", path);
+        let global_directory = home
+            .as_ref()
+            .join(GLOBAL_TEMPLATE_DIRECTORY)
+            .join(&directory);
 
-            std::process::exit(0x0f00);
-        }
+        is_template(&global_directory).then_some(global_directory)
+    }
+
+    /// Returns the templates installed in `$HOME/.pi_templates/`, sorted by
+    /// name.
+    pub fn installed<H: AsRef<Path>>(home: H) -> std::io::Result<Vec<PathBuf>> {
+        let mut templates = std::fs::read_dir(home.as_ref().join(GLOBAL_TEMPLATE_DIRECTORY))?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| is_template(path))
+            .collect::<Vec<_>>();
+
+        templates.sort();
 
-        let mut project: Self = match toml::from_str(&template) {
+        Ok(templates)
+    }
+
+    /// Given a filepath, read the .toml file there as containing the
+    /// directories/templates.
+    /// If no such file is found, read from global template directory in
+    /// `$HOME/.pi_templates/`.
+    pub fn from_path<D: AsRef<Path>, H: AsRef<Path>>(home: H, directory: D) -> Self {
+        match Self::read(home, directory) {
             Ok(project) => project,
             Err(error) => {
-                error!("Error parsing {:?}: {}", directory.as_ref(), error);
+                error!("{}", error);
 
                 std::process::exit(0x0f00);
             }
-        };
+        }
+    }
+
+    /// Like [`Project::from_path`], but fails with a report instead of
+    /// exiting when the template can't be found or read.
+    pub fn read<D: AsRef<Path>, H: AsRef<Path>>(home: H, directory: D) -> Result<Self, String> {
+        let path = Self::find(home, &directory).ok_or_else(|| {
+            format!(
+                "No template found in {}",
+                directory.as_ref().to_string_lossy()
+            )
+        })?;
+
+        let file = template_file(&path).unwrap_or_else(|| path.join(TEMPLATE_FILENAME));
+
+        let mut template_file = File::open(&file)
+            .map_err(|_| format!("File {:?} could not be opened, does it exist?", file))?;
+
+        let mut template = String::new();
+
+        template_file
+            .read_to_string(&mut template)
+            .map_err(|_| format!("Couldn't read content of file {:?}", file))?;
+
+        let mut project: Self = parse_template_file(&file, &template).map_err(|diagnostic| {
+            format!(
+                "Error parsing {:?}\n{}",
+                directory.as_ref(),
+                diagnostic.trim_end()
+            )
+        })?;
 
         project.path = path;
 
-        project
+        Ok(project)
     }
 }
 
 /// Struct for custom user keys
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CustomKeys {
     pub toml: Value,
 }
@@ -0,0 +1,305 @@
+//! `pi upgrade`, applying a newer version of its template to a generated
+//! project.
+//!
+//! The template is rendered again with the answers recorded in the
+//! project's manifest, and the checksums the manifest recorded tell the
+//! files modified in the project since it was generated from the ones only
+//! the template changed. The former are conflicts, left to the user.
+
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::dependencies::Dependency;
+use crate::diff::unified_diff;
+use crate::fetch::FetchedTemplate;
+use crate::http::HttpFetcher;
+use crate::manifest::{checksum, file_checksum, Manifest, TemplateSource};
+use crate::plan::Plan;
+use crate::prompt::choose;
+use crate::provenance::Provenance;
+use crate::types::Config;
+use crate::verify::{rerender_from, Rerendered};
+
+/// A change the upgrade brings to a project.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Change {
+    /// The template added the file
+    Created(PathBuf),
+    /// The template changed the file, which wasn't modified in the project
+    Updated(PathBuf),
+    /// The template changed the file, which was modified in the project too
+    Conflict(PathBuf),
+    /// The template doesn't generate the file anymore, which wasn't modified
+    /// in the project
+    Removed(PathBuf),
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Created(path) => write!(f, "created:  {}", path.to_string_lossy()),
+            Self::Updated(path) => write!(f, "updated:  {}", path.to_string_lossy()),
+            Self::Conflict(path) => write!(f, "conflict: {}", path.to_string_lossy()),
+            Self::Removed(path) => write!(f, "removed:  {}", path.to_string_lossy()),
+        }
+    }
+}
+
+/// A project rendered again from its template, see [`plan_upgrade`].
+#[derive(Debug)]
+pub struct Upgrade {
+    /// Manifest of the project once upgraded
+    pub manifest: Manifest,
+    /// Provenance of the project once upgraded
+    pub provenance: Provenance,
+    /// Files of the template as rendered now
+    pub plan: Plan,
+    pub changes: Vec<Change>,
+    /// Manifest of the project as generated before
+    previous: Manifest,
+    /// Kept for the assets of `plan` to be readable
+    template: FetchedTemplate,
+    _dependencies: Vec<Dependency>,
+}
+
+impl Upgrade {
+    /// Paths of the conflicting files.
+    pub fn conflicts(&self) -> Vec<&Path> {
+        self.changes
+            .iter()
+            .filter_map(|change| match change {
+                Change::Conflict(path) => Some(path.as_path()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Directory of the template the project is upgraded to.
+    pub fn template_path(&self) -> &Path {
+        self.template.path()
+    }
+
+    /// Ask which conflicting files to overwrite with the version of the
+    /// template, showing their diff on demand. Nothing is overwritten when
+    /// pi isn't interactive.
+    pub fn resolve_conflicts<P: AsRef<Path>>(&self, project_dir: P) -> Vec<PathBuf> {
+        let choices = [
+            ('o', "overwrite"),
+            ('s', "keep yours"),
+            ('d', "show diff"),
+            ('a', "overwrite all"),
+        ];
+
+        let conflicts = self.conflicts();
+
+        let mut overwrite = Vec::new();
+
+        for (index, path) in conflicts.iter().enumerate() {
+            let question = format!(
+                "{} was modified, and the template changed it too, overwrite it?",
+                path.to_string_lossy()
+            );
+
+            loop {
+                match choose(&question, &choices) {
+                    Some('o') => {
+                        overwrite.push(path.to_path_buf());
+
+                        break;
+                    }
+                    Some('d') => {
+                        let existing =
+                            fs::read(project_dir.as_ref().join(path)).unwrap_or_default();
+
+                        if let Some(file) = self.plan.file(path) {
+                            print!(
+                                "{}",
+                                unified_diff(path, &existing, &file.bytes().unwrap_or_default())
+                            );
+                        }
+                    }
+                    Some('a') => {
+                        overwrite.extend(conflicts[index..].iter().map(|path| path.to_path_buf()));
+
+                        return overwrite;
+                    }
+                    _ => break,
+                }
+            }
+        }
+
+        overwrite
+    }
+}
+
+/// Render the template of the project located in `project_dir` again, at
+/// `rev` if given, with the answers recorded in its manifest, and compare it
+/// with the project and with the files it was generated with.
+///
+/// Files deleted from the project are left deleted, and files the template
+/// doesn't generate anymore are only removed if they weren't modified.
+pub async fn plan_upgrade<H: AsRef<Path>, P: AsRef<Path>>(
+    home: H,
+    project_dir: P,
+    rev: Option<String>,
+    config: &Config,
+    fetcher: &dyn HttpFetcher,
+) -> Result<Upgrade, Box<dyn std::error::Error>> {
+    let project_dir = project_dir.as_ref();
+
+    let manifest = Manifest::from_project(project_dir)?;
+
+    let source = match (manifest.source.clone(), rev) {
+        (TemplateSource::Git { url, template, .. }, Some(rev)) => TemplateSource::Git {
+            url,
+            rev: Some(rev),
+            template,
+        },
+        (source, Some(_)) => {
+            return Err(format!("Only git templates have revisions, not {}", source).into())
+        }
+        (source, None) => source,
+    };
+
+    let rerendered = rerender_from(home, project_dir, &manifest, &source, config, fetcher).await?;
+
+    let secrets = rerendered.secrets();
+
+    let Rerendered {
+        plan,
+        project,
+        template,
+        dependencies,
+    } = rerendered;
+
+    let mut changes = Vec::new();
+
+    for file in &plan.files {
+        let current = project_dir.join(&file.path);
+
+        let generated = manifest
+            .files
+            .iter()
+            .find(|generated| generated.path == file.path)
+            .map(|generated| generated.checksum.as_str());
+
+        let planned = checksum(&file.bytes()?);
+
+        match (file_checksum(&current).ok(), generated) {
+            // deleted from the project since
+            (None, Some(_)) => {}
+            (None, None) => changes.push(Change::Created(file.path.clone())),
+            (Some(current), _) if current == planned => {}
+            (Some(current), Some(generated)) if current == generated => {
+                changes.push(Change::Updated(file.path.clone()))
+            }
+            // modified in the project, but the template didn't change
+            (Some(_), Some(generated)) if generated == planned => {}
+            (Some(_), _) => changes.push(Change::Conflict(file.path.clone())),
+        }
+    }
+
+    for generated in &manifest.files {
+        if plan.file(&generated.path).is_some() {
+            continue;
+        }
+
+        let current = file_checksum(project_dir.join(&generated.path)).ok();
+
+        if current.as_deref() == Some(generated.checksum.as_str()) {
+            changes.push(Change::Removed(generated.path.clone()));
+        }
+    }
+
+    changes.sort();
+
+    let provenance = Provenance::new(
+        project.template_name(),
+        source.clone(),
+        template.commit().map(str::to_string),
+    );
+
+    Ok(Upgrade {
+        manifest: Manifest::new(&manifest.name, source, &plan, &secrets),
+        provenance,
+        plan,
+        changes,
+        previous: manifest,
+        template,
+        _dependencies: dependencies,
+    })
+}
+
+/// Apply `upgrade` to the project located in `project_dir`, overwriting the
+/// conflicting files listed in `overwrite` and keeping the others.
+///
+/// The manifest keeps the checksums the kept files were generated with, so
+/// that they are still conflicts on the next upgrade.
+pub fn apply_upgrade<P: AsRef<Path>>(
+    project_dir: P,
+    upgrade: Upgrade,
+    overwrite: &[PathBuf],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let project_dir = project_dir.as_ref();
+
+    let Upgrade {
+        mut manifest,
+        provenance,
+        mut plan,
+        changes,
+        previous,
+        ..
+    } = upgrade;
+
+    let kept = |path: &PathBuf| {
+        changes.contains(&Change::Conflict(path.clone())) && !overwrite.contains(path)
+    };
+
+    // kept files pi didn't generate before aren't recorded either
+    manifest.files.retain_mut(|file| {
+        if !kept(&file.path) {
+            return true;
+        }
+
+        match previous
+            .files
+            .iter()
+            .find(|generated| generated.path == file.path)
+        {
+            Some(generated) => {
+                file.checksum = generated.checksum.clone();
+
+                true
+            }
+            None => false,
+        }
+    });
+
+    let written = changes
+        .iter()
+        .filter_map(|change| match change {
+            Change::Created(path) | Change::Updated(path) => Some(path),
+            Change::Conflict(path) if overwrite.contains(path) => Some(path),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    plan.files.retain(|file| written.contains(&&file.path));
+
+    plan.write(project_dir)?;
+
+    for change in &changes {
+        if let Change::Removed(path) = change {
+            fs::remove_file(project_dir.join(path))?;
+        }
+    }
+
+    manifest.write(project_dir)?;
+
+    if Provenance::path(project_dir).is_file() {
+        provenance.write(project_dir)?;
+    }
+
+    Ok(())
+}
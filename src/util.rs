@@ -2,77 +2,974 @@
 //! binary. They are included
 //! here in the hopes that they can be illuminating to users.
 
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::env;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use case::*;
-use chrono::{Datelike, Utc};
-use heck::ToUpperCamelCase;
-use rustache::{HashBuilder, VecBuilder};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Datelike, LocalResult, TimeZone, Utc};
+use heck::{ToKebabCase, ToShoutySnakeCase, ToSnakeCase, ToUpperCamelCase};
+use rustache::{Data, HashBuilder, VecBuilder};
 use toml::Value::Table;
-use tracing::{error, warn};
+use tracing::{info, warn};
 
+use crate::answers::{is_secret, write_answers};
+use crate::constants::{
+    ANSWERS_FILENAME, DEFAULT_DATE_FORMAT, GLOBAL_TEMPLATE_DIRECTORY, LICENSES_DIRECTORY,
+    LOCKFILE_FILENAME, SOURCE_DATE_EPOCH_VARIABLE,
+};
+use crate::dependencies::Dependency;
+use crate::diff::unified_diff;
+use crate::editorconfig::apply_editorconfig;
+use crate::hooks::{render_hook, run_hooks};
 use crate::includes;
-use crate::render::{render_dirs, render_file, render_files, render_templates};
+use crate::lockfile::{LockedTemplate, Lockfile};
+use crate::manifest::{Manifest, TemplateSource};
+use crate::names::validate_name;
+use crate::plan::{FileKind, Permissions, Plan, PlannedFile};
+use crate::prompt::{choose, confirm, input, is_interactive, multi_select};
+use crate::provenance::Provenance;
+use crate::render::{
+    checked_template, file_names, is_truthy, placeholders, render_assets, render_dirs, render_file,
+    render_files, render_path, render_templates, unresolved_keys,
+};
 use crate::repo::{darcs_init, git_init, hg_init, pijul_init};
-use crate::types::{Author, Config, License, Project, ProjectConfig, VersionControl};
+use crate::requirements::requirements;
+use crate::script::run_script;
+use crate::types::{Author, Config, CustomKeys, License, Project, ProjectConfig, VersionControl};
+use crate::variables::{prompt_order, selection, Variable};
+use crate::workspace::{member_list, plan_members};
+
+/// Options controlling how [`init_helper`] generates a project.
+#[derive(Debug, Default)]
+pub struct InitOptions {
+    /// Directory the project is generated into.
+    pub output: PathBuf,
+    /// Initialize the project even if the output directory already exists.
+    pub force: bool,
+    /// Generate into an existing output directory, only writing the files
+    /// missing from it.
+    pub merge: bool,
+    /// Keys taking precedence over every other source of keys.
+    pub keys: Vec<(String, String)>,
+    /// Where the template comes from, defaults to the template's directory.
+    pub source: Option<TemplateSource>,
+    /// Commit the template was fetched at, recorded in the project's
+    /// [`Provenance`].
+    pub commit: Option<String>,
+    /// Record a [`Manifest`] in the generated project.
+    pub manifest: bool,
+    /// Write a [`Lockfile`] pinning this template in the generated project.
+    pub lock: Option<LockedTemplate>,
+    /// Templates rendered before this one, see [`crate::dependencies`].
+    pub dependencies: Vec<Dependency>,
+    /// Ask for the value of placeholders no key resolves.
+    pub prompt: bool,
+    /// Write the answers, secrets excluded, in the generated project.
+    pub save_answers: bool,
+    /// Print the keys of the templates, with where their value comes from.
+    pub verbose: bool,
+    /// Only render the project in memory, see [`describe_generation`].
+    pub dry_run: bool,
+    /// Version control to initialize the project with, over the template's
+    /// and the configuration's.
+    pub version_control: Option<VersionControl>,
+    /// Don't initialize version control, whatever the template and the
+    /// configuration ask for.
+    pub no_version_control: bool,
+    /// Run the hooks of the templates once the project is written, see
+    /// [`crate::hooks`].
+    pub run_hooks: bool,
+    /// Run the hooks of remote templates without asking first, see
+    /// [`hooks_trusted`].
+    pub trust_hooks: bool,
+}
 
 /// Main orchestrator function.
 ///
-/// Takes the name (which is also for the moment the output dir) of the project,
+/// Takes the name of the project,
 /// the global [`Config`] struct (as parsed from the `$HOME/.pi.toml` file),
 /// the [`Project`] struct (as parsed from the project's `template.toml` file),
-/// and a `force` argument.
+/// and the [`InitOptions`] (output directory, `force`, overriding keys...).
 ///
 /// It will automatically call the proper render functions, create the required
-/// files and directories and populate them.
+/// files and directories and populate them. Returns the plan of what was
+/// written.
 pub fn init_helper(
     name: &str,
-    config: Config,
-    project: Project,
-    force: bool,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let now = Utc::now();
+    config: &Config,
+    project: &Project,
+    options: &InitOptions,
+) -> Result<Plan, Box<dyn std::error::Error>> {
+    let output = options.output.as_path();
 
-    let year = now.year();
+    // answers given for a dependency also apply to the templates after it
+    let mut keys = options.keys.clone();
 
-    let formatted_date = format!(
-        "{month}-{day}-{year}",
-        month = now.month0(),
-        day = now.day0(),
-        year = year
-    );
+    let mut plan = plan_generation(name, config, project, options, &mut keys)?;
 
-    let project_files = project.files;
+    let merging = options.merge && output.exists();
 
-    let project_config = project.config;
+    if merging {
+        let existing = plan.existing(output);
 
-    // prefer project-specific license over global
-    let license = project.license.or(config.license);
+        for path in existing {
+            info!(
+                "Skipping {}, already in the project",
+                path.to_string_lossy()
+            );
+
+            plan.skip(path);
+        }
+    }
+
+    if options.dry_run {
+        return Ok(plan);
+    }
+
+    // check if the directory exists and exit, if we haven't forced an overwrite
+    // and can't ask which files to overwrite.
+    if output.exists() && !options.force && !merging {
+        if !is_interactive() {
+            return Err(format!(
+                "Path '{}' already exists, rerun with -f or --force to overwrite",
+                output.to_string_lossy()
+            )
+            .into());
+        }
+
+        resolve_conflicts(&mut plan, output);
+    };
+
+    plan.write(output)?;
+
+    // pi's own files are only written if missing too when merging
+    let missing = |path: PathBuf| !merging || !path.exists();
+
+    let templates = options
+        .dependencies
+        .iter()
+        .map(|dependency| &dependency.project)
+        .chain([project]);
+
+    let secrets = templates
+        .clone()
+        .flat_map(|template| template.secrets.iter().cloned())
+        .collect::<Vec<_>>();
+
+    if options.manifest && missing(Manifest::path(output)) {
+        let source = options
+            .source
+            .clone()
+            .unwrap_or_else(|| TemplateSource::from_path(&project.path));
+
+        Manifest::new(name, source, &plan, &secrets).write(output)?;
+    }
+
+    if options.manifest && missing(Provenance::path(output)) {
+        let source = options
+            .source
+            .clone()
+            .unwrap_or_else(|| TemplateSource::from_path(&project.path));
+
+        Provenance::new(project.template_name(), source, options.commit.clone()).write(output)?;
+    }
+
+    if let Some(template) = options
+        .lock
+        .as_ref()
+        .filter(|_| missing(output.join(LOCKFILE_FILENAME)))
+    {
+        Lockfile::new(name, template.clone(), &plan, &secrets).write(output)?;
+    }
+
+    if options.save_answers && missing(output.join(ANSWERS_FILENAME)) {
+        let mut variables = keys.iter().map(|(key, _)| key.clone()).collect::<Vec<_>>();
+
+        for template in templates {
+            variables.extend(custom_key_names(template));
+        }
+
+        let answers = plan
+            .keys
+            .iter()
+            .filter(|(key, _)| variables.contains(key) && !is_secret(key, &secrets))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+
+        write_answers(output, &answers)?;
+    }
+
+    // initialize version control, the existing directory keeps its own
+    if let Some(version_control) = version_control(config, project, options).filter(|_| !merging) {
+        match version_control {
+            VersionControl::Git => git_init(output)?,
+            VersionControl::Hg | VersionControl::Mercurial => hg_init(output)?,
+            VersionControl::Pijul => pijul_init(output)?,
+            VersionControl::Darcs => darcs_init(output)?,
+            VersionControl::Unknown => warn!("Version control not yet supported, supported version control tools are git, darcs, pijul, and mercurial, ignoring...")
+        }
+    }
+
+    // hooks act on a new project, like version control
+    if options.run_hooks && !merging && !plan.hooks.is_empty() && hooks_trusted(options, &plan) {
+        run_hooks(&plan.hooks, output)?;
+    }
+
+    Ok(plan)
+}
+
+/// Returns `true` if some of the templates of `options` don't come from the
+/// local filesystem.
+fn remote_templates(options: &InitOptions) -> bool {
+    options
+        .source
+        .iter()
+        .chain(
+            options
+                .dependencies
+                .iter()
+                .map(|dependency| &dependency.source),
+        )
+        .any(|source| !matches!(source, TemplateSource::Path { .. }))
+}
+
+/// Whether the hooks of `plan` may run: those of local templates always do,
+/// those of remote ones only when trusted, or once the user saw them and
+/// accepted to run them.
+fn hooks_trusted(options: &InitOptions, plan: &Plan) -> bool {
+    if options.trust_hooks || !remote_templates(options) {
+        return true;
+    }
+
+    if !is_interactive() {
+        warn!("Skipping the hooks of the template, pass --trust-hooks to run them");
+
+        return false;
+    }
+
+    println!("The template runs these commands in the project:");
+
+    for hook in &plan.hooks {
+        println!("  {}", hook);
+    }
+
+    confirm("Run them?", false)
+}
+
+/// Version control the project generated from `project` is initialized with,
+/// the one of `options` taking precedence over the template's, and the
+/// template's over the configuration's.
+fn version_control(
+    config: &Config,
+    project: &Project,
+    options: &InitOptions,
+) -> Option<VersionControl> {
+    if options.no_version_control {
+        return None;
+    }
+
+    options
+        .version_control
+        .clone()
+        .or_else(|| {
+            project
+                .config
+                .as_ref()
+                .and_then(|project_config| project_config.version_control.clone())
+        })
+        .or_else(|| config.version_control.clone())
+}
 
-    // set license if it's set
-    let license_contents =
-        // prefer project-specific license over global
-        match license {
-            None => {
-                warn!("Requested license not specified, license file not generated");
+/// Describe what [`init_helper`] would do with the `plan` it returned for
+/// `options.dry_run`: the files it would write, the existing ones it would
+/// overwrite, and the version control it would initialize.
+pub fn describe_generation(
+    config: &Config,
+    project: &Project,
+    options: &InitOptions,
+    plan: &Plan,
+) -> String {
+    let output = options.output.as_path();
 
-                None
-            },
-            Some(License::Unknown) => {
-                warn!("Unknown requested license, license file not generated");
+    let merging = options.merge && output.exists();
 
-                None
+    let mut description = format!(
+        "Would create {} in {}\n",
+        plan.stats(),
+        output.to_string_lossy()
+    );
+
+    description.push_str(&plan.tree(&output.to_string_lossy()));
+
+    if output.exists() && !merging {
+        let conflicts = plan.conflicts(output);
+
+        if options.force {
+            for path in conflicts {
+                description.push_str(&format!("Would overwrite {}\n", path.to_string_lossy()));
+            }
+        } else if is_interactive() {
+            for path in conflicts {
+                description.push_str(&format!(
+                    "Would ask whether to overwrite {}\n",
+                    path.to_string_lossy()
+                ));
             }
-            Some(License::Bsd3) => Some(includes::BSD3),
-            Some(License::Bsd) => Some(includes::BSD),
-            Some(License::Mit) => Some(includes::MIT),
-            Some(License::Gpl3) => Some(includes::GPL3),
-            Some(License::AllRightsReserved) => Some(includes::ALL_RIGHTS_RESERVED),
+        } else {
+            description.push_str(&format!(
+                "Would stop, {} already exists\n",
+                output.to_string_lossy()
+            ));
+
+            return description;
+        }
+    }
+
+    let missing = |path: &Path| !merging || !output.join(path).exists();
+
+    let records = [
+        (options.manifest, Manifest::path("")),
+        (options.manifest, Provenance::path("")),
+        (options.lock.is_some(), PathBuf::from(LOCKFILE_FILENAME)),
+        (options.save_answers, PathBuf::from(ANSWERS_FILENAME)),
+    ];
+
+    for (_, path) in records
+        .iter()
+        .filter(|(enabled, path)| *enabled && missing(path))
+    {
+        description.push_str(&format!("Would record {}\n", path.to_string_lossy()));
+    }
+
+    match version_control(config, project, options).filter(|_| !merging) {
+        Some(VersionControl::Unknown) | None => {}
+        Some(version_control) => description.push_str(&format!(
+            "Would initialize a {} repository\n",
+            version_control
+        )),
+    }
+
+    if options.run_hooks && !merging {
+        let action = if options.trust_hooks || !remote_templates(options) {
+            "Would run"
+        } else if is_interactive() {
+            "Would ask whether to run"
+        } else {
+            "Would skip"
         };
 
+        for hook in &plan.hooks {
+            description.push_str(&format!("{} {}\n", action, hook));
+        }
+    }
+
+    description
+}
+
+/// Render `project` and the dependencies of `options` in memory, asking for
+/// the keys they need if `options.prompt` is set.
+///
+/// `keys` take precedence over every other key, the answers are added to
+/// them.
+pub fn plan_generation(
+    name: &str,
+    config: &Config,
+    project: &Project,
+    options: &InitOptions,
+    keys: &mut Vec<(String, String)>,
+) -> Result<Plan, Box<dyn std::error::Error>> {
+    let mut plan = Plan::default();
+
+    for dependency in &options.dependencies {
+        let (hash, sources) =
+            resolve_keys(name, config, &dependency.project, options.prompt, keys)?;
+
+        if options.verbose {
+            println!("Keys of {}:", dependency.source);
+            print!("{}", keys_table(&hash, &sources));
+        }
+
+        plan.merge(plan_with_keys(config, &dependency.project, hash)?);
+    }
+
+    let (hash, sources) = resolve_keys(name, config, project, options.prompt, keys)?;
+
+    if options.verbose {
+        print!("{}", keys_table(&hash, &sources));
+    }
+
+    plan.merge(plan_with_keys(config, project, hash)?);
+
+    apply_editorconfig(&mut plan, &options.output);
+
+    Ok(plan)
+}
+
+/// Ask whether to overwrite each file of `plan` that already exists in
+/// `output` with other contents, removing the ones to keep from the plan.
+fn resolve_conflicts(plan: &mut Plan, output: &Path) {
+    let choices = [
+        ('o', "overwrite"),
+        ('s', "skip"),
+        ('d', "show diff"),
+        ('a', "overwrite all"),
+    ];
+
+    for path in plan.conflicts(output) {
+        let question = format!("{} already exists, overwrite it?", path.to_string_lossy());
+
+        loop {
+            match choose(&question, &choices) {
+                Some('o') => break,
+                Some('d') => {
+                    let existing = fs::read(output.join(&path)).unwrap_or_default();
+
+                    if let Some(file) = plan.file(&path) {
+                        print!(
+                            "{}",
+                            unified_diff(&path, &existing, &file.bytes().unwrap_or_default())
+                        );
+                    }
+                }
+                Some('a') => return,
+                _ => {
+                    plan.skip(&path);
+
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Names of the custom keys declared by `project`.
+fn custom_key_names(project: &Project) -> Vec<String> {
+    match project.custom_keys {
+        Some(CustomKeys {
+            toml: Table(ref custom_keys),
+        }) => custom_keys.keys().cloned().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Name of the project rendered when checking a template.
+pub const CHECK_PROJECT_NAME: &str = "pi-template-check";
+
+/// Render `project` with a placeholder name, to make sure it can be used.
+///
+/// Like any rendering, this fails with a report if the template is broken.
+pub fn check_template(config: &Config, project: &Project) -> Result<Plan, String> {
+    plan_project(CHECK_PROJECT_NAME, config, project, &[])
+}
+
+/// Render every directory and file of `project` in memory.
+pub fn plan_project(
+    name: &str,
+    config: &Config,
+    project: &Project,
+    overrides: &[(String, String)],
+) -> Result<Plan, String> {
+    plan_with_keys(
+        config,
+        project,
+        template_keys(name, config, project, overrides)?,
+    )
+}
+
+/// Ask for the value of the variables of `project` that are secrets, which
+/// manifests and lockfiles don't record, adding the answers to `overrides`.
+///
+/// Variables `overrides` already hold aren't asked for, and nothing is asked
+/// when pi isn't interactive.
+pub fn ask_secrets(project: &Project, overrides: &mut Vec<(String, String)>) {
+    if !is_interactive() {
+        return;
+    }
+
+    for (key, variable) in prompt_order(&project.variables) {
+        if !is_secret(key, &project.secrets)
+            || overrides.iter().any(|(overridden, _)| overridden == key)
+        {
+            continue;
+        }
+
+        if let Some(value) = ask_variable(key, variable) {
+            overrides.push((key.to_string(), value));
+        }
+    }
+}
+
+/// Build the keys of `project` from `overrides` and, if `prompt` is set, ask
+/// for the value of the placeholders they leave unresolved, adding the
+/// answers to `overrides`.
+///
+/// When prompts are disabled, unresolved placeholders are an error listing
+/// them. Returns the keys along with where their value comes from.
+fn resolve_keys<'a>(
+    name: &str,
+    config: &Config,
+    project: &Project,
+    prompt: bool,
+    overrides: &mut Vec<(String, String)>,
+) -> Result<(HashBuilder<'a>, BTreeMap<String, KeySource>), String> {
+    // values given beforehand must be valid too
+    for (key, value) in overrides.iter() {
+        if let Some(variable) = project.variables.get(key) {
+            variable
+                .validate(value)
+                .map_err(|error| format!("Invalid value {:?} for {}: {}", value, key, error))?;
+        }
+    }
+
+    if prompt && is_interactive() {
+        let mut group = None;
+
+        for (key, variable) in prompt_order(&project.variables) {
+            if overrides.iter().any(|(overridden, _)| overridden == key) {
+                continue;
+            }
+
+            if variable.group.is_some() && variable.group != group {
+                println!("\n{}", variable.group.as_deref().unwrap_or_default());
+            }
+
+            group = variable.group.clone();
+
+            if let Some(value) = ask_variable(key, variable) {
+                overrides.push((key.to_string(), value));
+            }
+        }
+    }
+
+    let (mut keys, mut sources) = template_keys_with_sources(name, config, project, overrides)?;
+
+    if !prompt {
+        return Ok((keys, sources));
+    }
+
+    let mut missing = Vec::new();
+
+    for unresolved in unresolved_keys(project, &keys) {
+        // `files` is only known once files are created, but always exists,
+        // nested keys can't be given a value, and declared variables were
+        // already asked for
+        if unresolved.key == "files"
+            || unresolved.key.contains('.')
+            || keys.data.contains_key(&unresolved.key)
+            || (is_interactive() && project.variables.contains_key(&unresolved.key))
+        {
+            continue;
+        }
+
+        let location = unresolved.path.to_string_lossy().into_owned();
+
+        if !is_interactive() {
+            if !missing.iter().any(|(key, _)| *key == unresolved.key) {
+                missing.push((unresolved.key, location));
+            }
+
+            continue;
+        }
+
+        let question = format!("Value for {} (used in {}):", unresolved.key, location);
+
+        if let Some(value) = input(&question) {
+            keys = keys.insert(unresolved.key.clone(), value.clone());
+
+            sources.insert(unresolved.key.clone(), KeySource::Override);
+
+            overrides.push((unresolved.key, value));
+        }
+    }
+
+    if !missing.is_empty() {
+        let missing = missing
+            .iter()
+            .map(|(key, location)| format!("{} (used in {})", key, location))
+            .collect::<Vec<_>>();
+
+        return Err(format!(
+            "No value for the keys {}, and prompts are disabled",
+            missing.join(", ")
+        ));
+    }
+
+    Ok((keys, sources))
+}
+
+/// Ask for the value of the declared `variable` named `key` until it is
+/// valid. Returns `None` to keep the default, when the answer is empty or
+/// can't be read.
+fn ask_variable(key: &str, variable: &Variable) -> Option<String> {
+    if let Some(ref options) = variable.multi {
+        let selected = selection(variable.default.as_deref().unwrap_or_default());
+
+        let defaults = options
+            .iter()
+            .map(|option| selected.contains(option))
+            .collect::<Vec<_>>();
+
+        let mut question = variable.prompt.as_deref().unwrap_or(key).to_string();
+
+        if let Some(ref help) = variable.help {
+            question = format!("{}\n  {}", question, help);
+        }
+
+        let picked = multi_select(&question, options, &defaults)?;
+
+        return Some(
+            picked
+                .into_iter()
+                .map(|index| options[index].as_str())
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+    }
+
+    let question = variable.question(key);
+
+    loop {
+        let value = match variable.help {
+            Some(ref help) => {
+                println!("{}\n  {}", question, help);
+
+                input(">")
+            }
+            None => input(&question),
+        }
+        .filter(|value| !value.is_empty())?;
+
+        match variable.validate(&value) {
+            Ok(()) => return Some(value),
+            Err(error) => println!("Invalid value for {}: {}", key, error),
+        }
+    }
+}
+
+/// Render every directory and file of `project` in memory, with `keys`.
+///
+/// Fails with a report if a file name or a template doesn't render.
+pub fn plan_with_keys(
+    config: &Config,
+    project: &Project,
+    mut keys: HashBuilder,
+) -> Result<Plan, String> {
+    let project_files = &project.files;
+
+    let mut plan = Plan {
+        requirements: requirements(&project.requires, &project.hints),
+        entry: project
+            .entry
+            .as_ref()
+            .map(|entry| render_path(entry, &keys))
+            .transpose()?,
+        ..Plan::default()
+    };
+
+    plan.permissions = Permissions {
+        file_mode: project.file_mode.or(config.file_mode),
+        script_mode: project.script_mode.or(config.script_mode),
+        private: render_dirs(project_files.private.as_deref().unwrap_or_default(), &keys)?,
+    };
+
+    if let Some(ref directories) = project_files.directories {
+        plan.directories = render_dirs(directories, &keys)?;
+    }
+
+    // create a list of files contained in the project, and create those files.
+    // TODO should include templates/scripts/etc.
+    if let Some(ref files) = project_files.files {
+        // FIXME files need to have a newline insert in between them?
+        plan.files = render_files(files, &keys)?;
+    }
+
+    let files = file_names(&plan.files);
+
+    // create license if it was asked for
+    if let Some(license) = license_contents(config, project) {
+        plan.files.push(PlannedFile {
+            path: PathBuf::from("LICENSE"),
+            contents: render_file(&license, &keys)
+                .map_err(|error| format!("Failed to render the license: {}", error))?,
+            source: None,
+            kind: FileKind::License,
+        });
+    }
+
+    // render readme if requested
+    if project.with_readme {
+        plan.files.push(PlannedFile {
+            path: PathBuf::from("README.md"),
+            contents: render_file(includes::README, &keys)
+                .map_err(|error| format!("Failed to render the readme: {}", error))?,
+            source: None,
+            kind: FileKind::Readme,
+        });
+    }
+
+    // Make a keys for inserting stuff into templates.
+    keys = keys.insert("files", files);
+
+    // render templates
+    if let Some(ref templates) = project_files.templates {
+        plan.files.extend(render_templates(
+            &project.path,
+            &keys,
+            templates,
+            FileKind::Template,
+        )?);
+    }
+
+    // render scripts, i.e. files that should be executable.
+    if let Some(ref scripts) = project_files.scripts {
+        plan.files.extend(render_templates(
+            &project.path,
+            &keys,
+            scripts,
+            FileKind::Script,
+        )?);
+    }
+
+    let os_scripts = project_files.current_os_scripts();
+
+    plan.files.extend(render_templates(
+        &project.path,
+        &keys,
+        &os_scripts,
+        FileKind::Script,
+    )?);
+
+    // copy assets as they are
+    if let Some(ref assets) = project_files.assets {
+        plan.files
+            .extend(render_assets(&project.path, &keys, assets)?);
+    }
+
+    // drop the files and directories whose condition doesn't hold
+    if let Some(ref conditional) = project_files.conditional {
+        for (key, paths) in conditional {
+            if !is_truthy(keys.data.get(key)) {
+                for path in render_dirs(paths, &keys)? {
+                    plan.exclude(path);
+                }
+            }
+        }
+    }
+
+    plan.record_keys(&keys);
+
+    // multi-select variables are recorded as given, their options separated
+    // by commas
+    for (key, variable) in &project.variables {
+        if let (Some(_), Some(Data::Vector(picked))) = (&variable.multi, keys.data.get(key)) {
+            let picked = picked
+                .iter()
+                .filter_map(|option| match option {
+                    Data::String(option) => Some(option.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+
+            plan.keys.insert(key.clone(), picked.join(","));
+        }
+    }
+
+    plan.hooks = project
+        .hooks
+        .iter()
+        .map(|hook| render_hook(hook, &plan.keys))
+        .collect::<Result<_, _>>()?;
+
+    if let Some(ref workspace) = project.workspace {
+        plan.keys.insert(
+            workspace.members.clone(),
+            workspace.member_names(&keys).join(","),
+        );
+
+        plan.merge(plan_members(config, project, &keys)?);
+    }
+
+    Ok(plan)
+}
+
+/// Returns the text of the license requested by `project`, or by the global
+/// configuration.
+///
+/// Licenses are looked up by name in `$HOME/.pi_templates/licenses/` first,
+/// so that organizations can add their own and replace the builtin ones.
+pub fn license_contents(config: &Config, project: &Project) -> Option<Cow<'static, str>> {
+    // prefer project-specific license over global
+    let license = match project.license.as_ref().or(config.license.as_ref()) {
+        Some(license) => license,
+        None => {
+            warn!("Requested license not specified, license file not generated");
+
+            return None;
+        }
+    };
+
+    if let Some(text) = user_license(license) {
+        return Some(Cow::Owned(text));
+    }
+
+    match license {
+        License::Bsd3 => Some(Cow::Borrowed(includes::BSD3)),
+        License::Bsd => Some(Cow::Borrowed(includes::BSD)),
+        License::Mit => Some(Cow::Borrowed(includes::MIT)),
+        License::Gpl3 => Some(Cow::Borrowed(includes::GPL3)),
+        License::AllRightsReserved => Some(Cow::Borrowed(includes::ALL_RIGHTS_RESERVED)),
+        License::Custom(name) => {
+            warn!(
+                "Unknown requested license {}, add its text to ~/{}/{}/{} to use it, license file not generated",
+                name, GLOBAL_TEMPLATE_DIRECTORY, LICENSES_DIRECTORY, name
+            );
+
+            None
+        }
+    }
+}
+
+/// Parse the license `name` given on the command line, a builtin one, in any
+/// case, or one whose text is in `$HOME/.pi_templates/licenses/`.
+pub fn requested_license(name: &str) -> Result<License, String> {
+    let license = match License::from(name.to_string()) {
+        License::Custom(_) => License::from(name.to_uppercase().replace('-', "_")),
+        license => license,
+    };
+
+    match license {
+        License::Custom(_) if user_license(&License::from(name.to_string())).is_none() => {
+            Err(format!(
+                "Unknown license {}, expected BSD3, BSD, MIT, GPL3 or ALL_RIGHTS_RESERVED, or add its text to ~/{}/{}/{}",
+                name, GLOBAL_TEMPLATE_DIRECTORY, LICENSES_DIRECTORY, name
+            ))
+        }
+        License::Custom(_) => Ok(License::from(name.to_string())),
+        license => Ok(license),
+    }
+}
+
+/// Text of `license` in the licenses directory of the user, if it is there.
+fn user_license(license: &License) -> Option<String> {
+    let name = String::from(license.clone());
+
+    // the name must not reach outside of the licenses directory
+    validate_name(&name, &[]).ok()?;
+
+    let path = dirs::home_dir()?
+        .join(GLOBAL_TEMPLATE_DIRECTORY)
+        .join(LICENSES_DIRECTORY)
+        .join(&name);
+
+    if !path.is_file() {
+        return None;
+    }
+
+    match fs::read_to_string(&path) {
+        Ok(text) => Some(text),
+        Err(error) => {
+            warn!(
+                "Failed to read license {}: {}, using the builtin one if any",
+                path.to_string_lossy(),
+                error
+            );
+
+            None
+        }
+    }
+}
+
+/// Returns the time set by `$SOURCE_DATE_EPOCH`, if any. Fails if it isn't a
+/// Unix timestamp.
+pub fn source_date_epoch() -> Result<Option<DateTime<Utc>>, String> {
+    let value = match env::var(SOURCE_DATE_EPOCH_VARIABLE) {
+        Ok(value) => value,
+        Err(_) => return Ok(None),
+    };
+
+    match value
+        .trim()
+        .parse::<i64>()
+        .map(|seconds| Utc.timestamp_opt(seconds, 0))
+    {
+        Ok(LocalResult::Single(time)) => Ok(Some(time)),
+        _ => Err(format!(
+            "Invalid {} {:?}, expected a Unix timestamp",
+            SOURCE_DATE_EPOCH_VARIABLE, value
+        )),
+    }
+}
+
+/// The current time, or the one set by `$SOURCE_DATE_EPOCH` for reproducible
+/// output. An invalid `$SOURCE_DATE_EPOCH` is ignored here, rendering the
+/// keys of a template reports it.
+pub fn now() -> DateTime<Utc> {
+    source_date_epoch().ok().flatten().unwrap_or_else(Utc::now)
+}
+
+/// Format `date` with the chrono format string `format`, falling back to
+/// [`DEFAULT_DATE_FORMAT`] if it is invalid.
+pub fn format_date<Tz: TimeZone>(date: &DateTime<Tz>, format: &str) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    if StrftimeItems::new(format).any(|item| item == Item::Error) {
+        warn!(
+            "Invalid date_format {:?}, defaulting to {:?}",
+            format, DEFAULT_DATE_FORMAT
+        );
+
+        return date.format(DEFAULT_DATE_FORMAT).to_string();
+    }
+
+    date.format(format).to_string()
+}
+
+/// Build the keys available to the templates of `project`.
+///
+/// Keys are resolved from the template and global `custom_keys`, the builtin
+/// keys (project name, date, author, ...), the `overrides`, and finally the
+/// template's script if it has one.
+pub fn template_keys<'a>(
+    name: &str,
+    config: &Config,
+    project: &Project,
+    overrides: &[(String, String)],
+) -> Result<HashBuilder<'a>, String> {
+    Ok(template_keys_with_sources(name, config, project, overrides)?.0)
+}
+
+/// Like [`template_keys`], also returning where the value of each key comes
+/// from.
+pub fn template_keys_with_sources<'a>(
+    name: &str,
+    config: &Config,
+    project: &Project,
+    overrides: &[(String, String)],
+) -> Result<(HashBuilder<'a>, BTreeMap<String, KeySource>), String> {
+    let mut sources = BTreeMap::new();
+
+    let now = source_date_epoch()?.unwrap_or_else(Utc::now);
+
+    let year = now.year();
+
+    let date_format = project
+        .date_format
+        .as_deref()
+        .or(config.date_format.as_deref())
+        .unwrap_or(DEFAULT_DATE_FORMAT);
+
+    let formatted_date = format_date(&now, date_format);
+
+    // prefer project-specific license over global
+    let license = project.license.as_ref().or(config.license.as_ref());
+
     // set version
-    let version = match project_config {
+    let version = match project.config {
         Some(ProjectConfig {
             version: Some(ref version),
             ..
@@ -89,7 +986,7 @@ pub fn init_helper(
         Some(Author {
             github_username: Some(ref github_username),
             ..
-        }) => github_username,
+        }) => github_username.as_str(),
         _ => {
             warn!("No github username found, defaulting to ''");
 
@@ -98,39 +995,65 @@ pub fn init_helper(
     };
 
     // make custom_keys into a vector; prepare to insert them into the `HashBuilder`
-    let custom_keys =
-        if let Some(Table(custom_keys)) = project.custom_keys.map(|custom_keys| custom_keys.toml) {
-            Some(custom_keys)
-        } else {
-            None
-        };
+    let custom_keys = match project.custom_keys {
+        Some(CustomKeys {
+            toml: Table(ref custom_keys),
+        }) => Some(custom_keys),
+        _ => None,
+    };
 
     // make custom_keys into a vector; prepare to insert them into the `HashBuilder`
-    let custom_keys_global = if let Some(Table(custom_keys_global)) =
-        config.custom_keys.map(|custom_keys| custom_keys.toml)
-    {
-        Some(custom_keys_global)
-    } else {
-        None
+    let custom_keys_global = match config.custom_keys {
+        Some(CustomKeys {
+            toml: Table(ref custom_keys_global),
+        }) => Some(custom_keys_global),
+        _ => None,
     };
 
     // Make a hash for inserting stuff into templates.
     let mut keys = HashBuilder::new();
 
+    // the machine generating the project and random values, any other key
+    // takes precedence
+    for (key, value) in machine_keys() {
+        keys = keys.insert(key, value);
+
+        sources.insert(key.to_string(), KeySource::Builtin);
+    }
+
+    for (key, value) in random_keys(project)? {
+        keys = keys.insert(&key, value);
+
+        sources.insert(key, KeySource::Builtin);
+    }
+
     // project-specific
     if let Some(custom_keys) = custom_keys {
-        for (key, value) in &custom_keys {
+        for (key, value) in custom_keys {
             if let Some(value) = value.as_str() {
                 keys = keys.insert(key, value);
+
+                sources.insert(key.clone(), KeySource::Template);
             }
         }
     }
 
+    // defaults of the declared variables
+    for (key, variable) in &project.variables {
+        if let Some(ref default) = variable.default {
+            keys = keys.insert(key, default.as_str());
+
+            sources.insert(key.clone(), KeySource::Variable);
+        }
+    }
+
     // global
     if let Some(custom_keys) = custom_keys_global {
-        for (key, value) in &custom_keys {
+        for (key, value) in custom_keys {
             if let Some(value) = value.as_str() {
                 keys = keys.insert(key, value);
+
+                sources.insert(key.clone(), KeySource::Config);
             }
         }
     }
@@ -140,15 +1063,23 @@ pub fn init_helper(
         .insert("project", name)
         .insert("Project", name.to_capitalized())
         .insert("ProjectCamelCase", name.to_upper_camel_case())
+        .insert("project_snake", name.to_snake_case())
+        .insert("project-kebab", name.to_kebab_case())
+        .insert("PROJECT_SHOUTY", name.to_shouty_snake_case())
+        .insert("projectlower", name.to_lowercase())
         .insert("year", year)
         .insert("version", version)
         .insert("github_username", github_username)
         .insert("date", formatted_date);
 
     match config.author {
-        Some(Author { email, name, .. }) => {
-            keys = keys.insert("name", name);
-            keys = keys.insert("email", email);
+        Some(Author {
+            ref email,
+            ref name,
+            ..
+        }) => {
+            keys = keys.insert("name", name.as_str());
+            keys = keys.insert("email", email.as_str());
         }
         _ => {
             keys = keys.insert("name", "");
@@ -160,64 +1091,392 @@ pub fn init_helper(
         keys = keys.insert("license", license.to_string())
     }
 
-    // check if the directory exists and exit, if we haven't forced an overwrite.
-    if Path::new(name).exists() && !force {
-        error!(
-            "Path '{}' already exists, rerun with -f or --force to overwrite",
-            name
-        );
+    let copyright_holder = project
+        .copyright_holder
+        .as_deref()
+        .or(config.copyright_holder.as_deref())
+        .or(config.author.as_ref().map(|author| author.name.as_str()))
+        .unwrap_or_default();
+
+    // an answer can give the start year too
+    let start_year = overrides
+        .iter()
+        .rev()
+        .find(|(key, _)| key == "copyright_start_year")
+        .and_then(|(_, year)| year.trim().parse::<i32>().ok())
+        .or(project.copyright_start_year);
 
-        std::process::exit(0x0f00);
+    let copyright_years = match start_year {
+        Some(start_year) if start_year < year => format!("{}-{}", start_year, year),
+        _ => year.to_string(),
     };
 
-    // create directories
-    let _ = fs::create_dir(name);
+    keys = keys
+        .insert("copyright_holder", copyright_holder)
+        .insert("copyright_years", copyright_years);
 
-    if let Some(directories) = project_files.directories {
-        render_dirs(directories, &keys, name);
+    for key in keys.data.keys() {
+        if BUILTIN_KEYS.contains(&key.as_str()) {
+            sources.insert(key.clone(), KeySource::Builtin);
+        }
     }
 
-    // create a list of files contained in the project, and create those files.
-    // TODO should include templates/scripts/etc.
-    let files = match project_files.files {
-        // FIXME files need to have a newline insert in between them?
-        Some(files) => render_files(files, &keys, name),
-        None => VecBuilder::new(),
-    };
+    // overrides take precedence over everything else
+    for (key, value) in overrides {
+        keys = keys.insert(key, value.as_str());
 
-    // create license if it was asked for
-    if let Some(license) = license_contents {
-        render_file(license, name, "LICENSE", &keys);
+        sources.insert(key.clone(), KeySource::Override);
     }
 
-    // render readme if requested
-    if project.with_readme {
-        render_file(includes::README, name, "README.md", &keys);
+    // multi-select variables are lists, with a boolean for each option
+    for (key, variable) in &project.variables {
+        if let Some(ref options) = variable.multi {
+            let value = overrides
+                .iter()
+                .rev()
+                .find(|(overridden, _)| overridden == key)
+                .map(|(_, value)| value.as_str())
+                .or(variable.default.as_deref())
+                .unwrap_or_default();
+
+            let selected = selection(value);
+
+            let source = sources.get(key).copied().unwrap_or(KeySource::Variable);
+
+            for option in options {
+                keys = keys.insert(format!("{}_{}", key, option), selected.contains(option));
+
+                sources.insert(format!("{}_{}", key, option), source);
+            }
+
+            sources.insert(key.clone(), source);
+
+            keys = keys.insert(
+                key,
+                VecBuilder {
+                    data: selected.into_iter().map(Data::from).collect(),
+                },
+            );
+        }
     }
 
-    // Make a keys for inserting stuff into templates.
-    keys = keys.insert("files", files);
+    // the members of a workspace are a list, whatever named them
+    if let Some(ref workspace) = project.workspace {
+        if let Some(Data::String(members)) = keys.data.get(&workspace.members) {
+            let members = member_list(members);
 
-    // render templates
-    render_templates(&project.path, name, &keys, project_files.templates, false);
+            keys = keys.insert(&workspace.members, members);
+        }
+    }
 
-    // render scripts, i.e. files that should be executable.
-    render_templates(&project.path, name, &keys, project_files.scripts, true);
+    // run the template script, if any, to compute additional keys
+    if let Some(ref script) = project.script {
+        let script_path = project.path.join(script);
 
-    let version_control = project_config
-        .and_then(|project_config| project_config.version_control)
-        .or(config.version_control);
+        let before = keys
+            .data
+            .iter()
+            .map(|(key, value)| (key.clone(), format!("{:?}", value)))
+            .collect::<BTreeMap<_, _>>();
 
-    // initialize version control
-    if let Some(version_control) = version_control {
-        match version_control {
-            VersionControl::Git => git_init(name),
-            VersionControl::Hg | VersionControl::Mercurial => hg_init(name),
-            VersionControl::Pijul => pijul_init(name),
-            VersionControl::Darcs => darcs_init(name),
-            VersionControl::Unknown => warn!("Version control not yet supported, supported version control tools are git, darcs, pijul, and mercurial, ignoring...")
+        keys = run_script(&script_path, keys).map_err(|error| {
+            format!(
+                "Failed to run script {}: {}",
+                script_path.to_string_lossy(),
+                error
+            )
+        })?;
+
+        for (key, value) in &keys.data {
+            if before.get(key) != Some(&format!("{:?}", value)) {
+                sources.insert(key.clone(), KeySource::Script);
+            }
         }
     }
 
-    Ok(())
+    Ok((keys, sources))
+}
+
+/// Keys describing the machine pi runs on: its operating system and
+/// architecture, as named by Rust, the current user and the host name.
+///
+/// Unlike the other builtin keys, they give way to custom keys and variables
+/// of the same name.
+pub fn machine_keys() -> [(&'static str, String); 4] {
+    let username = env::var("USER")
+        .or_else(|_| env::var("USERNAME"))
+        .unwrap_or_default();
+
+    [
+        ("os", env::consts::OS.to_string()),
+        ("arch", env::consts::ARCH.to_string()),
+        ("username", username),
+        ("hostname", hostname()),
+    ]
+}
+
+/// Prefix of the keys holding as many random hexadecimal digits as the
+/// number following it, as in `random_hex:16`.
+const RANDOM_HEX_PREFIX: &str = "random_hex:";
+
+/// Most random hexadecimal digits a key can ask for.
+const MAX_RANDOM_HEX: usize = 1024;
+
+/// Keys with a random value, drawn again on each run: `uuid`, a version 4
+/// UUID, `random_port`, a port between 1024 and 65535, and `random_hex:N`
+/// for each number of digits N the file names and templates of `project`
+/// use outside of sections.
+///
+/// Each key has a single value for the whole project, so that a secret used
+/// in several files matches.
+pub fn random_keys(project: &Project) -> Result<Vec<(String, String)>, String> {
+    let mut bytes = random_bytes(16)?;
+
+    // version 4, variant 1
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let uuid = hex::encode(&bytes);
+
+    let port = random_bytes(2)?;
+
+    let port = 1024 + u16::from_be_bytes([port[0], port[1]]) % (u16::MAX - 1024 + 1);
+
+    let mut keys = vec![
+        (
+            "uuid".to_string(),
+            format!(
+                "{}-{}-{}-{}-{}",
+                &uuid[..8],
+                &uuid[8..12],
+                &uuid[12..16],
+                &uuid[16..20],
+                &uuid[20..]
+            ),
+        ),
+        ("random_port".to_string(), port.to_string()),
+    ];
+
+    for key in project_placeholders(project) {
+        let digits = match key
+            .strip_prefix(RANDOM_HEX_PREFIX)
+            .and_then(|digits| digits.trim().parse::<usize>().ok())
+        {
+            Some(digits) if digits <= MAX_RANDOM_HEX => digits,
+            _ => continue,
+        };
+
+        if !keys.iter().any(|(random, _)| *random == key) {
+            let mut value = hex::encode(random_bytes(digits / 2 + 1)?);
+
+            value.truncate(digits);
+
+            keys.push((key, value));
+        }
+    }
+
+    Ok(keys)
+}
+
+/// Returns `count` bytes from the random source of the system.
+///
+/// Fails if the system has none.
+fn random_bytes(count: usize) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![0; count];
+
+    getrandom::getrandom(&mut bytes)
+        .map_err(|error| format!("Couldn't draw random values: {}", error))?;
+
+    Ok(bytes)
+}
+
+/// Returns the placeholders used outside of sections by the file names and
+/// templates of `project`.
+fn project_placeholders(project: &Project) -> Vec<String> {
+    let os_scripts = Some(project.files.current_os_scripts());
+
+    let names = [
+        &project.files.files,
+        &project.files.directories,
+        &project.files.templates,
+        &project.files.scripts,
+        &project.files.assets,
+        &os_scripts,
+    ];
+
+    let mut keys = names
+        .into_iter()
+        .flatten()
+        .flatten()
+        .flat_map(|path| placeholders(&path.to_string_lossy()))
+        .collect::<Vec<_>>();
+
+    let templates = [
+        &project.files.templates,
+        &project.files.scripts,
+        &os_scripts,
+    ];
+
+    for path in templates.into_iter().flatten().flatten() {
+        // missing and broken templates are reported when generating
+        if let Ok(template) = checked_template(&project.path.join(path)) {
+            keys.extend(template.placeholders.iter().cloned());
+        }
+    }
+
+    keys
+}
+
+/// Name of the machine pi runs on, empty if it can't be found.
+fn hostname() -> String {
+    #[cfg(unix)]
+    {
+        let mut buffer = [0u8; 256];
+
+        // SAFETY: the buffer outlives the call, whose length leaves room for
+        // the terminating NUL
+        let result = unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len() - 1) };
+
+        if result == 0 {
+            let length = buffer.iter().position(|&byte| byte == 0).unwrap_or(0);
+
+            return String::from_utf8_lossy(&buffer[..length]).into_owned();
+        }
+    }
+
+    env::var("COMPUTERNAME")
+        .or_else(|_| env::var("HOSTNAME"))
+        .unwrap_or_default()
+}
+
+/// Keys every template gets.
+pub const BUILTIN_KEYS: &[&str] = &[
+    "project",
+    "Project",
+    "ProjectCamelCase",
+    "project_snake",
+    "project-kebab",
+    "PROJECT_SHOUTY",
+    "projectlower",
+    "year",
+    "version",
+    "github_username",
+    "date",
+    "name",
+    "email",
+    "license",
+    "copyright_holder",
+    "copyright_years",
+];
+
+/// Where the value of a key comes from, see [`template_keys_with_sources`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeySource {
+    /// Computed by pi, or taken from the global configuration's author,
+    /// license and version
+    Builtin,
+    /// The `custom_keys` of the global configuration
+    Config,
+    /// The `custom_keys` of the template
+    Template,
+    /// The default of a declared variable
+    Variable,
+    /// Given on the command line, in an answers file or a lockfile, or typed
+    /// when asked
+    Override,
+    /// Set by the template's script
+    Script,
+}
+
+impl std::fmt::Display for KeySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let source = match self {
+            Self::Builtin => "builtin",
+            Self::Config => "config custom_keys",
+            Self::Template => "template custom_keys",
+            Self::Variable => "variable default",
+            Self::Override => "answer",
+            Self::Script => "script",
+        };
+
+        write!(f, "{}", source)
+    }
+}
+
+/// Short description of the value of a key, lists and tables being
+/// abbreviated.
+pub fn data_summary(value: &Data) -> String {
+    match value {
+        Data::String(value) => format!("{:?}", value),
+        Data::Bool(value) => value.to_string(),
+        Data::Integer(value) => value.to_string(),
+        Data::Float(value) => value.to_string(),
+        Data::Vector(values) => {
+            let values = values
+                .iter()
+                .map(|value| match value {
+                    Data::String(value) => format!("{:?}", value),
+                    _ => "..".to_string(),
+                })
+                .collect::<Vec<_>>();
+
+            format!("[{}]", values.join(", "))
+        }
+        Data::Hash(values) => format!("{{{} keys}}", values.len()),
+        Data::Lambda(_) => "<lambda>".to_string(),
+    }
+}
+
+/// Table of the keys of `keys`, with their value and source.
+pub fn keys_table(keys: &HashBuilder, sources: &BTreeMap<String, KeySource>) -> String {
+    let mut rows = keys
+        .data
+        .iter()
+        .map(|(key, value)| {
+            let value = data_summary(value);
+
+            let source = sources
+                .get(key)
+                .map_or_else(|| "-".to_string(), KeySource::to_string);
+
+            (key.clone(), value, source)
+        })
+        .collect::<Vec<_>>();
+
+    rows.sort();
+
+    let key_width = rows
+        .iter()
+        .map(|(key, _, _)| key.len())
+        .max()
+        .unwrap_or(0)
+        .max(3);
+
+    let value_width = rows
+        .iter()
+        .map(|(_, value, _)| value.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(5);
+
+    let mut table = format!(
+        "{:key_width$}  {:value_width$}  SOURCE\n",
+        "KEY",
+        "VALUE",
+        key_width = key_width,
+        value_width = value_width
+    );
+
+    for (key, value, source) in rows {
+        table.push_str(&format!(
+            "{:key_width$}  {:value_width$}  {}\n",
+            key,
+            value,
+            source,
+            key_width = key_width,
+            value_width = value_width
+        ));
+    }
+
+    table
 }
@@ -0,0 +1,223 @@
+//! `pi validate`, linting a template before anyone generates it.
+//!
+//! Unlike generating the template, which stops at the first problem, every
+//! problem found is reported, and nothing is written.
+
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rustache::Render;
+
+use crate::render::{check_syntax, unresolved_keys};
+use crate::types::{parse_template_file, template_file, Config, Project};
+use crate::util::{template_keys, CHECK_PROJECT_NAME};
+
+/// A problem found in a template, see [`validate`].
+#[derive(Debug)]
+pub struct Problem {
+    /// File of the template the problem is in, relative to the template
+    pub path: PathBuf,
+    /// Line and column of the problem, from 1, when it is in a file's content
+    pub position: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.position {
+            Some((line, column)) => write!(
+                f,
+                "{}:{}:{}: {}",
+                self.path.to_string_lossy(),
+                line,
+                column,
+                self.message
+            ),
+            None => write!(f, "{}: {}", self.path.to_string_lossy(), self.message),
+        }
+    }
+}
+
+/// Check the template in `directory`: that its description parses, that the
+/// files it lists exist, that their mustache syntax is valid, that the
+/// defaults of its variables are accepted, and that every placeholder has a
+/// value once rendered for a placeholder project.
+///
+/// Returns the rendered diagnostic when the description doesn't parse, as
+/// nothing else can be checked then.
+pub fn validate(config: &Config, directory: &Path) -> Result<Vec<Problem>, String> {
+    let file = template_file(directory).ok_or_else(|| {
+        format!(
+            "{} doesn't hold a template description",
+            directory.to_string_lossy()
+        )
+    })?;
+
+    let content = fs::read_to_string(&file)
+        .map_err(|error| format!("Couldn't read {}: {}", file.to_string_lossy(), error))?;
+
+    let mut project: Project = parse_template_file(&file, &content)?;
+
+    project.path = directory.to_path_buf();
+
+    let description = PathBuf::from(file.file_name().unwrap_or_default());
+
+    let mut problems = Vec::new();
+
+    for (name, variable) in &project.variables {
+        if let Some(ref default) = variable.default {
+            if let Err(error) = variable.validate(default) {
+                problems.push(Problem {
+                    path: description.clone(),
+                    position: None,
+                    message: format!("default of variable `{}` is invalid: {}", name, error),
+                });
+            }
+        }
+    }
+
+    if let Some(ref script) = project.script {
+        if !directory.join(script).is_file() {
+            problems.push(Problem {
+                path: description.clone(),
+                position: None,
+                message: format!("script {} doesn't exist", script.to_string_lossy()),
+            });
+        }
+    }
+
+    let files = &project.files;
+
+    // scripts of every operating system, not only the current one
+    let os_scripts = files
+        .os_scripts
+        .iter()
+        .flat_map(|scripts| scripts.values().flatten())
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let templates = [&files.templates, &files.scripts]
+        .into_iter()
+        .flatten()
+        .flatten()
+        .chain(&os_scripts)
+        .collect::<Vec<_>>();
+
+    // files read from the template, unlike the blank `files`
+    let sources = templates
+        .iter()
+        .copied()
+        .chain(files.assets.iter().flatten())
+        .collect::<Vec<_>>();
+
+    let mut broken = Vec::new();
+
+    for path in &sources {
+        if !directory.join(path).is_file() {
+            problems.push(Problem {
+                path: description.clone(),
+                position: None,
+                message: format!("listed file {} doesn't exist", path.to_string_lossy()),
+            });
+
+            broken.push(path.to_path_buf());
+        }
+    }
+
+    for path in &templates {
+        if broken.contains(path) {
+            continue;
+        }
+
+        let source = match fs::read_to_string(directory.join(path)) {
+            Ok(source) => source,
+            Err(error) => {
+                problems.push(Problem {
+                    path: path.to_path_buf(),
+                    position: None,
+                    message: format!("can't be read as text: {}", error),
+                });
+
+                broken.push(path.to_path_buf());
+
+                continue;
+            }
+        };
+
+        if let Err(error) = check_syntax(&source) {
+            let before = &source[..error.offset.min(source.len())];
+
+            problems.push(Problem {
+                path: path.to_path_buf(),
+                position: Some((
+                    before.matches('\n').count() + 1,
+                    before
+                        .rsplit('\n')
+                        .next()
+                        .unwrap_or_default()
+                        .chars()
+                        .count()
+                        + 1,
+                )),
+                message: error.message,
+            });
+
+            broken.push(path.to_path_buf());
+        }
+    }
+
+    // rendering broken templates would stop at the first one
+    if !broken.is_empty() {
+        return Ok(problems);
+    }
+
+    let keys = match template_keys(CHECK_PROJECT_NAME, config, &project, &[]) {
+        Ok(keys) => keys,
+        Err(error) => {
+            problems.push(Problem {
+                path: description,
+                position: None,
+                message: error,
+            });
+
+            return Ok(problems);
+        }
+    };
+
+    for unresolved in unresolved_keys(&project, &keys) {
+        // variables without a default are asked for
+        if project.variables.contains_key(&unresolved.key) {
+            continue;
+        }
+
+        let location = if unresolved.in_path {
+            "name"
+        } else {
+            "content"
+        };
+
+        problems.push(Problem {
+            path: unresolved.path,
+            position: None,
+            message: format!(
+                "`{{{{{}}}}}` in its {} has no value",
+                unresolved.key, location
+            ),
+        });
+    }
+
+    for path in templates {
+        let source = fs::read_to_string(directory.join(path)).unwrap_or_default();
+
+        if let Err(error) = keys.render(&source, &mut Vec::new()) {
+            problems.push(Problem {
+                path: path.clone(),
+                position: None,
+                message: format!("failed to render: {}", error),
+            });
+        }
+    }
+
+    Ok(problems)
+}
@@ -0,0 +1,153 @@
+//! Variables declared by templates in their `[variables]` table.
+//!
+//! Declared variables are asked for when generating a project, and their
+//! values, whether typed, read from an answers file or given on the command
+//! line, must pass the variable's constraints.
+//!
+//! Multi-select variables are exposed to templates as the list of picked
+//! options, along with a `<variable>_<option>` boolean for each option.
+
+use std::collections::BTreeMap;
+
+use regex::Regex;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Builtin checks for common kinds of values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Validator {
+    Email,
+    Url,
+    Semver,
+}
+
+impl Validator {
+    fn check(self, value: &str) -> Result<(), String> {
+        let valid = match self {
+            // anything more precise rejects valid addresses
+            Self::Email => value.split_once('@').is_some_and(|(user, domain)| {
+                !user.is_empty() && domain.contains('.') && !value.contains(char::is_whitespace)
+            }),
+            Self::Url => Url::parse(value).is_ok(),
+            Self::Semver => Version::parse(value).is_ok(),
+        };
+
+        if valid {
+            Ok(())
+        } else {
+            let expected = match self {
+                Self::Email => "an email address",
+                Self::Url => "an absolute url",
+                Self::Semver => "a semantic version, such as 1.0.0",
+            };
+
+            Err(format!("expected {}", expected))
+        }
+    }
+}
+
+/// A variable of a template, as declared in `[variables.<name>]`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Variable {
+    /// Question asked for the value, defaults to the variable's name
+    pub prompt: Option<String>,
+    pub default: Option<String>,
+    /// Regular expression the whole value must match
+    pub pattern: Option<String>,
+    pub validator: Option<Validator>,
+    /// Options any subset of which can be picked, the value being the picked
+    /// options separated by commas
+    pub multi: Option<Vec<String>>,
+    /// Explanation shown beneath the question
+    pub help: Option<String>,
+    /// Variables are asked for by increasing order, the ones without an order
+    /// last, then by name
+    pub order: Option<i64>,
+    /// Heading of the questions asked together with this one
+    pub group: Option<String>,
+}
+
+impl Variable {
+    /// Check that `value` satisfies the constraints of the variable.
+    pub fn validate(&self, value: &str) -> Result<(), String> {
+        if let Some(ref options) = self.multi {
+            return match selection(value)
+                .into_iter()
+                .find(|picked| !options.contains(picked))
+            {
+                Some(picked) => Err(format!(
+                    "unknown option {:?}, expected some of {}",
+                    picked,
+                    options.join(", ")
+                )),
+                None => Ok(()),
+            };
+        }
+
+        if let Some(ref pattern) = self.pattern {
+            let regex = Regex::new(pattern)
+                .map_err(|error| format!("invalid pattern {:?}: {}", pattern, error))?;
+
+            if !regex.is_match(value) {
+                return Err(format!("expected a value matching {:?}", pattern));
+            }
+        }
+
+        match self.validator {
+            Some(validator) => validator.check(value),
+            None => Ok(()),
+        }
+    }
+
+    /// Question asked for the value of the variable `name`.
+    pub fn question(&self, name: &str) -> String {
+        let question = self.prompt.as_deref().unwrap_or(name);
+
+        match self.default {
+            Some(ref default) => format!("{} [{}]:", question, default),
+            None => format!("{}:", question),
+        }
+    }
+}
+
+/// Returns the variables in the order they are asked for: by `order` then
+/// name, each group being asked at the position of its first variable.
+pub fn prompt_order(variables: &BTreeMap<String, Variable>) -> Vec<(&str, &Variable)> {
+    let mut sorted = variables
+        .iter()
+        .map(|(name, variable)| (name.as_str(), variable))
+        .collect::<Vec<_>>();
+
+    sorted.sort_by_key(|(name, variable)| (variable.order.unwrap_or(i64::MAX), *name));
+
+    let mut ordered: Vec<(&str, &Variable)> = Vec::with_capacity(sorted.len());
+
+    for (name, variable) in &sorted {
+        if ordered.iter().any(|(asked, _)| asked == name) {
+            continue;
+        }
+
+        match variable.group {
+            Some(ref group) => ordered.extend(
+                sorted
+                    .iter()
+                    .filter(|(_, other)| other.group.as_ref() == Some(group)),
+            ),
+            None => ordered.push((name, variable)),
+        }
+    }
+
+    ordered
+}
+
+/// Options picked in the value of a multi-select variable.
+pub fn selection(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|option| !option.is_empty())
+        .map(str::to_string)
+        .collect()
+}
@@ -0,0 +1,223 @@
+//! Detecting the drift of a generated project from its template.
+
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use walkdir::WalkDir;
+
+use crate::constants::{
+    ANSWERS_FILENAME, LOCKFILE_FILENAME, MANIFEST_DIRECTORY, VERSION_CONTROL_DIRECTORIES,
+};
+use crate::dependencies::{resolve_dependencies, Dependency};
+use crate::diff::unified_diff;
+use crate::fetch::{fetch_template, FetchedTemplate};
+use crate::http::{configured_fetcher, HttpFetcher};
+use crate::manifest::{Manifest, TemplateSource};
+use crate::plan::Plan;
+use crate::types::{is_template, Config, Project};
+use crate::util::{ask_secrets, plan_generation, InitOptions};
+
+/// A difference between a generated project and its template.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Drift {
+    /// The file was generated, but its content changed since
+    Modified(PathBuf),
+    /// The file was generated, but doesn't exist anymore
+    Deleted(PathBuf),
+    /// The file wasn't generated by pi
+    Added(PathBuf),
+}
+
+impl Display for Drift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Modified(path) => write!(f, "modified: {}", path.to_string_lossy()),
+            Self::Deleted(path) => write!(f, "deleted:  {}", path.to_string_lossy()),
+            Self::Added(path) => write!(f, "added:    {}", path.to_string_lossy()),
+        }
+    }
+}
+
+/// The template of a generated project rendered again, see [`rerender`].
+#[derive(Debug)]
+pub struct Rerendered {
+    /// Files of the template and of its dependencies as rendered now
+    pub plan: Plan,
+    pub project: Project,
+    pub template: FetchedTemplate,
+    /// Kept for the assets of `plan` to be readable
+    pub dependencies: Vec<Dependency>,
+}
+
+impl Rerendered {
+    /// Keys the template or one of its dependencies lists as secrets.
+    pub fn secrets(&self) -> Vec<String> {
+        self.dependencies
+            .iter()
+            .map(|dependency| &dependency.project)
+            .chain([&self.project])
+            .flat_map(|project| project.secrets.iter().cloned())
+            .collect()
+    }
+}
+
+/// Re-render the template of the project located in `project_dir` with the
+/// answers recorded in its manifest, as it would be generated now.
+pub async fn rerender<H: AsRef<Path>, P: AsRef<Path>>(
+    home: H,
+    project_dir: P,
+    config: &Config,
+) -> Result<Rerendered, Box<dyn std::error::Error>> {
+    let manifest = Manifest::from_project(&project_dir)?;
+
+    let fetcher = configured_fetcher(config).map_err(|error| error.to_string())?;
+
+    rerender_from(
+        home,
+        project_dir,
+        &manifest,
+        &manifest.source,
+        config,
+        fetcher.as_ref(),
+    )
+    .await
+}
+
+/// Render the template fetched from `source` again for the project located
+/// in `project_dir`, generated as `manifest` records, after its dependencies
+/// as `pi new` does. Secrets, which the manifest doesn't record, are asked
+/// for again.
+pub async fn rerender_from<H: AsRef<Path>, P: AsRef<Path>>(
+    home: H,
+    project_dir: P,
+    manifest: &Manifest,
+    source: &TemplateSource,
+    config: &Config,
+    fetcher: &dyn HttpFetcher,
+) -> Result<Rerendered, Box<dyn std::error::Error>> {
+    let template = fetch_template(source, config, fetcher).await?;
+
+    if !is_template(template.path()) {
+        return Err(format!("Template {} can't be found anymore", source).into());
+    }
+
+    let project = template.project(source);
+
+    let dependencies = resolve_dependencies(home, config, source, &project, fetcher).await?;
+
+    let mut overrides = manifest.overrides();
+
+    for template in dependencies
+        .iter()
+        .map(|dependency| &dependency.project)
+        .chain([&project])
+    {
+        ask_secrets(template, &mut overrides);
+    }
+
+    let options = InitOptions {
+        output: project_dir.as_ref().to_path_buf(),
+        dependencies,
+        ..Default::default()
+    };
+
+    let plan = plan_generation(&manifest.name, config, &project, &options, &mut overrides)?;
+
+    Ok(Rerendered {
+        plan,
+        project,
+        template,
+        dependencies: options.dependencies,
+    })
+}
+
+/// Re-render the template of the project located in `project_dir` with the
+/// answers recorded in its manifest, and compare the result with the files
+/// currently in the project.
+pub async fn verify<H: AsRef<Path>, P: AsRef<Path>>(
+    home: H,
+    project_dir: P,
+    config: &Config,
+) -> Result<Vec<Drift>, Box<dyn std::error::Error>> {
+    let project_dir = project_dir.as_ref();
+
+    let rerendered = rerender(home, project_dir, config).await?;
+
+    let plan = &rerendered.plan;
+
+    let mut drifts = Vec::new();
+
+    for file in &plan.files {
+        match fs::read(project_dir.join(&file.path)) {
+            Ok(contents) if file.bytes().is_ok_and(|planned| contents == *planned) => {}
+            Ok(_) => drifts.push(Drift::Modified(file.path.clone())),
+            Err(_) => drifts.push(Drift::Deleted(file.path.clone())),
+        }
+    }
+
+    let planned: BTreeSet<&Path> = plan.files.iter().map(|file| file.path.as_path()).collect();
+
+    for path in project_files(project_dir) {
+        // the lockfile and answers are pi's own, like the manifest
+        if !planned.contains(path.as_path())
+            && path != Path::new(LOCKFILE_FILENAME)
+            && path != Path::new(ANSWERS_FILENAME)
+        {
+            drifts.push(Drift::Added(path));
+        }
+    }
+
+    drifts.sort();
+
+    Ok(drifts)
+}
+
+/// List the files of the project in `root`, relative to it, skipping version
+/// control directories and pi's own files.
+pub fn project_files(root: &Path) -> Vec<PathBuf> {
+    WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0
+                || !VERSION_CONTROL_DIRECTORIES
+                    .iter()
+                    .chain(&[MANIFEST_DIRECTORY])
+                    .any(|directory| entry.file_name() == *directory)
+        })
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.path().strip_prefix(root).ok().map(Path::to_path_buf))
+        .collect()
+}
+
+/// Unified diff of every file of the project located in `project_dir` that
+/// re-rendering its template would change, files it would create back
+/// included. Files added to the project since are left out.
+pub async fn project_diff<H: AsRef<Path>, P: AsRef<Path>>(
+    home: H,
+    project_dir: P,
+    config: &Config,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let project_dir = project_dir.as_ref();
+
+    let rerendered = rerender(home, project_dir, config).await?;
+
+    let plan = &rerendered.plan;
+
+    let mut diff = String::new();
+
+    for file in &plan.files {
+        let planned = file.bytes()?;
+
+        let existing = fs::read(project_dir.join(&file.path)).unwrap_or_default();
+
+        if existing.as_slice() != &*planned {
+            diff.push_str(&unified_diff(&file.path, &existing, &planned));
+        }
+    }
+
+    Ok(diff)
+}
@@ -0,0 +1,90 @@
+//! Warnings deferred to the end of a command.
+//!
+//! While a project is generated, warnings are collected by [`WarningsLayer`]
+//! instead of being logged among the progress output, and reported together
+//! with [`summary`] once it is done. If an error aborts the command, the
+//! collected warnings are reported before it.
+
+use std::fmt::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Metadata, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+static DEFERRED: AtomicBool = AtomicBool::new(false);
+
+static WARNINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Start or stop deferring warnings.
+pub fn defer_warnings(defer: bool) {
+    DEFERRED.store(defer, Ordering::Relaxed);
+}
+
+/// Returns `true` when the event described by `metadata` is a warning being
+/// deferred, which must not be logged right away.
+pub fn is_deferred(metadata: &Metadata) -> bool {
+    *metadata.level() == Level::WARN && DEFERRED.load(Ordering::Relaxed)
+}
+
+/// Returns the warnings collected so far, forgetting them.
+pub fn take_warnings() -> Vec<String> {
+    WARNINGS
+        .lock()
+        .map(|mut warnings| std::mem::take(&mut *warnings))
+        .unwrap_or_default()
+}
+
+/// Numbered list of `warnings`, empty if there are none.
+pub fn summary(warnings: &[String]) -> String {
+    let mut summary = String::new();
+
+    if warnings.is_empty() {
+        return summary;
+    }
+
+    let _ = writeln!(summary, "Warnings:");
+
+    for (index, warning) in warnings.iter().enumerate() {
+        let _ = writeln!(summary, "{:>3}. {}", index + 1, warning);
+    }
+
+    summary
+}
+
+/// Layer collecting the deferred warnings.
+#[derive(Debug, Default)]
+pub struct WarningsLayer;
+
+impl<S: Subscriber> Layer<S> for WarningsLayer {
+    fn on_event(&self, event: &Event<'_>, _context: Context<'_, S>) {
+        if is_deferred(event.metadata()) {
+            let mut message = Message::default();
+
+            event.record(&mut message);
+
+            // templates rendered together, like dependencies, often raise
+            // the same warning
+            if let Ok(mut warnings) = WARNINGS.lock() {
+                if !warnings.contains(&message.0) {
+                    warnings.push(message.0);
+                }
+            }
+        } else if *event.metadata().level() == Level::ERROR {
+            eprint!("{}", summary(&take_warnings()));
+        }
+    }
+}
+
+/// Message of an event.
+#[derive(Default)]
+struct Message(String);
+
+impl Visit for Message {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.0 = format!("{:?}", value);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! `pi watch`, generating a template again each time it changes, while it is
+//! being written.
+//!
+//! Each generation runs `pi new` in a child process, so that a broken
+//! template reports its error and the watch goes on.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::manifest::Manifest;
+use crate::piignore::template_files;
+
+/// How often the template is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+/// Modification time and length of every file of a template, by path.
+type Snapshot = BTreeMap<PathBuf, (Option<SystemTime>, u64)>;
+
+/// Where and how a template is generated on each change.
+#[derive(Debug)]
+pub struct Watch {
+    /// Directory of the template
+    pub template: PathBuf,
+    /// Scratch directory the project is generated in, removed before each
+    /// generation
+    pub sandbox: PathBuf,
+    /// Answers file passed to `pi new`
+    pub answers: Option<PathBuf>,
+}
+
+impl Watch {
+    /// Check that the sandbox can be removed before each generation: it
+    /// doesn't exist or pi generated it.
+    pub fn check_sandbox(&self) -> Result<(), String> {
+        let is_empty =
+            fs::read_dir(&self.sandbox).is_ok_and(|mut entries| entries.next().is_none());
+
+        if !self.sandbox.exists() || is_empty || Manifest::path(&self.sandbox).is_file() {
+            Ok(())
+        } else {
+            Err(format!(
+                "{} already exists and wasn't generated by pi, pick another sandbox as it is removed on each change",
+                self.sandbox.to_string_lossy()
+            ))
+        }
+    }
+
+    /// Generate the template, then again each time one of its files changes,
+    /// until the process is stopped.
+    pub fn run(&self) -> io::Result<()> {
+        let mut snapshot = self.snapshot();
+
+        self.generate()?;
+
+        loop {
+            thread::sleep(POLL_INTERVAL);
+
+            let current = self.snapshot();
+
+            if current == snapshot {
+                continue;
+            }
+
+            // editors often write a file in several steps, wait for them
+            thread::sleep(POLL_INTERVAL);
+
+            snapshot = self.snapshot();
+
+            println!("\nTemplate changed, generating it again");
+
+            self.generate()?;
+        }
+    }
+
+    /// Files of the template, the sandbox excluded if it is inside of it.
+    fn snapshot(&self) -> Snapshot {
+        let sandbox = self.sandbox.strip_prefix(&self.template).ok();
+
+        template_files(&self.template)
+            .into_iter()
+            .filter(|path| sandbox.is_none_or(|sandbox| !path.starts_with(sandbox)))
+            .map(|path| {
+                let stamp = fs::metadata(self.template.join(&path))
+                    .map(|metadata| (metadata.modified().ok(), metadata.len()))
+                    .unwrap_or_default();
+
+                (path, stamp)
+            })
+            .collect()
+    }
+
+    /// Generate the template in the sandbox with `pi new`, its errors being
+    /// printed as they happen.
+    fn generate(&self) -> io::Result<()> {
+        if self.sandbox.exists() {
+            fs::remove_dir_all(&self.sandbox)?;
+        }
+
+        let name = self
+            .sandbox
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid sandbox"))?;
+
+        let parent = self
+            .sandbox
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+
+        fs::create_dir_all(parent)?;
+
+        let mut pi = Command::new(env::current_exe()?);
+
+        pi.current_dir(parent)
+            .arg("new")
+            .arg(&self.template)
+            .arg(name)
+            .arg("--no-input");
+
+        if let Some(ref answers) = self.answers {
+            pi.arg("--answers").arg(answers);
+        }
+
+        let status = pi.status()?;
+
+        // some errors exit with a status truncated to 0, but nothing is
+        // generated when rendering fails
+        if status.success() && self.sandbox.exists() {
+            println!("Generated {}", self.sandbox.to_string_lossy());
+        } else {
+            println!("Generation failed, waiting for the template to change");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+//! Workspace templates, generating several subprojects along with their
+//! root.
+//!
+//! A template with a `[workspace]` table names its members with a key, a
+//! list or a comma separated string, and generates each of them from a
+//! member template bundled with it. The root template sees the members as a
+//! list, to reference them in its manifest, and each member is rendered with
+//! its own name as the project name.
+
+use std::path::{Path, PathBuf};
+
+use rustache::{Data, HashBuilder, VecBuilder};
+use serde::Deserialize;
+
+use crate::names::validate_name;
+use crate::plan::Plan;
+use crate::render::render_path;
+use crate::types::{Config, Project};
+use crate::util::{plan_with_keys, template_keys, BUILTIN_KEYS};
+
+/// Key holding the name of the workspace in the keys of its members.
+pub const WORKSPACE_KEY: &str = "workspace";
+
+/// Key holding the name of the member in [`Workspace::path`].
+const MEMBER_KEY: &str = "member";
+
+/// The `[workspace]` table of a template.
+#[derive(Debug, Deserialize)]
+pub struct Workspace {
+    /// Key whose value names the members
+    pub members: String,
+    /// Template generating each member, relative to the workspace template
+    pub template: PathBuf,
+    /// Where each member is generated in the project, rendered with the
+    /// `member` key, defaults to the name of the member
+    pub path: Option<String>,
+}
+
+impl Workspace {
+    /// Names of the members in `keys`, once the members key was made a list
+    /// by [`member_list`].
+    pub fn member_names(&self, keys: &HashBuilder) -> Vec<String> {
+        match keys.data.get(&self.members) {
+            Some(Data::Vector(members)) => members
+                .iter()
+                .filter_map(|member| match member {
+                    Data::String(member) => Some(member.clone()),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Returns the members named by `value`, separated by commas or whitespace.
+pub fn member_list<'a>(value: &str) -> VecBuilder<'a> {
+    VecBuilder {
+        data: value
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|member| !member.is_empty())
+            .map(|member| Data::from(member.to_string()))
+            .collect(),
+    }
+}
+
+/// Render every member of the workspace `project`, whose keys are `keys`,
+/// into a plan holding them at their path in the workspace.
+///
+/// Members get the text keys of the workspace, except for the builtin ones,
+/// along with its name as `workspace`. Fails with a report if the member
+/// template is missing or a member name is invalid.
+pub fn plan_members(
+    config: &Config,
+    project: &Project,
+    keys: &HashBuilder,
+) -> Result<Plan, String> {
+    let mut plan = Plan::default();
+
+    let workspace = match project.workspace {
+        Some(ref workspace) => workspace,
+        None => return Ok(plan),
+    };
+
+    let template = project.path.join(&workspace.template);
+
+    let member = Project::read(&project.path, &template)?;
+
+    let mut overrides = keys
+        .data
+        .iter()
+        .filter(|(key, _)| !BUILTIN_KEYS.contains(&key.as_str()) && **key != workspace.members)
+        .filter_map(|(key, value)| match value {
+            Data::String(value) => Some((key.clone(), value.clone())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    if let Some(Data::String(name)) = keys.data.get("project") {
+        overrides.push((WORKSPACE_KEY.to_string(), name.clone()));
+    }
+
+    for name in workspace.member_names(keys) {
+        validate_name(&name, &member.name_rules)
+            .map_err(|error| format!("Invalid workspace member {:?}: {}", name, error))?;
+
+        let path = match workspace.path {
+            Some(ref path) => {
+                render_path(path, &HashBuilder::new().insert(MEMBER_KEY, name.as_str()))?
+            }
+            None => PathBuf::from(&name),
+        };
+
+        let mut member_plan = plan_with_keys(
+            config,
+            &member,
+            template_keys(&name, config, &member, &overrides)?,
+        )?;
+
+        nest(&mut member_plan, &path);
+
+        plan.merge(member_plan);
+    }
+
+    Ok(plan)
+}
+
+/// Move everything `plan` generates under `prefix`, creating it first.
+fn nest(plan: &mut Plan, prefix: &Path) {
+    let mut directories = prefix
+        .ancestors()
+        .filter(|ancestor| !ancestor.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+        .collect::<Vec<_>>();
+
+    directories.reverse();
+
+    directories.extend(
+        plan.directories
+            .iter()
+            .map(|directory| prefix.join(directory)),
+    );
+
+    plan.directories = directories;
+
+    for file in &mut plan.files {
+        file.path = prefix.join(&file.path);
+    }
+
+    for path in &mut plan.permissions.private {
+        *path = prefix.join(&*path);
+    }
+
+    // only the workspace's entry is opened, and its keys recorded
+    plan.entry = None;
+    plan.keys.clear();
+}